@@ -0,0 +1,76 @@
+// 对比每次新建reqwest::Client与复用RestClient连接池的延迟
+// cargo bench --bench rest_client
+use edp::rest::rclient::RestClient;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const ROUNDS: usize = 200;
+// 新连接建立时的额外延迟, 模拟TLS握手的往返
+const HANDSHAKE_DELAY: Duration = Duration::from_millis(2);
+
+async fn start_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                tokio::time::sleep(HANDSHAKE_DELAY).await;
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    match socket.read(&mut chunk).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                    // 请求都是不带body的GET
+                    while let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        buf.drain(..pos + 4);
+                        let resp = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}";
+                        if socket.write_all(resp.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn report(name: &str, samples: &mut [Duration]) {
+    samples.sort();
+    let total: Duration = samples.iter().sum();
+    println!(
+        "{:<16} mean {:>10?}  p50 {:>10?}  p99 {:>10?}",
+        name,
+        total / samples.len() as u32,
+        samples[samples.len() / 2],
+        samples[samples.len() * 99 / 100],
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let base_url = start_server().await;
+    let url = format!("{}/api/v3/ping", base_url);
+
+    let mut fresh = Vec::with_capacity(ROUNDS);
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let client = reqwest::Client::new();
+        client.get(&url).send().await.unwrap().text().await.unwrap();
+        fresh.push(start.elapsed());
+    }
+
+    let rest_client = RestClient::new(base_url);
+    let mut pooled = Vec::with_capacity(ROUNDS);
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        rest_client.get(url.clone()).await.unwrap();
+        pooled.push(start.elapsed());
+    }
+
+    report("new client", &mut fresh);
+    report("pooled client", &mut pooled);
+}
@@ -1,10 +1,9 @@
+use edp::binance::spot::BinanceSpot;
+use edp::traits::MarketDataAPI;
+
 #[tokio::main]
 async fn main() {
-	println!("hello");
-	let binance = edp::binance::spot::BinanceSpot {
-        base_url: "https://dapi.binance.com",
-        credential: None
-    };
-    // let data = binance.get_klines(symbol, "1m", None, None, Some(10)).await.expect("get price error");
-    // println!("{:?}", data)
-}
\ No newline at end of file
+    let binance = BinanceSpot::builder().build().expect("build client error");
+    let data = binance.get_klines("BTCUSDT", "1m", None, None, Some(10)).await.expect("get price error");
+    println!("{:?}", data)
+}
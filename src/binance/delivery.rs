@@ -0,0 +1,544 @@
+use crate::binance::spot::{Filter, RawKResp, RawTrade};
+use crate::error::{EdpError, Result};
+use crate::order::{order_id_params, OrderRequest};
+use crate::model::{
+    Balance, ContractType, FuturesBalance, KData, Market, OpenInterest, Order, OrderBook, OrderResp, OrderStatus, OrderType, PositionSide, PremiumIndex,
+    RateLimit, Side, SymbolFilters, SymbolInfo, Ticker, TimeInForce, Trade, WorkingType,
+};
+use crate::rest::limiter::RateLimiter;
+use crate::rest::rclient::RestClient;
+use crate::traits::{AccountAPI, DeliveryAPI, MarketDataAPI, TradingAPI};
+use crate::utils::de2float;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// 币本位合约, 以币为保证金, 下单数量是合约张数而不是币的数量
+// 每张合约的面值为 contractSize 美元, 例如 BTCUSD 一张 100 USD
+
+const TIME_END_POINT: &str = "/dapi/v1/time";
+
+pub struct BinanceDelivery {
+    rest_client: RestClient,
+}
+
+impl BinanceDelivery {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            rest_client: RestClient::new(base_url).with_time_sync(TIME_END_POINT),
+        }
+    }
+
+    pub fn with_key(base_url: String, keys: (String, String)) -> Self {
+        Self {
+            rest_client: RestClient::with_key(base_url, keys).with_time_sync(TIME_END_POINT),
+        }
+    }
+
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        // 额度为空时第一次请求前从 exchangeInfo 读取
+        self.rest_client = self.rest_client.with_limiter(limiter).with_rate_limits("/dapi/v1/exchangeInfo");
+        self
+    }
+
+    // 带合约面值和交割时间的交易对信息
+    pub async fn get_contracts(&self) -> Result<Vec<ContractInfo>> {
+        let end_point = "/dapi/v1/exchangeInfo";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), false)?;
+        let resp = self.rest_client.get(url).await?;
+        let raw: DPExchangeInfo = serde_json::from_str(&resp)?;
+        Ok(raw.symbols)
+    }
+
+    pub async fn get_premium_index(&self, symbol: &str) -> Result<PremiumIndex> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/dapi/v1/premiumIndex";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        // 指定symbol时也返回数组
+        let mut indexes: Vec<PremiumIndex> = serde_json::from_str(&resp)?;
+        indexes.pop().ok_or_else(|| empty_response(end_point))
+    }
+
+    // pair 为空时返回所有持仓, 例如 BTCUSD 返回该标的的永续和交割合约持仓
+    pub async fn get_positions(&self, pair: Option<&str>) -> Result<Vec<DeliveryPosition>> {
+        let mut params = BTreeMap::new();
+        if let Some(p) = pair {
+            params.insert("pair".to_string(), p.to_string());
+        }
+        let end_point = "/dapi/v1/positionRisk";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let positions: Vec<DeliveryPosition> = serde_json::from_str(&resp)?;
+        Ok(positions)
+    }
+}
+
+fn empty_response(end_point: &str) -> EdpError {
+    EdpError::Http {
+        status: StatusCode::OK,
+        body: format!("empty response from {}", end_point),
+    }
+}
+
+#[async_trait]
+impl MarketDataAPI for BinanceDelivery {
+    async fn ping(&self) -> Result<()> {
+        let end_point = "/dapi/v1/ping";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), false)?;
+        self.rest_client.get(url).await?;
+        Ok(())
+    }
+
+    async fn get_symbols(&self) -> Result<Vec<SymbolInfo>> {
+        let contracts = self.get_contracts().await?;
+        Ok(contracts.into_iter().map(SymbolInfo::from).collect())
+    }
+
+    async fn get_ticker(&self, symbol: &str) -> Result<Ticker> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/dapi/v1/ticker/bookTicker";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        // 指定symbol时也返回数组
+        let mut tickers: Vec<Ticker> = serde_json::from_str(&resp)?;
+        tickers.pop().ok_or_else(|| empty_response(end_point))
+    }
+
+    async fn get_order_book(&self, symbol: &str, limit: Option<u64>) -> Result<OrderBook> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        if let Some(l) = limit {
+            params.insert("limit".to_string(), l.to_string());
+        }
+        let end_point = "/dapi/v1/depth";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let ob: OrderBook = serde_json::from_str(&resp)?;
+        Ok(ob)
+    }
+
+    async fn get_trades(&self, symbol: &str, limit: Option<u64>) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        if let Some(l) = limit {
+            params.insert("limit".to_string(), l.to_string());
+        }
+        let end_point = "/dapi/v1/trades";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let trades: Vec<RawTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
+    // vol 为合约张数, turnover 为标的币数量
+    async fn get_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<KData>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("interval".to_string(), interval.to_string());
+        if let Some(start_ts) = start_time {
+            params.insert("startTime".to_string(), start_ts.to_string());
+        }
+        if let Some(end_ts) = end_time {
+            params.insert("endTime".to_string(), end_ts.to_string());
+        }
+        if let Some(lim) = limit {
+            params.insert("limit".to_string(), lim.to_string());
+        }
+        let end_point = "/dapi/v1/klines";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let kline: Vec<RawKResp> = serde_json::from_str(&resp)?;
+        Ok(kline.iter().map(|rkp| KData::from(*rkp)).collect())
+    }
+
+    fn kline_page_limit(&self) -> u64 {
+        1500
+    }
+}
+
+#[async_trait]
+impl TradingAPI for BinanceDelivery {
+    // order.quantity 为合约张数, 可用 ContractInfo::coin_to_contracts 换算
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderResp> {
+        let end_point = "/dapi/v1/order";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, order.to_params(), true)?;
+        let resp = self.rest_client.post_sign(url).await?;
+        let resp_typed: DPOrderResp = serde_json::from_str(&resp)?;
+        Ok(OrderResp::from(resp_typed))
+    }
+
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order> {
+        let params = order_id_params(symbol, order_id, client_order_id)?;
+        let end_point = "/dapi/v1/order";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.delete_sign(url).await?;
+        let resp_typed: DPOrderResp = serde_json::from_str(&resp)?;
+        Ok(Order::from(resp_typed))
+    }
+
+    async fn query_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order> {
+        let params = order_id_params(symbol, order_id, client_order_id)?;
+        let end_point = "/dapi/v1/order";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let resp_typed: DPOrderResp = serde_json::from_str(&resp)?;
+        Ok(Order::from(resp_typed))
+    }
+}
+
+#[async_trait]
+impl AccountAPI for BinanceDelivery {
+    async fn get_balances(&self) -> Result<Vec<Balance>> {
+        let end_point = "/dapi/v1/balance";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let raw: Vec<FuturesBalance> = serde_json::from_str(&resp)?;
+        Ok(raw
+            .into_iter()
+            .map(Balance::from)
+            .filter(|b| b.free != 0. || b.locked != 0.)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl DeliveryAPI for BinanceDelivery {
+    // 单位为张
+    async fn get_open_interest(&self, symbol: &str) -> Result<OpenInterest> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/dapi/v1/openInterest";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let oi: OpenInterest = serde_json::from_str(&resp)?;
+        Ok(oi)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DPExchangeInfo {
+    pub rate_limits: Vec<RateLimit>,
+    pub symbols: Vec<ContractInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractInfo {
+    pub symbol: String,
+    pub pair: String,
+    pub contract_type: ContractType,
+    // 交割时间, 永续合约为 4133404800000
+    pub delivery_date: i64,
+    pub onboard_date: i64,
+    pub contract_status: String,
+    // 每张合约的面值, 单位为报价资产(USD)
+    pub contract_size: u64,
+    pub margin_asset: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub price_precision: u8,
+    pub quantity_precision: u8,
+    pub base_asset_precision: u8,
+    pub quote_precision: u8,
+    pub filters: Vec<Filter>,
+}
+
+impl ContractInfo {
+    pub fn is_perpetual(&self) -> bool {
+        matches!(
+            self.contract_type,
+            ContractType::Perpetual | ContractType::PerpetualDelivering
+        )
+    }
+
+    // 合约张数对应的币数量
+    pub fn contracts_to_coin(&self, contracts: u64, price: f64) -> f64 {
+        (contracts * self.contract_size) as f64 / price
+    }
+
+    // 币数量按价格换算为合约张数, 不足一张的部分舍去
+    pub fn coin_to_contracts(&self, coin_qty: f64, price: f64) -> u64 {
+        // 容忍浮点误差, 避免 1.0 / 0.01 = 99.99999999999999 被舍去为99
+        (coin_qty * price / self.contract_size as f64 + 1e-9).floor() as u64
+    }
+}
+
+// stepSize 为 1, OrderRequest::build 会把数量取整为整张
+impl From<ContractInfo> for SymbolInfo {
+    fn from(raw: ContractInfo) -> Self {
+        SymbolInfo {
+            filters: SymbolFilters::from(raw.filters.as_slice()),
+            symbol: raw.symbol,
+            market: Market::Futures,
+            base: raw.base_asset,
+            quote: raw.quote_asset,
+            price_precision: raw.price_precision,
+            quantity_precision: raw.quantity_precision,
+            base_precision: raw.base_asset_precision,
+            quote_precision: raw.quote_precision,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryPosition {
+    pub symbol: String,
+    // 持仓张数, 空头为负数
+    #[serde(rename = "positionAmt", with = "de2contracts")]
+    pub contracts: i64,
+    #[serde(with = "de2float")]
+    pub entry_price: f64,
+    #[serde(with = "de2float")]
+    pub mark_price: f64,
+    #[serde(rename = "unRealizedProfit", with = "de2float")]
+    pub unrealized_profit: f64,
+    #[serde(with = "de2float")]
+    pub liquidation_price: f64,
+    #[serde(with = "de2float")]
+    pub leverage: f64,
+    pub margin_type: String,
+    #[serde(with = "de2float")]
+    pub isolated_margin: f64,
+    pub position_side: PositionSide,
+    // 持仓价值, 单位为保证金币种
+    #[serde(with = "de2float")]
+    pub notional_value: f64,
+    pub update_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DPOrderResp {
+    pub order_id: u64,
+    pub symbol: String,
+    pub pair: String,
+    pub status: OrderStatus,
+    pub client_order_id: String,
+    #[serde(with = "de2float")]
+    pub price: f64,
+    #[serde(with = "de2float")]
+    pub avg_price: f64,
+    // 以下数量均为合约张数
+    #[serde(with = "de2float")]
+    pub orig_qty: f64,
+    #[serde(with = "de2float")]
+    pub executed_qty: f64,
+    #[serde(with = "de2float")]
+    pub cum_qty: f64,
+    // 成交的标的币数量
+    #[serde(with = "de2float")]
+    pub cum_base: f64,
+    pub time_in_force: TimeInForce,
+    #[serde(rename = "type")]
+    pub type_field: OrderType,
+    pub reduce_only: bool,
+    pub close_position: bool,
+    pub side: Side,
+    pub position_side: PositionSide,
+    #[serde(with = "de2float")]
+    pub stop_price: f64,
+    pub working_type: WorkingType,
+    pub orig_type: OrderType,
+    pub update_time: i64,
+}
+
+impl From<DPOrderResp> for OrderResp {
+    fn from(dpo: DPOrderResp) -> Self {
+        Self {
+            symbol: dpo.symbol,
+            order_id: dpo.order_id,
+            client_order_id: dpo.client_order_id,
+            transact_time: dpo.update_time,
+        }
+    }
+}
+
+impl From<DPOrderResp> for Order {
+    fn from(dpo: DPOrderResp) -> Self {
+        Self {
+            symbol: dpo.symbol,
+            order_id: dpo.order_id,
+            client_order_id: dpo.client_order_id,
+            price: dpo.price,
+            orig_qty: dpo.orig_qty,
+            executed_qty: dpo.executed_qty,
+            status: dpo.status,
+            type_field: dpo.type_field,
+            side: dpo.side,
+            time_in_force: dpo.time_in_force,
+            update_time: dpo.update_time,
+        }
+    }
+}
+
+// positionAmt 为字符串形式的整数张数
+mod de2contracts {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &i64, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<i64, D::Error>
+        where D: Deserializer<'de>
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse::<f64>().map(|v| v.round() as i64).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::stub::{StubResponse, StubServer};
+
+    const EXCHANGE_INFO: &str = r#"{"exchangeFilters":[],"rateLimits":[{"interval":"MINUTE","intervalNum":1,"limit":6000,"rateLimitType":"REQUEST_WEIGHT"}],"serverTime":1565613908500,"symbols":[{"filters":[{"filterType":"PRICE_FILTER","maxPrice":"100000","minPrice":"0.1","tickSize":"0.1"},{"filterType":"LOT_SIZE","maxQty":"100000","minQty":"1","stepSize":"1"}],"OrderType":["LIMIT","MARKET"],"timeInForce":["GTC","IOC","FOK","GTX"],"liquidationFee":"0.010000","marketTakeBound":"0.30","symbol":"BTCUSD_200925","pair":"BTCUSD","contractType":"CURRENT_QUARTER","deliveryDate":1601020800000,"onboardDate":1590739200000,"contractStatus":"TRADING","contractSize":100,"quoteAsset":"USD","baseAsset":"BTC","marginAsset":"BTC","pricePrecision":1,"quantityPrecision":0,"baseAssetPrecision":8,"quotePrecision":8,"equalQtyPrecision":4,"triggerProtect":"0.0500","maintMarginPercent":"2.5000","requiredMarginPercent":"5.0000","underlyingType":"COIN","underlyingSubType":[]}],"timezone":"UTC"}"#;
+
+    const ORDER_JSON: &str = r#"{"clientOrderId":"testOrder","cumQty":"0","cumBase":"0","executedQty":"0","orderId":22542179,"avgPrice":"0.0","origQty":"10","price":"9000","reduceOnly":false,"side":"BUY","positionSide":"BOTH","status":"NEW","stopPrice":"0","closePosition":false,"symbol":"BTCUSD_200925","pair":"BTCUSD","timeInForce":"GTC","type":"LIMIT","origType":"LIMIT","updateTime":1566818724722,"workingType":"CONTRACT_PRICE","priceProtect":false}"#;
+
+    fn get_client(server: &StubServer) -> BinanceDelivery {
+        BinanceDelivery::with_key(server.url(), ("key".to_string(), "secret".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_get_contracts() {
+        let server = StubServer::start(|_| StubResponse::ok(EXCHANGE_INFO)).await;
+        let bd = get_client(&server);
+        let contracts = bd.get_contracts().await.unwrap();
+        let c = &contracts[0];
+        assert_eq!(c.contract_type, ContractType::CurrentQuarter);
+        assert_eq!(c.contract_size, 100);
+        assert_eq!(c.delivery_date, 1601020800000);
+        assert!(!c.is_perpetual());
+        // 1 BTC @ 10000 USD = 100 张 100 USD 的合约
+        assert_eq!(c.coin_to_contracts(1., 10000.), 100);
+        assert_eq!(c.coin_to_contracts(0.0099, 10000.), 0);
+        assert_eq!(c.contracts_to_coin(50, 10000.), 0.5);
+
+        let symbols = bd.get_symbols().await.unwrap();
+        assert_eq!(symbols[0].filters.step_size, 1.);
+        assert_eq!(server.requests()[0].path, "/dapi/v1/exchangeInfo");
+    }
+
+    #[tokio::test]
+    async fn test_market_data() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/dapi/v1/ticker/bookTicker" => StubResponse::ok(r#"[{"symbol":"BTCUSD_200626","pair":"BTCUSD","bidPrice":"9650.1","bidQty":"16","askPrice":"9650.3","askQty":"7","time":1591257300345}]"#),
+            "/dapi/v1/premiumIndex" => StubResponse::ok(r#"[{"symbol":"BTCUSD_200626","pair":"BTCUSD","markPrice":"9652.11","indexPrice":"9650.20","estimatedSettlePrice":"9650.00","lastFundingRate":"","interestRate":"","nextFundingTime":0,"time":1591257300345}]"#),
+            "/dapi/v1/openInterest" => StubResponse::ok(r#"{"symbol":"BTCUSD_200626","pair":"BTCUSD","openInterest":"15004","contractType":"CURRENT_QUARTER","time":1591261042378}"#),
+            "/dapi/v1/depth" => StubResponse::ok(r#"{"lastUpdateId":16769853,"symbol":"BTCUSD_PERP","pair":"BTCUSD","E":1591250106370,"T":1591250106368,"bids":[["9638.0","431"]],"asks":[["9638.2","12"]]}"#),
+            _ => StubResponse::ok(r#"[[1591258320000,"9640.7","9642.4","9640.6","9642.0","206",1591258379999,"2.13660389",48,"119","1.23424865","0"]]"#),
+        })
+        .await;
+        let bd = BinanceDelivery::new(server.url());
+        let ticker = bd.get_ticker("BTCUSD_200626").await.unwrap();
+        assert_eq!(ticker.ask_qty, 7.);
+        let pi = bd.get_premium_index("BTCUSD_200626").await.unwrap();
+        assert_eq!(pi.last_funding_rate, None);
+        let book = bd.get_order_book("BTCUSD_PERP", Some(5)).await.unwrap();
+        assert_eq!(book.bids[0].qty, 431.);
+        let klines = bd.get_klines("BTCUSD_PERP", "1m", None, None, Some(1)).await.unwrap();
+        assert_eq!(klines[0].vol, 206.);
+        let oi = DeliveryAPI::get_open_interest(&bd, "BTCUSD_200626").await.unwrap();
+        assert_eq!((oi.amount, oi.time), (15004., 1591261042378));
+
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            vec!["/dapi/v1/ticker/bookTicker", "/dapi/v1/premiumIndex", "/dapi/v1/depth", "/dapi/v1/klines", "/dapi/v1/openInterest"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_place_and_cancel_order() {
+        let server = StubServer::start(|_| StubResponse::ok(ORDER_JSON)).await;
+        let bd = get_client(&server);
+        let order = OrderRequest::builder("BTCUSD_200925", Side::Buy, OrderType::Limit)
+            .quantity(10.)
+            .price(9000.)
+            .build_unchecked();
+        let resp = bd.place_order(&order).await.unwrap();
+        assert_eq!(resp.order_id, 22542179);
+        let canceled = bd.cancel_order("BTCUSD_200925", Some(22542179), None).await.unwrap();
+        assert_eq!(canceled.orig_qty, 10.);
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].method, "POST");
+        assert_eq!(reqs[0].path, "/dapi/v1/order");
+        assert_eq!(reqs[0].param("quantity").as_deref(), Some("10"));
+        assert_eq!(reqs[1].method, "DELETE");
+        assert_eq!(reqs[1].param("orderId").as_deref(), Some("22542179"));
+        assert!(reqs[1].param("signature").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_positions_and_balances() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/dapi/v1/positionRisk" => StubResponse::ok(r#"[{"symbol":"BTCUSD_201225","positionAmt":"-3","entryPrice":"11707.70000003","markPrice":"11788.66626667","unRealizedProfit":"-0.00176179","liquidationPrice":"0","leverage":"125","maxQty":"50","marginType":"cross","isolatedMargin":"0.00000000","isAutoAddMargin":"false","positionSide":"SHORT","notionalValue":"-0.02544816","isolatedWallet":"0","updateTime":1627026881327,"breakEvenPrice":"0.0"}]"#),
+            _ => StubResponse::ok(r#"[{"accountAlias":"SgsR","asset":"BTC","balance":"0.00250000","withdrawAvailable":"0.00250000","crossWalletBalance":"0.00241969","crossUnPnl":"0.00000000","availableBalance":"0.00241969","updateTime":1592468353979}]"#),
+        })
+        .await;
+        let bd = get_client(&server);
+        let positions = bd.get_positions(Some("BTCUSD")).await.unwrap();
+        assert_eq!(positions[0].contracts, -3);
+        assert_eq!(positions[0].position_side, PositionSide::Short);
+        let balances = bd.get_balances().await.unwrap();
+        assert_eq!(balances[0].free, 0.00241969);
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].param("pair").as_deref(), Some("BTCUSD"));
+        assert_eq!(reqs[1].path, "/dapi/v1/balance");
+    }
+}
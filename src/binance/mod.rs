@@ -1,5 +1,4 @@
 pub mod delivery;
-pub mod delivery_test;
 pub mod perpetual;
 pub mod perpetual_test;
 pub mod spot;
@@ -1,15 +1,27 @@
-use crate::model::{OrderBook, OrderResp, Ticker};
+use crate::binance::spot::{Filter, RawAggTrade, RawKResp, RawTrade};
+use crate::error::{ApiResult, EdpError, Result};
+use crate::order::{order_id_params, Bracket, OrderRequest};
+use crate::model::{
+    Balance, Basis, ContractType, Fill, FundingRate, FuturesAccount, FuturesBalance, Income, IncomeType, KData,
+    LeverageBracket, LeverageInfo, LongShortRatio, MarginChange, MarginType, Market, OpenInterest, OpenInterestHist, Order,
+    OrderBook, OrderResp, OrderStatus, OrderType, PositionRisk, PositionSide, PremiumIndex, PriceTicker, RateLimit, Side,
+    SymbolFilters, SymbolInfo, TakerVolume, Ticker, TickerStats, TimeInForce, Trade, TradeFee, WorkingType,
+};
+use crate::model::opt_string_or_float;
+use crate::rest::limiter::RateLimiter;
+use crate::rest::paginate::{id_range, klines_range, time_range, PageQuery};
 use crate::rest::rclient::RestClient;
+use crate::ws::user::{ListenKeyEndpoint, UserStream};
 use crate::ws::wclient::WssClient;
-use crate::traits::{ExchangeAPI, PerpetualAPI};
+use crate::traits::{AccountAPI, MarketDataAPI, PerpetualAPI, TradingAPI};
 use crate::utils::de2float;
 use async_trait::async_trait;
-use chrono::prelude::*;
+use futures::Stream;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::process;
-use crate::indicator::{Indicator, EdpOrderBook, KlineBucket};
-use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 // 响应中如有数组，数组元素以时间升序排列，越早的数据越提前。
 // 所有时间、时间戳均为UNIX时间，单位为毫秒
@@ -18,145 +30,784 @@ use std::collections::HashMap;
 pub struct BinancePerpetual {
     rest_client: RestClient,
     wss_client: WssClient,
-    order_book: EdpOrderBook,
-    kline_bucket: KlineBucket,
 }
 
 impl BinancePerpetual {
     pub fn with_key(base_url_rest: String, base_url_ws: String,  keys: (String, String)) -> Self {
-        let rest_client = RestClient::with_key(base_url_rest, keys.clone());
-        let wss_client = WssClient::with_key(base_url_ws, keys);
-        let order_book = EdpOrderBook {};
-        let kline_bucket = KlineBucket {};
+        let rest_client = RestClient::with_key(base_url_rest, keys).with_time_sync("/fapi/v1/time");
+        let wss_client = WssClient::new(base_url_ws);
         Self {
             rest_client,
             wss_client,
-            order_book,
-            kline_bucket
         }
     }
-}
 
-#[async_trait]
-impl ExchangeAPI for BinancePerpetual {
-    async fn order(
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        // 额度为空时第一次请求前从 exchangeInfo 读取
+        self.rest_client = self.rest_client.with_limiter(limiter).with_rate_limits("/fapi/v1/exchangeInfo");
+        self
+    }
+
+    // 订单/账户/杠杆变化推送, 代替轮询 query_order
+    pub async fn user_stream(&self) -> Result<UserStream> {
+        self.wss_client
+            .user_stream(self.rest_client.clone(), ListenKeyEndpoint::UsdFutures)
+            .await
+    }
+
+    // 成交价/标记价格/指数价格/连续合约K线, 价格K线的成交量字段为0
+    pub async fn get_futures_klines(
         &self,
-        symbol: &str,
-        side: &str,
-        type_: &str,
-        quantity: f64,
-        price: Option<f64>,
-        time_in_force: &str,
-        recv_window: u64,
-        new_client_order_id: Option<&str>,
-        timestamp: Option<u64>,
-    ) -> anyhow::Result<OrderResp> {
-        // 1. convert params to request
-        let mut params: BTreeMap<String, String> = BTreeMap::new();
-        params.insert("symbol".to_string(), symbol.to_string());
-        params.insert("side".to_string(), side.to_string());
-        params.insert("type".to_string(), type_.to_string());
-        params.insert("quantity".to_string(), quantity.to_string());
-        if let Some(price_num) = price {
-            params.insert("price".to_string(), price_num.to_string());
+        kind: &FuturesKline<'_>,
+        interval: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<KData>> {
+        let mut params = kind.params();
+        params.insert("interval".to_string(), interval.to_string());
+        if let Some(start_ts) = start_time {
+            params.insert("startTime".to_string(), start_ts.to_string());
         }
-        params.insert("timeInForce".to_string(), time_in_force.to_string());
-        params.insert("recvWindow".to_string(), recv_window.to_string());
-        if let Some(client_id) = new_client_order_id {
-            params.insert("newClientOrderId".to_string(), client_id.to_string());
+        if let Some(end_ts) = end_time {
+            params.insert("endTime".to_string(), end_ts.to_string());
         }
+        // 默认500条, 最大1500条
+        if let Some(lim) = limit {
+            params.insert("limit".to_string(), lim.to_string());
+        }
+        let url = self
+            .rest_client
+            .build_request_string(kind.end_point(), params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let kline: Vec<RawKResp> = serde_json::from_str(&resp)?;
+        Ok(kline.iter().map(|rkp| KData::from(*rkp)).collect())
+    }
 
-        if let Some(ts) = timestamp {
-            params.insert("timestamp".to_string(), ts.to_string());
-        } else {
-            let ts = Utc::now().timestamp_millis();
-            params.insert("timestamp".to_string(), ts.to_string());
+    // 持仓风险, 不传 symbol 返回全部交易对(单向持仓模式下每个交易对一条)
+    pub async fn get_position_risk(&self, symbol: Option<&str>) -> Result<Vec<PositionRisk>> {
+        let mut params = BTreeMap::new();
+        if let Some(s) = symbol {
+            params.insert("symbol".to_string(), s.to_string());
         }
+        let end_point = "/fapi/v2/positionRisk";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let positions: Vec<PositionRisk> = serde_json::from_str(&resp)?;
+        Ok(positions)
+    }
 
-        let end_point: &str = "/fapi/v1/order";
-        // 构建完整的url
+    pub async fn get_account(&self) -> Result<FuturesAccount> {
+        let end_point = "/fapi/v2/account";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let account: FuturesAccount = serde_json::from_str(&resp)?;
+        Ok(account)
+    }
+
+    // 包括余额为0的资产
+    pub async fn get_futures_balances(&self) -> Result<Vec<FuturesBalance>> {
+        let end_point = "/fapi/v2/balance";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let balances: Vec<FuturesBalance> = serde_json::from_str(&resp)?;
+        Ok(balances)
+    }
+
+    // leverage: 1 到 125, 上限由杠杆分层决定
+    pub async fn change_leverage(&self, symbol: &str, leverage: u32) -> Result<LeverageInfo> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("leverage".to_string(), leverage.to_string());
+        let end_point = "/fapi/v1/leverage";
         let url = self
             .rest_client
             .build_request_string(end_point, params, true)?;
-        println!("{:#?}", url);
-        // 进行请求
-        let resp: String = self.rest_client.post_sign(url).await?;
-        // 将返回的字符串转换为相应的类型
-        let resp_typed: PPOrderResp = serde_json::from_str(&resp)?;
-        let or = OrderResp::from(resp_typed);
-        Ok(or)
+        let resp = self.rest_client.post_sign(url).await?;
+        let info: LeverageInfo = serde_json::from_str(&resp)?;
+        Ok(info)
     }
 
-    async fn cancel_order(
+    // 已经是目标模式时也返回成功, 便于部署前重复执行
+    pub async fn change_margin_type(&self, symbol: &str, margin_type: MarginType) -> Result<()> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("marginType".to_string(), margin_type.to_string());
+        let end_point = "/fapi/v1/marginType";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        ignore_no_change(self.rest_client.post_sign(url).await, NO_NEED_TO_CHANGE_MARGIN_TYPE)
+    }
+
+    // 调整逐仓保证金, 双向持仓模式下需要指定 position_side
+    pub async fn modify_isolated_margin(
         &self,
         symbol: &str,
-        order_id: Option<u64>,
-        client_order_id: Option<&str>,
-    ) -> anyhow::Result<OrderResp> {
+        amount: f64,
+        change: MarginChange,
+        position_side: Option<PositionSide>,
+    ) -> Result<()> {
         let mut params = BTreeMap::new();
         params.insert("symbol".to_string(), symbol.to_string());
-        if let Some(oi) = order_id {
-            params.insert("orderId".to_string(), oi.to_string());
+        params.insert("amount".to_string(), amount.to_string());
+        params.insert("type".to_string(), change.code().to_string());
+        if let Some(ps) = position_side {
+            params.insert("positionSide".to_string(), ps.to_string());
         }
-        if let Some(coi) = client_order_id {
-            params.insert("origClientOrderId".to_string(), coi.to_string());
+        let end_point = "/fapi/v1/positionMargin";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        self.rest_client.post_sign(url).await?;
+        Ok(())
+    }
+
+    // true 为双向持仓, 对所有交易对生效, 有持仓或挂单时不能修改
+    pub async fn set_dual_side_position(&self, dual_side: bool) -> Result<()> {
+        let mut params = BTreeMap::new();
+        params.insert("dualSidePosition".to_string(), dual_side.to_string());
+        let end_point = "/fapi/v1/positionSide/dual";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        ignore_no_change(self.rest_client.post_sign(url).await, NO_NEED_TO_CHANGE_POSITION_SIDE)
+    }
+
+    pub async fn get_dual_side_position(&self) -> Result<bool> {
+        let end_point = "/fapi/v1/positionSide/dual";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let mode: DualSidePosition = serde_json::from_str(&resp)?;
+        Ok(mode.dual_side_position)
+    }
+
+    // true 为联合保证金模式, 只能在全仓下使用
+    pub async fn set_multi_assets_mode(&self, multi_assets: bool) -> Result<()> {
+        let mut params = BTreeMap::new();
+        params.insert("multiAssetsMargin".to_string(), multi_assets.to_string());
+        let end_point = "/fapi/v1/multiAssetsMargin";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        self.rest_client.post_sign(url).await?;
+        Ok(())
+    }
+
+    pub async fn get_multi_assets_mode(&self) -> Result<bool> {
+        let end_point = "/fapi/v1/multiAssetsMargin";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let mode: MultiAssetsMargin = serde_json::from_str(&resp)?;
+        Ok(mode.multi_assets_margin)
+    }
+
+    pub async fn get_leverage_brackets(&self, symbol: Option<&str>) -> Result<Vec<LeverageBracket>> {
+        let mut params = BTreeMap::new();
+        if let Some(s) = symbol {
+            params.insert("symbol".to_string(), s.to_string());
         }
-        let ts = Utc::now().timestamp_millis();
-        params.insert("timestamp".to_string(), ts.to_string());
+        let end_point = "/fapi/v1/leverageBracket";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        // 指定 symbol 时可能返回单个对象
+        let brackets: OneOrMany<LeverageBracket> = serde_json::from_str(&resp)?;
+        Ok(brackets.into_vec())
+    }
 
-        let end_point: &str = "/fapi/v1/order";
+    pub async fn get_premium_index(&self, symbol: &str) -> Result<PremiumIndex> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/fapi/v1/premiumIndex";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let index: PremiumIndex = serde_json::from_str(&resp)?;
+        Ok(index)
+    }
+
+    // 全部交易对的标记价格和资金费率
+    pub async fn get_premium_indexes(&self) -> Result<Vec<PremiumIndex>> {
+        let end_point = "/fapi/v1/premiumIndex";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), false)?;
+        let resp = self.rest_client.get(url).await?;
+        let indexes: Vec<PremiumIndex> = serde_json::from_str(&resp)?;
+        Ok(indexes)
+    }
+
+    // 默认返回最近100条, 最多1000条
+    pub async fn get_funding_rate_history(
+        &self,
+        symbol: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<FundingRate>> {
+        let params = time_params(("symbol", symbol), None, start_time, end_time, limit);
+        let end_point = "/fapi/v1/fundingRate";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let rates: Vec<FundingRate> = serde_json::from_str(&resp)?;
+        Ok(rates)
+    }
+
+    // [start_time, end_time) 内的全部资金费率, 每页1000条
+    pub async fn get_funding_rate_range(&self, symbol: &str, start_time: u64, end_time: u64) -> Result<Vec<FundingRate>> {
+        let fetch = |start, end, limit| self.get_funding_rate_history(symbol, Some(start), Some(end), Some(limit));
+        time_range(start_time, end_time, FUNDING_PAGE_LIMIT, |r: &FundingRate| r.funding_time, fetch).await
+    }
+
+    // period: 5m 15m 30m 1h 2h 4h 6h 12h 1d, 最多500条
+    pub async fn get_open_interest_hist(
+        &self,
+        symbol: &str,
+        period: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<OpenInterestHist>> {
+        let params = time_params(("symbol", symbol), Some(period), start_time, end_time, limit);
+        self.get_futures_data("/futures/data/openInterestHist", params).await
+    }
+
+    pub async fn get_long_short_ratio(
+        &self,
+        kind: LongShortRatioKind,
+        symbol: &str,
+        period: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<LongShortRatio>> {
+        let params = time_params(("symbol", symbol), Some(period), start_time, end_time, limit);
+        self.get_futures_data(kind.end_point(), params).await
+    }
+
+    pub async fn get_taker_volume(
+        &self,
+        symbol: &str,
+        period: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<TakerVolume>> {
+        let params = time_params(("symbol", symbol), Some(period), start_time, end_time, limit);
+        self.get_futures_data("/futures/data/takerlongshortRatio", params).await
+    }
+
+    // limit 必填, 最多500条
+    pub async fn get_basis(
+        &self,
+        pair: &str,
+        contract_type: ContractType,
+        period: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<Basis>> {
+        let mut params = time_params(("pair", pair), Some(period), start_time, end_time, Some(limit));
+        params.insert("contractType".to_string(), contract_type.to_string());
+        self.get_futures_data("/futures/data/basis", params).await
+    }
+
+    pub async fn get_24h_stats(&self, symbol: &str) -> Result<TickerStats> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        self.get_public("/fapi/v1/ticker/24hr", params).await
+    }
+
+    // 全部交易对, 权重40
+    pub async fn get_all_24h_stats(&self) -> Result<Vec<TickerStats>> {
+        self.get_public("/fapi/v1/ticker/24hr", BTreeMap::new()).await
+    }
+
+    pub async fn get_price(&self, symbol: &str) -> Result<PriceTicker> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        self.get_public("/fapi/v2/ticker/price", params).await
+    }
+
+    pub async fn get_all_prices(&self) -> Result<Vec<PriceTicker>> {
+        self.get_public("/fapi/v2/ticker/price", BTreeMap::new()).await
+    }
+
+    async fn get_public<T: DeserializeOwned>(&self, end_point: &str, params: BTreeMap<String, String>) -> Result<T> {
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        Ok(serde_json::from_str(&resp)?)
+    }
+
+    async fn get_futures_data<T: DeserializeOwned>(&self, end_point: &str, params: BTreeMap<String, String>) -> Result<Vec<T>> {
+        self.get_public(end_point, params).await
+    }
+
+    // 每5个订单一个请求依次提交, 返回值与 orders 一一对应, 单个订单失败不影响其它订单
+    // 外层的错误表示某个请求失败, 此时之前的请求中的订单可能已经提交, 该请求的订单状态未知, 需要用 client_order_id 查询
+    pub async fn place_batch_orders(&self, orders: &[OrderRequest]) -> Result<Vec<Result<OrderResp>>> {
+        if orders.is_empty() {
+            return Err(EdpError::InvalidOrder("batch orders need at least 1 order".to_string()));
+        }
+        let mut results = Vec::with_capacity(orders.len());
+        for chunk in orders.chunks(BATCH_ORDER_LIMIT) {
+            results.extend(self.place_batch_chunk(chunk).await?);
+        }
+        Ok(results)
+    }
+
+    async fn place_batch_chunk(&self, orders: &[OrderRequest]) -> Result<Vec<Result<OrderResp>>> {
+        let batch: Vec<BTreeMap<String, String>> = orders.iter().map(|o| o.to_params()).collect();
+        let mut params = BTreeMap::new();
+        params.insert("batchOrders".to_string(), serde_json::to_string(&batch)?);
+        let end_point = "/fapi/v1/batchOrders";
         let url = self
             .rest_client
             .build_request_string(end_point, params, true)?;
-        println!("{:#?}", url);
-        let resp = self.rest_client.delete_sign(url).await?;
-        let resp_typed: PPOrderResp = serde_json::from_str(&resp)?;
-        let or = OrderResp::from(resp_typed);
-        Ok(or)
+        let resp = self.rest_client.post_sign(url).await?;
+        let items: Vec<ApiResult<PPOrderResp>> = serde_json::from_str(&resp)?;
+        Ok(items.into_iter().map(|i| i.into_result().map(OrderResp::from)).collect())
     }
 
-    async fn query_order(
+    // 入场单和止损止盈单在同一个批量请求中提交, 返回值依次为 entry, stop_loss, take_profit
+    // 批量请求内不保证顺序, 没有持仓时 reduceOnly 的平仓单可能被拒绝(-2022)
+    // 任意一个订单失败时撤销其他已提交的订单并返回该错误, 市价入场单此时可能已经成交, 需要调用方处理仓位
+    // 平仓单成交后另一个不会自动撤销, 需要配合 OcoPair::watch_stream 或 OcoPair::watch_polling 使用
+    pub async fn place_bracket(&self, bracket: &Bracket) -> Result<Vec<OrderResp>> {
+        let orders = [bracket.entry.clone(), bracket.stop_loss.clone(), bracket.take_profit.clone()];
+        let results = self.place_batch_orders(&orders).await?;
+        if results.iter().all(|r| r.is_ok()) {
+            return results.into_iter().collect();
+        }
+        let placed: Vec<u64> = results.iter().filter_map(|r| r.as_ref().ok()).map(|o| o.order_id).collect();
+        if !placed.is_empty() {
+            match self.cancel_batch_orders(&bracket.entry.symbol, &placed).await {
+                Ok(cancels) => {
+                    for err in cancels.iter().filter_map(|r| r.as_ref().err()) {
+                        log::warn!("failed to cancel bracket order: {}", err);
+                    }
+                }
+                Err(err) => log::warn!("failed to cancel bracket orders {:?}: {}", placed, err),
+            }
+        }
+        let (i, err) = results.into_iter().enumerate().find_map(|(i, r)| r.err().map(|e| (i, e))).unwrap();
+        log::warn!("bracket order {} rejected: {}", orders[i].new_client_order_id.as_deref().unwrap_or_default(), err);
+        Err(err)
+    }
+
+    // 一次最多撤10个订单, 返回值与 order_ids 一一对应
+    pub async fn cancel_batch_orders(&self, symbol: &str, order_ids: &[u64]) -> Result<Vec<Result<Order>>> {
+        let ids = serde_json::to_string(order_ids)?;
+        self.cancel_batch(symbol, ("orderIdList", ids), order_ids.len()).await
+    }
+
+    pub async fn cancel_batch_orders_by_client_id(
         &self,
         symbol: &str,
-        order_id: Option<u64>,
-        client_order_id: Option<&str>,
-    ) -> anyhow::Result<OrderResp> {
+        client_order_ids: &[&str],
+    ) -> Result<Vec<Result<Order>>> {
+        let ids = serde_json::to_string(client_order_ids)?;
+        self.cancel_batch(symbol, ("origClientOrderIdList", ids), client_order_ids.len()).await
+    }
+
+    async fn cancel_batch(&self, symbol: &str, ids: (&str, String), count: usize) -> Result<Vec<Result<Order>>> {
+        if count == 0 || count > BATCH_CANCEL_LIMIT {
+            return Err(EdpError::InvalidOrder(format!(
+                "batch cancel accepts 1 to {} orders, got {}",
+                BATCH_CANCEL_LIMIT, count
+            )));
+        }
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert(ids.0.to_string(), ids.1);
+        let end_point = "/fapi/v1/batchOrders";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.delete_sign(url).await?;
+        let items: Vec<ApiResult<PPOrderResp>> = serde_json::from_str(&resp)?;
+        Ok(items.into_iter().map(|i| i.into_result().map(Order::from)).collect())
+    }
+
+    // 撤销该交易对的全部挂单
+    pub async fn cancel_all_open_orders(&self, symbol: &str) -> Result<()> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/fapi/v1/allOpenOrders";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        self.rest_client.delete_sign(url).await?;
+        Ok(())
+    }
+
+    // 倒计时撤单, countdown 内没有再次调用时撤销该交易对全部挂单, 用于断线保护
+    // 需要在倒计时结束前反复调用来续期, countdown 为 0 时取消倒计时
+    pub async fn countdown_cancel_all(&self, symbol: &str, countdown: Duration) -> Result<()> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("countdownTime".to_string(), countdown.as_millis().to_string());
+        let end_point = "/fapi/v1/countdownCancelAll";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        self.rest_client.post_sign(url).await?;
+        Ok(())
+    }
+
+    // [start_time, end_time) 内的全部逐笔成交
+    // historicalTrades 只能按 fromId 查询, 先用 aggTrades 找到窗口内第一笔成交的id
+    pub fn trades<'a>(&'a self, symbol: &'a str, start_time: u64, end_time: u64) -> impl Stream<Item = Result<Trade>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            AGG_TRADES_WINDOW,
+            HISTORICAL_TRADES_PAGE_LIMIT,
+            true,
+            |t: &Trade| (t.time, t.id),
+            move |query, limit| async move {
+                let from_id = match query {
+                    PageQuery::Time { .. } => match self.get_agg_trades(symbol, query, 1).await?.first() {
+                        Some(t) => t.first_trade_id,
+                        None => return Ok(Vec::new()),
+                    },
+                    PageQuery::FromId(id) => id,
+                };
+                self.get_historical_trades(symbol, Some(from_id), limit).await
+            },
+        )
+    }
+
+    // 不指定 from_id 时返回最近的成交, 需要 API key
+    pub async fn get_historical_trades(&self, symbol: &str, from_id: Option<u64>, limit: u64) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        if let Some(id) = from_id {
+            params.insert("fromId".to_string(), id.to_string());
+        }
+        let end_point = "/fapi/v1/historicalTrades";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get_key(url).await?;
+        let trades: Vec<RawTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
+    // [start_time, end_time) 内的归集成交, 时间条件每次最多查询1小时
+    pub fn agg_trades<'a>(&'a self, symbol: &'a str, start_time: u64, end_time: u64) -> impl Stream<Item = Result<Trade>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            AGG_TRADES_WINDOW,
+            HISTORY_PAGE_LIMIT,
+            true,
+            |t: &Trade| (t.time, t.id),
+            move |query, limit| self.get_agg_trades(symbol, query, limit),
+        )
+    }
+
+    pub async fn get_agg_trades(&self, symbol: &str, query: PageQuery, limit: u64) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        query.apply(&mut params);
+        let end_point = "/fapi/v1/aggTrades";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let trades: Vec<RawAggTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
+    // [start_time, end_time) 内的成交, 按成交时间升序
+    // 每次最多查询7天, 一页装不下时改用 fromId 翻页
+    pub fn fills<'a>(&'a self, symbol: &'a str, start_time: u64, end_time: u64) -> impl Stream<Item = Result<Fill>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            HISTORY_WINDOW,
+            HISTORY_PAGE_LIMIT,
+            true,
+            |f: &Fill| (f.time, f.id),
+            move |query, limit| self.get_user_trades(symbol, query, limit),
+        )
+    }
+
+    pub async fn get_user_trades(&self, symbol: &str, query: PageQuery, limit: u64) -> Result<Vec<Fill>> {
         let mut params = BTreeMap::new();
         params.insert("symbol".to_string(), symbol.to_string());
-        if let Some(oi) = order_id {
-            params.insert("orderId".to_string(), oi.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        query.apply(&mut params);
+        let end_point = "/fapi/v1/userTrades";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let trades: Vec<PPTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Fill::from).collect())
+    }
+
+    // [start_time, end_time) 内的资金流水, 同样按7天拆分, 没有 fromId 只能按时间翻页
+    pub fn income<'a>(
+        &'a self,
+        symbol: Option<&'a str>,
+        income_type: Option<IncomeType>,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<Income>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            HISTORY_WINDOW,
+            HISTORY_PAGE_LIMIT,
+            false,
+            |i: &Income| (i.time, i.tran_id),
+            move |query, limit| self.get_income(symbol, income_type, query, limit),
+        )
+    }
+
+    pub async fn get_income(
+        &self,
+        symbol: Option<&str>,
+        income_type: Option<IncomeType>,
+        query: PageQuery,
+        limit: u64,
+    ) -> Result<Vec<Income>> {
+        let mut params = BTreeMap::new();
+        if let Some(s) = symbol {
+            params.insert("symbol".to_string(), s.to_string());
         }
-        if let Some(coi) = client_order_id {
-            params.insert("origClientOrderId".to_string(), coi.to_string());
+        if let Some(t) = income_type {
+            params.insert("incomeType".to_string(), t.to_string());
         }
-        let ts = Utc::now().timestamp_millis();
-        params.insert("timestamp".to_string(), ts.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        query.apply(&mut params);
+        let end_point = "/fapi/v1/income";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let income: Vec<Income> = serde_json::from_str(&resp)?;
+        Ok(income)
+    }
 
-        let end_point: &str = "/fapi/v1/order";
+    pub async fn get_commission_rate(&self, symbol: &str) -> Result<TradeFee> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/fapi/v1/commissionRate";
         let url = self
             .rest_client
             .build_request_string(end_point, params, true)?;
-        println!("{:#?}", url);
         let resp = self.rest_client.get_sign(url).await?;
-        println!("{}", resp);
-        let resp_typed: PPOrderResp = serde_json::from_str(&resp)?;
-        let or = OrderResp::from(resp_typed);
-        Ok(or)
+        let fee: TradeFee = serde_json::from_str(&resp)?;
+        Ok(fee)
     }
 
-    async fn get_ticker(&self, symbol: &str) -> anyhow::Result<Ticker> {
+    // [start_time, end_time) 内的全部K线, 每页1500根
+    pub async fn get_futures_klines_range(
+        &self,
+        kind: &FuturesKline<'_>,
+        interval: &str,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<KData>> {
+        klines_range(start_time, end_time, KLINE_PAGE_LIMIT, |start, end, limit| {
+            self.get_futures_klines(kind, interval, Some(start), Some(end), Some(limit))
+        })
+        .await
+    }
+}
+
+const KLINE_PAGE_LIMIT: u64 = 1500;
+const FUNDING_PAGE_LIMIT: u64 = 1000;
+const HISTORY_WINDOW: u64 = 7 * 24 * 3_600_000;
+const HISTORY_PAGE_LIMIT: u64 = 1000;
+// U本位合约 historicalTrades 每次最多500条
+const HISTORICAL_TRADES_PAGE_LIMIT: u64 = 500;
+const AGG_TRADES_WINDOW: u64 = 3_600_000;
+const BATCH_ORDER_LIMIT: usize = 5;
+const BATCH_CANCEL_LIMIT: usize = 10;
+const NO_NEED_TO_CHANGE_MARGIN_TYPE: i64 = -4046;
+const NO_NEED_TO_CHANGE_POSITION_SIDE: i64 = -4059;
+
+// 设置项已经是目标值时交易所返回错误码 code, 视为成功
+fn ignore_no_change(resp: Result<String>, code: i64) -> Result<()> {
+    match resp {
+        Ok(_) => Ok(()),
+        Err(err) if err.api_code().map(|c| c.code()) == Some(code) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DualSidePosition {
+    dual_side_position: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiAssetsMargin {
+    multi_assets_margin: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(t) => vec![t],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+// 多空比的统计口径
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongShortRatioKind {
+    // 大户账户数多空比
+    TopTraderAccounts,
+    // 大户持仓量多空比
+    TopTraderPositions,
+    // 全市场账户数多空比
+    GlobalAccounts,
+}
+
+impl LongShortRatioKind {
+    fn end_point(&self) -> &'static str {
+        match self {
+            LongShortRatioKind::TopTraderAccounts => "/futures/data/topLongShortAccountRatio",
+            LongShortRatioKind::TopTraderPositions => "/futures/data/topLongShortPositionRatio",
+            LongShortRatioKind::GlobalAccounts => "/futures/data/globalLongShortAccountRatio",
+        }
+    }
+}
+
+// key 为 symbol 或 pair
+fn time_params(
+    key: (&str, &str),
+    period: Option<&str>,
+    start_time: Option<u64>,
+    end_time: Option<u64>,
+    limit: Option<u64>,
+) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    params.insert(key.0.to_string(), key.1.to_string());
+    if let Some(p) = period {
+        params.insert("period".to_string(), p.to_string());
+    }
+    if let Some(start_ts) = start_time {
+        params.insert("startTime".to_string(), start_ts.to_string());
+    }
+    if let Some(end_ts) = end_time {
+        params.insert("endTime".to_string(), end_ts.to_string());
+    }
+    if let Some(lim) = limit {
+        params.insert("limit".to_string(), lim.to_string());
+    }
+    params
+}
+
+// U本位合约的K线种类
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FuturesKline<'a> {
+    // 成交价, symbol
+    Trade(&'a str),
+    // 标记价格, symbol
+    MarkPrice(&'a str),
+    // 指数价格, pair
+    IndexPrice(&'a str),
+    // 连续合约, pair
+    Continuous(&'a str, ContractType),
+}
+
+impl FuturesKline<'_> {
+    fn end_point(&self) -> &'static str {
+        match self {
+            FuturesKline::Trade(_) => "/fapi/v1/klines",
+            FuturesKline::MarkPrice(_) => "/fapi/v1/markPriceKlines",
+            FuturesKline::IndexPrice(_) => "/fapi/v1/indexPriceKlines",
+            FuturesKline::Continuous(..) => "/fapi/v1/continuousKlines",
+        }
+    }
+
+    fn params(&self) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        match self {
+            FuturesKline::Trade(symbol) | FuturesKline::MarkPrice(symbol) => {
+                params.insert("symbol".to_string(), symbol.to_string());
+            }
+            FuturesKline::IndexPrice(pair) => {
+                params.insert("pair".to_string(), pair.to_string());
+            }
+            FuturesKline::Continuous(pair, contract_type) => {
+                params.insert("pair".to_string(), pair.to_string());
+                params.insert("contractType".to_string(), contract_type.to_string());
+            }
+        }
+        params
+    }
+}
+
+#[async_trait]
+impl MarketDataAPI for BinancePerpetual {
+    async fn ping(&self) -> Result<()> {
+        let end_point = "/fapi/v1/ping";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), false)?;
+        self.rest_client.get(url).await?;
+        Ok(())
+    }
+
+    async fn get_symbols(&self) -> Result<Vec<SymbolInfo>> {
+        let end_point = "/fapi/v1/exchangeInfo";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, BTreeMap::new(), false)?;
+        let resp = self.rest_client.get(url).await?;
+        let raw: PPExchangeInfo = serde_json::from_str(&resp)?;
+        Ok(raw.symbols.into_iter().map(SymbolInfo::from).collect())
+    }
+
+    async fn get_ticker(&self, symbol: &str) -> Result<Ticker> {
         let mut params = BTreeMap::new();
         params.insert("symbol".to_string(), symbol.to_string());
         let end_point = "/fapi/v1/ticker/bookTicker";
         let url = self
             .rest_client
             .build_request_string(end_point, params, false)?;
-        let resp = self.rest_client.get_sign(url).await?;
+        let resp = self.rest_client.get(url).await?;
         let ticker: Ticker = serde_json::from_str(&resp)?;
         Ok(ticker)
     }
 
-    async fn get_order_book(&self, symbol: &str, limit: Option<u64>) -> anyhow::Result<OrderBook> {
+    async fn get_order_book(&self, symbol: &str, limit: Option<u64>) -> Result<OrderBook> {
         let mut params = BTreeMap::new();
         params.insert("symbol".to_string(), symbol.to_string());
         // 默认100条
@@ -167,24 +818,161 @@ impl ExchangeAPI for BinancePerpetual {
         let url = self
             .rest_client
             .build_request_string(end_point, params, false)?;
-        let resp = self.rest_client.get_sign(url).await?;
+        let resp = self.rest_client.get(url).await?;
         let ob: OrderBook = serde_json::from_str(&resp)?;
         Ok(ob)
     }
 
-    async fn get_klines() {
-        unimplemented!()
+    async fn get_trades(&self, symbol: &str, limit: Option<u64>) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        if let Some(l) = limit {
+            params.insert("limit".to_string(), l.to_string());
+        }
+        let end_point = "/fapi/v1/trades";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let trades: Vec<RawTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
+    async fn get_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<KData>> {
+        self.get_futures_klines(&FuturesKline::Trade(symbol), interval, start_time, end_time, limit)
+            .await
+    }
+
+    fn kline_page_limit(&self) -> u64 {
+        KLINE_PAGE_LIMIT
+    }
+}
+
+#[async_trait]
+impl TradingAPI for BinancePerpetual {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderResp> {
+        let end_point: &str = "/fapi/v1/order";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, order.to_params(), true)?;
+        let resp = self.rest_client.post_sign(url).await?;
+        let resp_typed: PPOrderResp = serde_json::from_str(&resp)?;
+        Ok(OrderResp::from(resp_typed))
+    }
+
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order> {
+        let params = order_id_params(symbol, order_id, client_order_id)?;
+        let end_point: &str = "/fapi/v1/order";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.delete_sign(url).await?;
+        let resp_typed: PPOrderResp = serde_json::from_str(&resp)?;
+        Ok(Order::from(resp_typed))
+    }
+
+    async fn query_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order> {
+        let params = order_id_params(symbol, order_id, client_order_id)?;
+        let end_point: &str = "/fapi/v1/order";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let resp_typed: PPOrderResp = serde_json::from_str(&resp)?;
+        Ok(Order::from(resp_typed))
     }
 }
 
-impl PerpetualAPI for BinancePerpetual {}
+#[async_trait]
+impl AccountAPI for BinancePerpetual {
+    async fn get_balances(&self) -> Result<Vec<Balance>> {
+        let raw = self.get_futures_balances().await?;
+        Ok(raw
+            .into_iter()
+            .map(Balance::from)
+            .filter(|b| b.free != 0. || b.locked != 0.)
+            .collect())
+    }
+}
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[async_trait]
+impl PerpetualAPI for BinancePerpetual {
+    async fn get_open_interest(&self, symbol: &str) -> Result<OpenInterest> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/fapi/v1/openInterest";
+        let url = self
+            .rest_client
+            .build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let oi: OpenInterest = serde_json::from_str(&resp)?;
+        Ok(oi)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PPExchangeInfo {
+    pub rate_limits: Vec<RateLimit>,
+    pub symbols: Vec<PPSymbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PPSymbol {
+    pub symbol: String,
+    pub pair: String,
+    pub contract_type: String,
+    pub status: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub margin_asset: String,
+    pub price_precision: u8,
+    pub quantity_precision: u8,
+    pub base_asset_precision: u8,
+    pub quote_precision: u8,
+    pub filters: Vec<Filter>,
+}
+
+impl From<PPSymbol> for SymbolInfo {
+    fn from(raw: PPSymbol) -> Self {
+        SymbolInfo {
+            filters: SymbolFilters::from(raw.filters.as_slice()),
+            symbol: raw.symbol,
+            market: Market::Futures,
+            base: raw.base_asset,
+            quote: raw.quote_asset,
+            price_precision: raw.price_precision,
+            quantity_precision: raw.quantity_precision,
+            base_precision: raw.base_asset_precision,
+            quote_precision: raw.quote_precision,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PPOrderResp {
     pub order_id: u64,
     pub symbol: String,
-    pub status: String,
+    pub status: OrderStatus,
     pub client_order_id: String,
     #[serde(with = "de2float")]
     pub price: f64,
@@ -198,21 +986,90 @@ pub struct PPOrderResp {
     pub cum_qty: Option<String>,
     #[serde(with = "de2float")]
     pub cum_quote: f64,
-    pub time_in_force: String,
+    pub time_in_force: TimeInForce,
     #[serde(rename = "type")]
-    pub type_field: String,
+    pub type_field: OrderType,
     pub reduce_only: bool,
     pub close_position: bool,
-    pub side: String,
-    pub position_side: String,
+    pub side: Side,
+    pub position_side: PositionSide,
     #[serde(with = "de2float")]
     pub stop_price: f64,
-    pub working_type: String,
-    pub orig_type: String,
+    pub working_type: WorkingType,
+    pub orig_type: OrderType,
+    #[serde(default)]
+    pub price_protect: bool,
+    // 仅跟踪止损单返回
+    #[serde(default, with = "opt_string_or_float")]
+    pub activate_price: Option<f64>,
+    #[serde(default, with = "opt_string_or_float")]
+    pub price_rate: Option<f64>,
     pub update_time: i64,
     pub time: Option<i64>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PPTrade {
+    pub symbol: String,
+    pub id: u64,
+    pub order_id: u64,
+    pub side: Side,
+    pub position_side: PositionSide,
+    #[serde(with = "de2float")]
+    pub price: f64,
+    #[serde(with = "de2float")]
+    pub qty: f64,
+    #[serde(with = "de2float")]
+    pub quote_qty: f64,
+    #[serde(with = "de2float")]
+    pub commission: f64,
+    pub commission_asset: String,
+    #[serde(with = "de2float")]
+    pub realized_pnl: f64,
+    pub maker: bool,
+    pub buyer: bool,
+    pub time: u64,
+}
+
+impl From<PPTrade> for Fill {
+    fn from(t: PPTrade) -> Self {
+        Self {
+            symbol: t.symbol,
+            id: t.id,
+            order_id: t.order_id,
+            side: t.side,
+            price: t.price,
+            qty: t.qty,
+            quote_qty: t.quote_qty,
+            commission: t.commission,
+            commission_asset: t.commission_asset,
+            is_maker: t.maker,
+            time: t.time,
+            realized_pnl: Some(t.realized_pnl),
+            position_side: Some(t.position_side),
+        }
+    }
+}
+
+impl From<PPOrderResp> for Order {
+    fn from(ppo: PPOrderResp) -> Self {
+        Self {
+            symbol: ppo.symbol,
+            order_id: ppo.order_id,
+            client_order_id: ppo.client_order_id,
+            price: ppo.price,
+            orig_qty: ppo.orig_qty,
+            executed_qty: ppo.executed_qty,
+            status: ppo.status,
+            type_field: ppo.type_field,
+            side: ppo.side,
+            time_in_force: ppo.time_in_force,
+            update_time: ppo.update_time,
+        }
+    }
+}
+
 impl From<PPOrderResp> for OrderResp {
     fn from(ppo: PPOrderResp) -> Self {
         Self {
@@ -227,143 +1084,636 @@ impl From<PPOrderResp> for OrderResp {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::rest::stub::{StubRequest, StubResponse, StubServer};
     use dotenv::dotenv;
+    use futures::TryStreamExt;
     use std::env;
 
-    const REST_BASE_URL: &'static str = "https://fapi.binance.com";
-    const WS_BASE_URL: &'static str = "wss://fstream.binance.com";
+    const REST_BASE_URL: &str = "https://fapi.binance.com";
+    const WS_BASE_URL: &str = "wss://fstream.binance.com";
 
     fn get_client() -> BinancePerpetual {
         dotenv().ok();
         let api_key = env::var("API_KEY").unwrap();
         let sec_key = env::var("SEC_KEY").unwrap();
-        let bp = BinancePerpetual::with_key(REST_BASE_URL.to_string(), WS_BASE_URL.to_string(), (api_key, sec_key));
-        bp
+        BinancePerpetual::with_key(REST_BASE_URL.to_string(), WS_BASE_URL.to_string(), (api_key, sec_key))
     }
 
-    #[tokio::main]
-    #[test]
-    async fn test_order() {
-        /*
-        symbol=BTCUSDT
-        &side=BUY
-        &type=LIMIT
-        &timeInForce=GTC
-        &quantity=1
-        &price=0.1
-        &recvWindow=5000
-        &timestamp=1499827319559
-        */
-        // let bp = BinancePerpetual::new();
-        let secret_key = "2b5eb11e18796d12d88f13dc27dbbd02c2cc51ff7059765ed9821957d82bb4d9";
-        let api_key = "dbefbc809e3e83c283a984c3a1459732ea7db1360ca80c5c2c8867408d28cc83";
-        let target_signature = "3c661234138461fcc7a7d8746c6558c9842d4e10870d2ecbedf7777cad694af9";
-        let bp = BinancePerpetual::with_key(REST_BASE_URL.to_string(), WS_BASE_URL.to_string(), (api_key.to_string(), secret_key.to_string()));
-
-        //https://fapi.binance.com/fapi/v1/order?symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=1&price=9000&timeInForce=GTC&recvWindow=5000&timestamp=1591702613943&signature=3c661234138461fcc7a7d8746c6558c9842d4e10870d2ecbedf7777cad694af9
-        //https://fapi.binance.com/fapi/v1/order?symbol=BTCUSDT&side=BUY&type=LIMIT&quantity=1&price=9000&timeInForce=GTC&recvWindow=5000&timestamp=1591702613943&signature=3c661234138461fcc7a7d8746c6558c9842d4e10870d2ecbedf7777cad694af9
-
-        bp.order(
-            "BTCUSDT",
-            "BUY",
-            "LIMIT",
-            1.,
-            Some(9000.),
-            "GTC",
-            5000,
-            None,
-            Some(1591702613943),
-            // None
-        )
+    const ORDER_JSON: &str = r#"{"clientOrderId":"testOrder","cumQty":"0","cumQuote":"0","executedQty":"0","orderId":22542179,"avgPrice":"0.00000","origQty":"10","price":"0","reduceOnly":false,"side":"BUY","positionSide":"SHORT","status":"NEW","stopPrice":"9300","closePosition":false,"symbol":"BTCUSDT","timeInForce":"GTC","type":"TRAILING_STOP_MARKET","origType":"TRAILING_STOP_MARKET","updateTime":1566818724722,"workingType":"CONTRACT_PRICE"}"#;
+
+    fn get_stub_client(server: &StubServer) -> BinancePerpetual {
+        BinancePerpetual::with_key(server.url(), WS_BASE_URL.to_string(), ("key".to_string(), "secret".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_place_order() {
+        let server = StubServer::start(|_| StubResponse::ok(ORDER_JSON)).await;
+        let bp = get_stub_client(&server);
+        let order = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(1.)
+            .price(9000.)
+            .build_unchecked();
+        let resp = bp.place_order(&order).await.unwrap();
+        assert_eq!(resp.order_id, 22542179);
+        assert_eq!(resp.transact_time, 1566818724722);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/fapi/v1/order");
+        assert_eq!(req.param("timeInForce").as_deref(), Some("GTC"));
+        assert!(req.param("signature").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_query_order_by_client_id() {
+        let server = StubServer::start(|_| StubResponse::ok(ORDER_JSON)).await;
+        let bp = get_stub_client(&server);
+        let order = bp.query_order("BTCUSDT", None, Some("testOrder")).await.unwrap();
+        assert_eq!(order.client_order_id, "testOrder");
+        assert_eq!(order.orig_qty, 10.);
+        assert_eq!(order.status, OrderStatus::New);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.param("origClientOrderId").as_deref(), Some("testOrder"));
+    }
+
+    #[tokio::test]
+    async fn test_get_balances() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"[{"accountAlias":"SgsR","asset":"USDT","balance":"122.60","crossWalletBalance":"122.60","crossUnPnl":"0.00","availableBalance":"100.10","maxWithdrawAmount":"100.10","marginAvailable":true,"updateTime":1617939110373},{"accountAlias":"SgsR","asset":"BNB","balance":"0.00","crossWalletBalance":"0.00","crossUnPnl":"0.00","availableBalance":"0.00","maxWithdrawAmount":"0.00","marginAvailable":true,"updateTime":0}]"#)
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let balances = bp.get_balances().await.unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].free, 100.1);
+        assert!((balances[0].locked - 22.5).abs() < 1e-9);
+        assert_eq!(server.requests()[0].path, "/fapi/v2/balance");
+    }
+
+    const POSITION_RISK_JSON: &str = r#"[{"entryPrice":"6563.66500","breakEvenPrice":"6570.2","marginType":"isolated","isAutoAddMargin":"false","isolatedMargin":"15517.54150468","leverage":"10","liquidationPrice":"5930.78","markPrice":"6679.50671178","maxNotionalValue":"20000000","positionAmt":"20.000","notional":"133590.13","isolatedWallet":"15401.21","symbol":"BTCUSDT","unRealizedProfit":"2316.83423560","positionSide":"BOTH","updateTime":1625474304765}]"#;
+    const ACCOUNT_JSON: &str = r#"{"feeTier":0,"canTrade":true,"canDeposit":true,"canWithdraw":true,"updateTime":0,"multiAssetsMargin":false,"totalInitialMargin":"0.00000000","totalMaintMargin":"0.00000000","totalWalletBalance":"23.72469206","totalUnrealizedProfit":"0.00000000","totalMarginBalance":"23.72469206","totalPositionInitialMargin":"0.00000000","totalOpenOrderInitialMargin":"0.00000000","totalCrossWalletBalance":"23.72469206","totalCrossUnPnl":"0.00000000","availableBalance":"23.72469206","maxWithdrawAmount":"23.72469206","assets":[{"asset":"USDT","walletBalance":"23.72469206","unrealizedProfit":"0.00000000","marginBalance":"23.72469206","maintMargin":"0.00000000","initialMargin":"0.00000000","positionInitialMargin":"0.00000000","openOrderInitialMargin":"0.00000000","crossWalletBalance":"23.72469206","crossUnPnl":"0.00000000","availableBalance":"23.72469206","maxWithdrawAmount":"23.72469206","marginAvailable":true,"updateTime":1625474304765}],"positions":[{"symbol":"BTCUSDT","initialMargin":"0","maintMargin":"0","unrealizedProfit":"0.00000000","positionInitialMargin":"0","openOrderInitialMargin":"0","leverage":"100","isolated":true,"entryPrice":"0.00000","maxNotional":"250000","bidNotional":"0","askNotional":"0","positionSide":"BOTH","positionAmt":"0","updateTime":0}]}"#;
+    const BRACKET_JSON: &str = r#"{"symbol":"ETHUSDT","notionalCoef":1.50,"brackets":[{"bracket":1,"initialLeverage":75,"notionalCap":10000,"notionalFloor":0,"maintMarginRatio":0.0065,"cum":0}]}"#;
+
+    #[tokio::test]
+    async fn test_get_position_risk_and_account() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/fapi/v2/positionRisk" => StubResponse::ok(POSITION_RISK_JSON),
+            _ => StubResponse::ok(ACCOUNT_JSON),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let positions = bp.get_position_risk(Some("BTCUSDT")).await.unwrap();
+        assert_eq!(positions[0].margin_type, MarginType::Isolated);
+        assert_eq!(positions[0].leverage, 10);
+        assert!(!positions[0].is_auto_add_margin);
+        assert_eq!(positions[0].position_amt, 20.);
+
+        let account = bp.get_account().await.unwrap();
+        assert_eq!(account.total_wallet_balance, 23.72469206);
+        assert_eq!(account.assets[0].margin_available, Some(true));
+        assert_eq!(account.positions[0].leverage, 100);
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].param("symbol").as_deref(), Some("BTCUSDT"));
+        assert_eq!(reqs[1].path, "/fapi/v2/account");
+        assert!(reqs.iter().all(|r| r.param("signature").is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_change_leverage_and_margin() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/fapi/v1/leverage" => StubResponse::ok(r#"{"leverage":21,"maxNotionalValue":"1000000","symbol":"BTCUSDT"}"#),
+            "/fapi/v1/marginType" => {
+                StubResponse::with_status(400, r#"{"code":-4046,"msg":"No need to change margin type."}"#)
+            }
+            "/fapi/v1/positionSide/dual" if req.method == "POST" => {
+                StubResponse::with_status(400, r#"{"code":-4068,"msg":"Position side cannot be changed if there exists position."}"#)
+            }
+            _ => StubResponse::ok(r#"{"amount":100.0,"code":200,"msg":"Successfully modify position margin.","type":1}"#),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let info = bp.change_leverage("BTCUSDT", 21).await.unwrap();
+        assert_eq!((info.leverage, info.max_notional_value), (21, 1000000.));
+        // 已经是逐仓
+        bp.change_margin_type("BTCUSDT", MarginType::Isolated).await.unwrap();
+        bp.modify_isolated_margin("BTCUSDT", 100., MarginChange::Reduce, Some(PositionSide::Long))
+            .await
+            .unwrap();
+        let err = bp.set_dual_side_position(true).await.unwrap_err();
+        assert_eq!(err.api_code().map(|c| c.code()), Some(-4068));
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].param("leverage").as_deref(), Some("21"));
+        assert_eq!(reqs[1].param("marginType").as_deref(), Some("ISOLATED"));
+        assert_eq!(reqs[2].param("type").as_deref(), Some("2"));
+        assert_eq!(reqs[2].param("positionSide").as_deref(), Some("LONG"));
+        assert_eq!(reqs[3].param("dualSidePosition").as_deref(), Some("true"));
+        assert!(reqs.iter().all(|r| r.method == "POST"));
+    }
+
+    #[tokio::test]
+    async fn test_position_modes_and_brackets() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/fapi/v1/positionSide/dual" => StubResponse::ok(r#"{"dualSidePosition":true}"#),
+            "/fapi/v1/multiAssetsMargin" if req.method == "GET" => StubResponse::ok(r#"{"multiAssetsMargin":false}"#),
+            "/fapi/v1/multiAssetsMargin" => StubResponse::ok(r#"{"code":200,"msg":"success"}"#),
+            _ if req.param("symbol").is_some() => StubResponse::ok(BRACKET_JSON),
+            _ => StubResponse::ok(&format!("[{}]", BRACKET_JSON)),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        assert!(bp.get_dual_side_position().await.unwrap());
+        assert!(!bp.get_multi_assets_mode().await.unwrap());
+        bp.set_multi_assets_mode(true).await.unwrap();
+        let one = bp.get_leverage_brackets(Some("ETHUSDT")).await.unwrap();
+        let all = bp.get_leverage_brackets(None).await.unwrap();
+        assert_eq!(one, all);
+        assert_eq!(one[0].brackets[0].initial_leverage, 75);
+        assert_eq!(one[0].notional_coef, Some(1.5));
+        assert_eq!(server.requests()[2].param("multiAssetsMargin").as_deref(), Some("true"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_orders_partial_failure() {
+        let body = format!(r#"[{},{{"code":-2022,"msg":"ReduceOnly Order is rejected."}}]"#, ORDER_JSON);
+        let server = StubServer::start(move |_| StubResponse::ok(&body)).await;
+        let bp = get_stub_client(&server);
+        let orders: Vec<OrderRequest> = [(9000., "bid-1"), (9100., "bid-2")]
+            .iter()
+            .map(|(price, id)| {
+                OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+                    .quantity(0.01)
+                    .price(*price)
+                    .client_order_id(id)
+                    .build_unchecked()
+            })
+            .collect();
+        let results = bp.place_batch_orders(&orders).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().order_id, 22542179);
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!(err.api_code().map(|c| c.code()), Some(-2022));
+
+        let req = &server.requests()[0];
+        assert_eq!((req.method.as_str(), req.path.as_str()), ("POST", "/fapi/v1/batchOrders"));
+        let batch: Vec<BTreeMap<String, String>> = serde_json::from_str(&req.param("batchOrders").unwrap()).unwrap();
+        assert_eq!(batch, vec![orders[0].to_params(), orders[1].to_params()]);
+
+        assert!(matches!(bp.place_batch_orders(&[]).await, Err(EdpError::InvalidOrder(_))));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_orders_are_chunked() {
+        // 按请求中的订单依次返回, 第7个订单失败
+        let server = StubServer::start(|req| {
+            let batch: Vec<BTreeMap<String, String>> = serde_json::from_str(&req.param("batchOrders").unwrap()).unwrap();
+            let items: Vec<String> = batch
+                .iter()
+                .map(|o| match o["newClientOrderId"].as_str() {
+                    "q-6" => r#"{"code":-2019,"msg":"Margin is insufficient."}"#.to_string(),
+                    id => ORDER_JSON.replace("testOrder", id),
+                })
+                .collect();
+            StubResponse::ok(&format!("[{}]", items.join(",")))
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let orders: Vec<OrderRequest> = (0..10)
+            .map(|i| {
+                OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+                    .quantity(0.01)
+                    .price(9000. + i as f64)
+                    .client_order_id(&format!("q-{}", i))
+                    .build_unchecked()
+            })
+            .collect();
+        let results = bp.place_batch_orders(&orders).await.unwrap();
+        assert_eq!(results.len(), 10);
+        for (i, result) in results.iter().enumerate() {
+            match result {
+                Ok(resp) => assert_eq!(resp.client_order_id, format!("q-{}", i)),
+                Err(err) => {
+                    assert_eq!(i, 6);
+                    assert_eq!(err.api_code().map(|c| c.code()), Some(-2019));
+                }
+            }
+        }
+
+        let sizes: Vec<usize> = server
+            .requests()
+            .iter()
+            .map(|r| serde_json::from_str::<Vec<BTreeMap<String, String>>>(&r.param("batchOrders").unwrap()).unwrap().len())
+            .collect();
+        assert_eq!(sizes, vec![5, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_place_bracket() {
+        let body = format!("[{0},{0},{0}]", ORDER_JSON);
+        let server = StubServer::start(move |_| StubResponse::ok(&body)).await;
+        let bp = get_stub_client(&server);
+        let bracket = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Market)
+            .quantity(0.01)
+            .bracket(9000., 9600.)
+            .build_unchecked();
+        let results = bp.place_bracket(&bracket).await.unwrap();
+        assert_eq!(results.len(), 3);
+
+        let batch: Vec<BTreeMap<String, String>> =
+            serde_json::from_str(&server.requests()[0].param("batchOrders").unwrap()).unwrap();
+        let types: Vec<&str> = batch.iter().map(|o| o["type"].as_str()).collect();
+        assert_eq!(types, vec!["MARKET", "STOP_MARKET", "TAKE_PROFIT_MARKET"]);
+        assert_eq!(batch[1]["stopPrice"], "9000");
+        assert_eq!(batch[2]["reduceOnly"], "true");
+    }
+
+    #[tokio::test]
+    async fn test_place_bracket_exit_rejected() {
+        let rejected = r#"{"code":-2022,"msg":"ReduceOnly Order is rejected."}"#;
+        let body = format!("[{0},{1},{1}]", ORDER_JSON, rejected);
+        let cancels = format!("[{}]", ORDER_JSON);
+        let server = StubServer::start(move |req| match req.method.as_str() {
+            "POST" => StubResponse::ok(&body),
+            _ => StubResponse::ok(&cancels),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let bracket = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.01)
+            .price(9300.)
+            .client_order_id("entry")
+            .bracket(9000., 9600.)
+            .build_unchecked();
+        let err = bp.place_bracket(&bracket).await.unwrap_err();
+        assert_eq!(err.api_code().map(|c| c.code()), Some(-2022));
+
+        // 平仓单失败时撤销已提交的入场单
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        assert_eq!((reqs[1].method.as_str(), reqs[1].path.as_str()), ("DELETE", "/fapi/v1/batchOrders"));
+        assert_eq!(reqs[1].param("orderIdList").as_deref(), Some("[22542179]"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_batch_and_countdown() {
+        let body = format!(r#"[{},{{"code":-2011,"msg":"Unknown order sent."}}]"#, ORDER_JSON);
+        let server = StubServer::start(move |req| match req.path.as_str() {
+            "/fapi/v1/batchOrders" => StubResponse::ok(&body),
+            "/fapi/v1/allOpenOrders" => StubResponse::ok(r#"{"code":200,"msg":"The operation of cancel all open order is done."}"#),
+            _ => StubResponse::ok(r#"{"symbol":"BTCUSDT","countdownTime":"120000"}"#),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let results = bp.cancel_batch_orders("BTCUSDT", &[22542179, 1]).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().order_id, 22542179);
+        assert!(results[1].is_err());
+        bp.cancel_batch_orders_by_client_id("BTCUSDT", &["bid-1", "bid-2"]).await.unwrap();
+        bp.cancel_all_open_orders("BTCUSDT").await.unwrap();
+        bp.countdown_cancel_all("BTCUSDT", Duration::from_secs(120)).await.unwrap();
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].method, "DELETE");
+        assert_eq!(reqs[0].param("orderIdList").as_deref(), Some("[22542179,1]"));
+        assert_eq!(reqs[1].param("origClientOrderIdList").as_deref(), Some(r#"["bid-1","bid-2"]"#));
+        assert_eq!((reqs[2].method.as_str(), reqs[2].path.as_str()), ("DELETE", "/fapi/v1/allOpenOrders"));
+        assert_eq!(reqs[3].path, "/fapi/v1/countdownCancelAll");
+        assert_eq!(reqs[3].param("countdownTime").as_deref(), Some("120000"));
+    }
+
+    // 每5分钟一笔成交, 共3000笔, 按时间或 fromId 返回
+    fn serve_user_trades(req: &StubRequest) -> StubResponse {
+        let limit: usize = req.param("limit").unwrap().parse().unwrap();
+        let ids: Vec<u64> = match req.param("fromId") {
+            Some(from) => (from.parse().unwrap()..3000).take(limit).collect(),
+            None => {
+                let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+                let end: u64 = req.param("endTime").unwrap().parse().unwrap();
+                (start.div_ceil(300_000)..=end / 300_000).take_while(|i| *i < 3000).take(limit).collect()
+            }
+        };
+        let trades: Vec<String> = ids
+            .iter()
+            .map(|i| {
+                format!(
+                    r#"{{"buyer":false,"commission":"-0.07819010","commissionAsset":"USDT","id":{},"maker":true,"orderId":25851813,"price":"7819.01","qty":"0.002","quoteQty":"15.63802","realizedPnl":"-0.91539999","side":"SELL","positionSide":"SHORT","symbol":"BTCUSDT","time":{}}}"#,
+                    i,
+                    i * 300_000
+                )
+            })
+            .collect();
+        StubResponse::ok(&format!("[{}]", trades.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_fills() {
+        let server = StubServer::start(serve_user_trades).await;
+        let bp = get_stub_client(&server);
+        let end = 2500 * 300_000;
+        let fills: Vec<Fill> = bp.fills("BTCUSDT", 0, end).try_collect().await.unwrap();
+        assert_eq!(fills.len(), 2500);
+        assert!(fills.windows(2).all(|w| w[1].id == w[0].id + 1));
+        let f = &fills[0];
+        assert_eq!((f.side, f.position_side, f.is_maker), (Side::Sell, Some(PositionSide::Short), true));
+        assert_eq!((f.commission, f.realized_pnl), (-0.0781901, Some(-0.91539999)));
+
+        // 第一个7天窗口有2016笔, 超过一页后改用 fromId
+        let reqs = server.requests();
+        assert_eq!(reqs[0].param("endTime"), Some((HISTORY_WINDOW - 1).to_string()));
+        assert_eq!(reqs[1].param("fromId").as_deref(), Some("1000"));
+        assert_eq!(reqs[1].param("startTime"), None);
+        assert_eq!(reqs.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_public_trades() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/fapi/v1/aggTrades" => {
+                let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+                if start <= 1498793709153 {
+                    StubResponse::ok(r#"[{"a":26129,"p":"0.01633102","q":"4.70443515","f":27781,"l":27783,"T":1498793709153,"m":false}]"#)
+                } else {
+                    StubResponse::ok("[]")
+                }
+            }
+            _ => StubResponse::ok(r#"[{"id":28457,"price":"4.00000100","qty":"12.00000000","quoteQty":"48.00","time":1499865549590,"isBuyerMaker":true}]"#),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let start = 1498793709153 - 10;
+        let agg: Vec<Trade> = bp.agg_trades("BTCUSDT", start, start + 2 * 3_600_000).try_collect().await.unwrap();
+        assert_eq!(agg.len(), 1);
+        assert_eq!((agg[0].id, agg[0].side, agg[0].first_trade_id, agg[0].last_trade_id), (26129, Side::Buy, 27781, 27783));
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[1].param("startTime"), Some((start + AGG_TRADES_WINDOW).to_string()));
+
+        let trades = bp.get_trades("BTCUSDT", Some(1)).await.unwrap();
+        assert_eq!((trades[0].id, trades[0].side, trades[0].qty), (28457, Side::Sell, 12.));
+        let trades = bp.get_historical_trades("BTCUSDT", Some(28457), 500).await.unwrap();
+        assert_eq!(trades[0].time, 1499865549590);
+        let req = server.requests().pop().unwrap();
+        assert_eq!(req.path, "/fapi/v1/historicalTrades");
+        assert_eq!(req.param("fromId").as_deref(), Some("28457"));
+        assert_eq!(req.headers.get("x-mbx-apikey").map(String::as_str), Some("key"));
+        assert_eq!(req.param("timestamp"), None);
+
+        // 按时间拉取逐笔成交时 historicalTrades 每页最多500条
+        let count = server.requests().len();
+        bp.trades("BTCUSDT", start, start + 3_600_000).try_collect::<Vec<Trade>>().await.unwrap();
+        let reqs = server.requests().split_off(count);
+        assert_eq!((reqs[0].path.as_str(), reqs[0].param("limit").as_deref()), ("/fapi/v1/aggTrades", Some("1")));
+        assert_eq!(reqs[1].path, "/fapi/v1/historicalTrades");
+        assert_eq!(reqs[1].param("fromId").as_deref(), Some("27781"));
+        assert_eq!(reqs[1].param("limit").as_deref(), Some("500"));
+    }
+
+    #[tokio::test]
+    async fn test_ticker_stats() {
+        let server = StubServer::start(|req| match (req.path.as_str(), req.param("symbol").is_some()) {
+            ("/fapi/v1/ticker/24hr", true) => StubResponse::ok(r#"{"symbol":"BTCUSDT","priceChange":"-94.99999800","priceChangePercent":"-95.960","weightedAvgPrice":"0.29628482","lastPrice":"4.00000200","lastQty":"200.00000000","openPrice":"99.00000000","highPrice":"100.00000000","lowPrice":"0.10000000","volume":"8913.30000000","quoteVolume":"15.30000000","openTime":1499783499040,"closeTime":1499869899040,"firstId":28385,"lastId":28460,"count":76}"#),
+            ("/fapi/v1/ticker/24hr", false) => StubResponse::ok(r#"[{"symbol":"BTCUSDT","priceChange":"1","priceChangePercent":"1","weightedAvgPrice":"1","lastPrice":"1","lastQty":"1","openPrice":"1","highPrice":"1","lowPrice":"1","volume":"1","quoteVolume":"1","openTime":1,"closeTime":2,"firstId":1,"lastId":2,"count":2}]"#),
+            (_, true) => StubResponse::ok(r#"{"symbol":"BTCUSDT","price":"6000.01","time":1589437530011}"#),
+            _ => StubResponse::ok(r#"[{"symbol":"BTCUSDT","price":"6000.01","time":1589437530011}]"#),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let stats = bp.get_24h_stats("BTCUSDT").await.unwrap();
+        assert_eq!((stats.quote_volume, stats.count, stats.first_id), (15.3, 76, 28385));
+        assert_eq!((stats.prev_close_price, stats.bid_price), (None, None));
+        assert_eq!(bp.get_all_24h_stats().await.unwrap().len(), 1);
+        let price = bp.get_price("BTCUSDT").await.unwrap();
+        assert_eq!((price.price, price.time), (6000.01, Some(1589437530011)));
+        assert_eq!(bp.get_all_prices().await.unwrap()[0].symbol, "BTCUSDT");
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths[2..], ["/fapi/v2/ticker/price", "/fapi/v2/ticker/price"]);
+    }
+
+    #[tokio::test]
+    async fn test_income_and_commission_rate() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/fapi/v1/commissionRate" => StubResponse::ok(r#"{"symbol":"BTCUSDT","makerCommissionRate":"0.0002","takerCommissionRate":"0.0004"}"#),
+            _ => StubResponse::ok(r#"[{"symbol":"","incomeType":"TRANSFER","income":"-0.37500000","asset":"USDT","info":"TRANSFER","time":1570608000000,"tranId":9689322392,"tradeId":""},{"symbol":"BTCUSDT","incomeType":"COMMISSION","income":"-0.01000000","asset":"USDT","info":"COMMISSION","time":1570636800000,"tranId":9689322392,"tradeId":"2059192"},{"symbol":"BTCUSDT","incomeType":"STRATEGY_UMFUTURES_TRANSFER","income":"1","asset":"USDT","info":"","time":1570636800001,"tranId":"9689322393","tradeId":""}]"#),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let income: Vec<Income> = bp
+            .income(Some("BTCUSDT"), None, 1570608000000, 1570608000000 + 86_400_000)
+            .try_collect()
+            .await
+            .unwrap();
+        let types: Vec<IncomeType> = income.iter().map(|i| i.income_type).collect();
+        assert_eq!(types, vec![IncomeType::Transfer, IncomeType::Commission, IncomeType::Other]);
+        assert_eq!(income[1].trade_id, "2059192");
+        assert_eq!(income[2].tran_id, 9689322393);
+        let fee = bp.get_commission_rate("BTCUSDT").await.unwrap();
+        assert_eq!((fee.maker, fee.taker), (0.0002, 0.0004));
+
+        let req = &server.requests()[0];
+        assert_eq!(req.path, "/fapi/v1/income");
+        assert_eq!(req.param("symbol").as_deref(), Some("BTCUSDT"));
+        assert_eq!(req.param("limit").as_deref(), Some("1000"));
+    }
+
+    // 每8小时一条资金费率, 按 startTime/endTime/limit 返回
+    fn serve_funding(req: &StubRequest) -> StubResponse {
+        let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+        let end: u64 = req.param("endTime").unwrap().parse().unwrap();
+        let limit: u64 = req.param("limit").unwrap().parse().unwrap();
+        let period = 8 * 3_600_000;
+        let first = start.div_ceil(period) * period;
+        let rates: Vec<String> = (0..limit)
+            .map(|i| first + i * period)
+            .take_while(|ts| *ts <= end)
+            .map(|ts| format!(r#"{{"symbol":"BTCUSDT","fundingTime":{},"fundingRate":"0.00010000","markPrice":""}}"#, ts))
+            .collect();
+        StubResponse::ok(&format!("[{}]", rates.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_funding_rate_range() {
+        let server = StubServer::start(serve_funding).await;
+        let bp = get_stub_client(&server);
+        let period = 8 * 3_600_000;
+        let rates = bp.get_funding_rate_range("BTCUSDT", 0, 1500 * period).await.unwrap();
+        assert_eq!(rates.len(), 1500);
+        assert!(rates.windows(2).all(|w| w[1].funding_time - w[0].funding_time == period));
+        assert_eq!(rates[0].mark_price, None);
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        assert!(reqs.iter().all(|r| r.path == "/fapi/v1/fundingRate" && r.param("limit").as_deref() == Some("1000")));
+    }
+
+    #[tokio::test]
+    async fn test_premium_index_and_open_interest() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/fapi/v1/premiumIndex" => StubResponse::ok(r#"{"symbol":"BTCUSDT","markPrice":"11793.63104562","indexPrice":"11781.80495970","estimatedSettlePrice":"11781.16138815","lastFundingRate":"0.00038246","interestRate":"0.00010000","nextFundingTime":1597392000000,"time":1597370495002}"#),
+            "/fapi/v1/openInterest" => StubResponse::ok(r#"{"openInterest":"10659.509","symbol":"BTCUSDT","time":1589437530011}"#),
+            _ => StubResponse::ok(r#"[{"symbol":"BTCUSDT","sumOpenInterest":"20403.63700000","sumOpenInterestValue":"150570784.07809979","timestamp":1583127900000}]"#),
+        })
         .await;
+        let bp = get_stub_client(&server);
+        let index = bp.get_premium_index("BTCUSDT").await.unwrap();
+        assert_eq!(index.last_funding_rate, Some(0.00038246));
+        let oi = PerpetualAPI::get_open_interest(&bp, "BTCUSDT").await.unwrap();
+        assert_eq!(oi.amount, 10659.509);
+        let hist = bp.get_open_interest_hist("BTCUSDT", "5m", None, None, Some(10)).await.unwrap();
+        assert_eq!(hist[0].sum_open_interest, 20403.637);
+        let req = &server.requests()[2];
+        assert_eq!(req.path, "/futures/data/openInterestHist");
+        assert_eq!(req.param("period").as_deref(), Some("5m"));
+    }
+
+    #[tokio::test]
+    async fn test_futures_data() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/futures/data/takerlongshortRatio" => StubResponse::ok(r#"[{"buySellRatio":"1.5586","buyVol":"387.3300","sellVol":"248.5030","timestamp":1585614900000}]"#),
+            "/futures/data/basis" => StubResponse::ok(r#"[{"indexPrice":"34400.15945055","contractType":"PERPETUAL","basisRate":"0.0004","futuresPrice":"34414.10","annualizedBasisRate":"","basis":"13.94054945","pair":"BTCUSDT","timestamp":1698742800000}]"#),
+            _ => StubResponse::ok(r#"[{"symbol":"BTCUSDT","longShortRatio":"1.4342","longAccount":"0.5891","shortAccount":"0.4108","timestamp":1583139600000}]"#),
+        })
+        .await;
+        let bp = get_stub_client(&server);
+        let ratio = bp
+            .get_long_short_ratio(LongShortRatioKind::TopTraderPositions, "BTCUSDT", "1h", None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(ratio[0].long_short_ratio, 1.4342);
+        let taker = bp.get_taker_volume("BTCUSDT", "1h", None, None, None).await.unwrap();
+        assert_eq!(taker[0].buy_vol, 387.33);
+        let basis = bp.get_basis("BTCUSDT", ContractType::Perpetual, "1h", None, None, 30).await.unwrap();
+        assert_eq!(basis[0].annualized_basis_rate, None);
+        assert_eq!(basis[0].contract_type, ContractType::Perpetual);
+
+        let reqs = server.requests();
+        assert_eq!(reqs[0].path, "/futures/data/topLongShortPositionRatio");
+        assert_eq!(reqs[2].param("contractType").as_deref(), Some("PERPETUAL"));
+        assert_eq!(reqs[2].param("pair").as_deref(), Some("BTCUSDT"));
+        assert!(reqs.iter().all(|r| r.param("signature").is_none()));
+    }
+
+    // 按 startTime/endTime/limit 返回每分钟一根的K线
+    fn serve_klines(req: &StubRequest) -> StubResponse {
+        let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+        let end: u64 = req.param("endTime").unwrap().parse().unwrap();
+        let limit: u64 = req.param("limit").unwrap().parse().unwrap();
+        let first = start.div_ceil(60_000) * 60_000;
+        let bars: Vec<String> = (0..limit)
+            .map(|i| first + i * 60_000)
+            .take_while(|ts| *ts <= end)
+            .map(|ts| format!(r#"[{},"1.0","2.0","0.5","1.5","0",{},"0",0,"0","0","0"]"#, ts, ts + 59_999))
+            .collect();
+        StubResponse::ok(&format!("[{}]", bars.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_klines_range_pagination() {
+        let server = StubServer::start(serve_klines).await;
+        let bp = get_stub_client(&server);
+        let start = 1_600_000_030_000;
+        let end = 1_600_000_000_000 + 4000 * 60_000;
+        let klines = bp.get_klines_range("BTCUSDT", "1m", start, end).await.unwrap();
+        assert_eq!(klines.len(), 3999);
+        assert_eq!(klines[0].ts, 1_600_000_080_000);
+        assert!(klines.windows(2).all(|w| w[1].ts - w[0].ts == 60_000));
+
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 3);
+        assert!(reqs.iter().all(|r| r.path == "/fapi/v1/klines" && r.param("limit").as_deref() == Some("1500")));
+        assert_eq!(reqs[0].param("endTime"), Some((end - 1).to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_price_klines_params() {
+        let server = StubServer::start(serve_klines).await;
+        let bp = get_stub_client(&server);
+        let kinds = [
+            FuturesKline::MarkPrice("BTCUSDT"),
+            FuturesKline::IndexPrice("BTCUSDT"),
+            FuturesKline::Continuous("BTCUSDT", ContractType::CurrentQuarter),
+        ];
+        for kind in kinds.iter() {
+            let klines = bp.get_futures_klines_range(kind, "1m", 0, 120_000).await.unwrap();
+            assert_eq!(klines.len(), 2);
+            assert_eq!(klines[1].close, 1.5);
+        }
+        let reqs = server.requests();
+        assert_eq!(reqs[0].path, "/fapi/v1/markPriceKlines");
+        assert_eq!(reqs[0].param("symbol").as_deref(), Some("BTCUSDT"));
+        assert_eq!(reqs[1].path, "/fapi/v1/indexPriceKlines");
+        assert_eq!(reqs[1].param("pair").as_deref(), Some("BTCUSDT"));
+        assert_eq!(reqs[2].path, "/fapi/v1/continuousKlines");
+        assert_eq!(reqs[2].param("contractType").as_deref(), Some("CURRENT_QUARTER"));
+    }
+
+    // 远低于市价的限价买单, 不会成交, 测试结束前撤销
+    fn far_limit_order() -> OrderRequest {
+        OrderRequest::builder("ETHUSDT", Side::Buy, OrderType::Limit)
+            .quantity(1.1)
+            .price(210.1)
+            .build_unchecked()
     }
 
     #[tokio::main]
     #[test]
+    #[ignore = "needs API_KEY and SEC_KEY in .env"]
     async fn test_send_order() {
         let bp = get_client();
-        let order_status = bp
-            .order(
-                "ETHUSDT",
-                "BUY",
-                "LIMIT",
-                1.1,
-                Some(210.1),
-                "GTC",
-                5000,
-                None,
-                None,
-            )
-            .await;
-        match order_status {
-            Ok(os) => {
-                println!("{:#?}", os);
-            }
-            Err(err) => {
-                println!("{:#?}", err);
-            }
-        }
+        let resp = bp.place_order(&far_limit_order()).await.unwrap();
+        assert_eq!(resp.symbol, "ETHUSDT");
+        assert!(!resp.client_order_id.is_empty());
+        bp.cancel_order("ETHUSDT", Some(resp.order_id), None).await.unwrap();
     }
 
     #[tokio::main]
     #[test]
+    #[ignore = "needs API_KEY and SEC_KEY in .env"]
     async fn test_cancel_order() {
         let bp = get_client();
-        let order_status = bp.cancel_order("ETHUSDT", Some(2163768930), None).await;
-        match order_status {
-            Ok(os) => {
-                println!("{:#?}", os);
-            }
-            Err(err) => {
-                println!("{:#?}", err);
-            }
-        }
+        let resp = bp.place_order(&far_limit_order()).await.unwrap();
+        let order = bp.cancel_order("ETHUSDT", Some(resp.order_id), None).await.unwrap();
+        assert_eq!(order.order_id, resp.order_id);
+        assert_eq!(order.status, OrderStatus::Canceled);
+        // 再次撤销被拒绝
+        let err = bp.cancel_order("ETHUSDT", Some(resp.order_id), None).await.unwrap_err();
+        assert!(err.api_code().is_some());
     }
 
     #[tokio::main]
     #[test]
+    #[ignore = "needs API_KEY and SEC_KEY in .env"]
     async fn test_query_order() {
         let bp = get_client();
-        let order_status = bp
-            .query_order("ETHUSDT", None, Some("bzFftXVJkS3bPyF0MTZm6Q"))
-            .await;
-        match order_status {
-            Ok(os) => {
-                println!("{:#?}", os);
-            }
-            Err(err) => {
-                println!("{:#?}", err);
-            }
-        }
+        let resp = bp.place_order(&far_limit_order()).await.unwrap();
+        let order = bp
+            .query_order("ETHUSDT", None, Some(&resp.client_order_id))
+            .await
+            .unwrap();
+        assert_eq!(order.order_id, resp.order_id);
+        assert_eq!((order.side, order.price, order.orig_qty), (Side::Buy, 210.1, 1.1));
+        bp.cancel_order("ETHUSDT", Some(resp.order_id), None).await.unwrap();
     }
 
     #[tokio::main]
     #[test]
+    #[ignore = "needs API_KEY and SEC_KEY in .env"]
     async fn test_get_ticker() {
         let bp = get_client();
-        let order_status = bp.get_ticker("ETHUSDT").await;
-        match order_status {
-            Ok(os) => {
-                println!("{:#?}", os);
-            }
-            Err(err) => {
-                println!("{:#?}", err);
-            }
-        }
+        let ticker = bp.get_ticker("ETHUSDT").await.unwrap();
+        assert_eq!(ticker.symbol, "ETHUSDT");
+        assert!(ticker.bid_price > 0. && ticker.ask_price >= ticker.bid_price);
     }
 
     #[tokio::main]
     #[test]
+    #[ignore = "needs API_KEY and SEC_KEY in .env"]
     async fn test_get_order_book() {
         let bp = get_client();
-        let order_status = bp.get_order_book("ETHUSDT", None).await;
-        match order_status {
-            Ok(os) => {
-                println!("{:#?}", os);
-            }
-            Err(err) => {
-                println!("{:#?}", err);
-            }
-        }
+        let ob = bp.get_order_book("ETHUSDT", None).await.unwrap();
+        assert!(!ob.bids.is_empty() && !ob.asks.is_empty());
+        assert!(ob.bids[0].price < ob.asks[0].price);
+        assert!(ob.bids.windows(2).all(|w| w[0].price > w[1].price));
     }
 }
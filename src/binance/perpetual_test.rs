@@ -1,6 +1,6 @@
 
 // 响应中如有数组，数组元素以时间升序排列，越早的数据越提前。
 // 所有时间、时间戳均为UNIX时间，单位为毫秒
-const REST_BASE_URL: &'static str = "https://testnet.binancefuture.com";
-const WS_BASE_URL: &'static str = "wss://stream.binancefuture.com";
+const REST_BASE_URL: &str = "https://testnet.binancefuture.com";
+const WS_BASE_URL: &str = "wss://stream.binancefuture.com";
 
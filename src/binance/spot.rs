@@ -1,85 +1,533 @@
 use async_trait::async_trait;
-use crate::rest::{PublicAPI, PrivateAPI};
-use anyhow::Result;
+use crate::rest::limiter::RateLimiter;
+use crate::rest::paginate::{id_range, PageQuery};
+use crate::rest::rclient::{ClientConfig, RestClient};
+use crate::rest::retry::RetryPolicy;
+use crate::ws::user::{ListenKeyEndpoint, UserStream};
+use crate::ws::wclient::WssClient;
+use crate::error::{ApiResult, EdpError, Result};
+use crate::order::{order_id_params, OcoRequest, OrderRequest};
+use crate::traits::{AccountAPI, MarketDataAPI, SpotAPI, TradingAPI};
 use crate::model::{
     KData, 
+    Order,
+    OrderBook,
     SymbolInfo, 
+    Market,
+    SymbolFilters,
     Ticker,
     OrderResp,
     QueryOrderResult,
     CancelOrderResult,
-    Balance
+    CancelReplaceMode,
+    CancelReplaceStatus,
+    OrderList,
+    Fill,
+    Trade,
+    TickerStats,
+    RollingTicker,
+    PriceTicker,
+    AvgPrice,
+    TradeFee,
+    Balance,
+    RateLimit,
+    Side,
 };
+use futures::Stream;
+use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
-const BASE_URL: &'static str = "https://binance.com";
-pub struct BinanceSpot<'a> {
-    pub base_url: &'a str,
-    pub credential: Option<(&'a str, &'a str)>,
+const BASE_URL: &str = "https://api.binance.com";
+const TIME_END_POINT: &str = "/api/v3/time";
+pub struct BinanceSpot {
+    rest_client: RestClient,
 }
 
-pub struct BinanceSpotBuilder<'a> {
-    pub base_url: &'a str,
-    pub credential: Option<(&'a str, &'a str)>,
+pub struct BinanceSpotBuilder {
+    base_url: String,
+    credential: Option<(String, String)>,
+    config: ClientConfig,
+    limiter: Option<Arc<RateLimiter>>,
+    retry: RetryPolicy,
+    recv_window: Option<u64>,
 }
 
-impl<'a> BinanceSpot<'a> {
-    fn new() -> BinanceSpotBuilder<'a> {
+impl BinanceSpot {
+    pub fn builder() -> BinanceSpotBuilder {
         BinanceSpotBuilder {
-            base_url: BASE_URL,
+            base_url: BASE_URL.to_string(),
             credential: None,
+            config: ClientConfig::default(),
+            limiter: None,
+            retry: RetryPolicy::default(),
+            recv_window: None,
         }
     }
+
+    pub fn new(base_url: String) -> Self {
+        Self {
+            rest_client: RestClient::new(base_url).with_time_sync(TIME_END_POINT),
+        }
+    }
+
+    pub fn with_key(base_url: String, keys: (String, String)) -> Self {
+        Self {
+            rest_client: RestClient::with_key(base_url, keys).with_time_sync(TIME_END_POINT),
+        }
+    }
+
+    // 订单/余额变化推送, wss_client 为现货行情地址 wss://stream.binance.com:9443
+    pub async fn user_stream(&self, wss_client: &WssClient) -> Result<UserStream> {
+        wss_client
+            .user_stream(self.rest_client.clone(), ListenKeyEndpoint::Spot)
+            .await
+    }
+
+    pub async fn place_oco(&self, oco: &OcoRequest) -> Result<OrderList> {
+        let end_point = "/api/v3/order/oco";
+        let url = self.rest_client.build_request_string(end_point, oco.to_params(), true)?;
+        let resp = self.rest_client.post_sign(url).await?;
+        let list: OrderList = serde_json::from_str(&resp)?;
+        Ok(list)
+    }
+
+    // 撤销整个订单列表, 撤销其中任意一个订单也会撤销整个列表
+    pub async fn cancel_order_list(
+        &self,
+        symbol: &str,
+        order_list_id: Option<u64>,
+        list_client_order_id: Option<&str>,
+    ) -> Result<OrderList> {
+        let mut params = order_list_params(order_list_id, list_client_order_id)?;
+        params.insert("symbol".to_string(), symbol.to_string());
+        let end_point = "/api/v3/orderList";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.delete_sign(url).await?;
+        let list: OrderList = serde_json::from_str(&resp)?;
+        Ok(list)
+    }
+
+    // 不返回 order_reports, 各订单状态需要用 query_order 查询
+    pub async fn query_order_list(
+        &self,
+        order_list_id: Option<u64>,
+        list_client_order_id: Option<&str>,
+    ) -> Result<OrderList> {
+        let params = order_list_params(order_list_id, list_client_order_id)?;
+        let end_point = "/api/v3/orderList";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let list: OrderList = serde_json::from_str(&resp)?;
+        Ok(list)
+    }
+
+    // 撤单并下新单, 在交易所一次完成, 用于改价
+    // 撤单或下单失败时交易所返回错误码 -2021/-2022, 这里仍然返回 Ok, 结果见 canceled/new_order
+    pub async fn cancel_replace(
+        &self,
+        cancel_order_id: Option<u64>,
+        cancel_client_order_id: Option<&str>,
+        order: &OrderRequest,
+        mode: CancelReplaceMode,
+    ) -> Result<CancelReplaceResult> {
+        let mut params = order.to_params();
+        params.insert("cancelReplaceMode".to_string(), mode.to_string());
+        match (cancel_order_id, cancel_client_order_id) {
+            (Some(id), _) => {
+                params.insert("cancelOrderId".to_string(), id.to_string());
+            }
+            (None, Some(id)) => {
+                params.insert("cancelOrigClientOrderId".to_string(), id.to_string());
+            }
+            (None, None) => {
+                return Err(EdpError::InvalidOrder("cancel order_id or client_order_id is required".to_string()))
+            }
+        }
+        let end_point = "/api/v3/order/cancelReplace";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let raw: RawCancelReplace = match self.rest_client.post_sign(url).await {
+            Ok(resp) => serde_json::from_str(&resp)?,
+            Err(err) => match err {
+                EdpError::Api { data: Some(ref data), .. } => match RawCancelReplace::deserialize(data) {
+                    Ok(raw) => raw,
+                    Err(_) => return Err(err),
+                },
+                _ => return Err(err),
+            },
+        };
+        Ok(CancelReplaceResult::from(raw))
+    }
+
+    pub async fn get_24h_stats(&self, symbol: &str) -> Result<TickerStats> {
+        self.get_public("/api/v3/ticker/24hr", symbol_param(symbol)).await
+    }
+
+    // 全部交易对, 权重80
+    pub async fn get_all_24h_stats(&self) -> Result<Vec<TickerStats>> {
+        self.get_public("/api/v3/ticker/24hr", BTreeMap::new()).await
+    }
+
+    pub async fn get_price(&self, symbol: &str) -> Result<PriceTicker> {
+        self.get_public("/api/v3/ticker/price", symbol_param(symbol)).await
+    }
+
+    pub async fn get_all_prices(&self) -> Result<Vec<PriceTicker>> {
+        self.get_public("/api/v3/ticker/price", BTreeMap::new()).await
+    }
+
+    // 滚动窗口统计, 不支持查询全部交易对, 每次最多100个, 超过时分多次请求
+    // window_size 如 "15m", "4h", "1d", 交易所默认1d
+    pub async fn get_rolling_tickers(&self, symbols: &[&str], window_size: &str) -> Result<Vec<RollingTicker>> {
+        let mut tickers = Vec::with_capacity(symbols.len());
+        for chunk in symbols.chunks(ROLLING_TICKER_SYMBOL_LIMIT) {
+            let mut params = BTreeMap::new();
+            params.insert("symbols".to_string(), serde_json::to_string(chunk)?);
+            params.insert("windowSize".to_string(), window_size.to_string());
+            let page: Vec<RollingTicker> = self.get_public("/api/v3/ticker", params).await?;
+            tickers.extend(page);
+        }
+        Ok(tickers)
+    }
+
+    pub async fn get_avg_price(&self, symbol: &str) -> Result<AvgPrice> {
+        self.get_public("/api/v3/avgPrice", symbol_param(symbol)).await
+    }
+
+    async fn get_public<T: DeserializeOwned>(&self, end_point: &str, params: BTreeMap<String, String>) -> Result<T> {
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        Ok(serde_json::from_str(&resp)?)
+    }
+
+    // [start_time, end_time) 内的全部逐笔成交
+    // historicalTrades 只能按 fromId 查询, 先用 aggTrades 找到窗口内第一笔成交的id
+    pub fn trades<'a>(&'a self, symbol: &'a str, start_time: u64, end_time: u64) -> impl Stream<Item = Result<Trade>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            AGG_TRADES_WINDOW,
+            TRADES_PAGE_LIMIT,
+            true,
+            |t: &Trade| (t.time, t.id),
+            move |query, limit| async move {
+                let from_id = match query {
+                    PageQuery::Time { .. } => match self.get_agg_trades(symbol, query, 1).await?.first() {
+                        Some(t) => t.first_trade_id,
+                        None => return Ok(Vec::new()),
+                    },
+                    PageQuery::FromId(id) => id,
+                };
+                self.get_historical_trades(symbol, Some(from_id), limit).await
+            },
+        )
+    }
+
+    // 不指定 from_id 时返回最近的成交, 需要 API key
+    pub async fn get_historical_trades(&self, symbol: &str, from_id: Option<u64>, limit: u64) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        if let Some(id) = from_id {
+            params.insert("fromId".to_string(), id.to_string());
+        }
+        let end_point = "/api/v3/historicalTrades";
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get_key(url).await?;
+        let trades: Vec<RawTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
+    // [start_time, end_time) 内的归集成交, 时间条件每次最多查询1小时
+    pub fn agg_trades<'a>(&'a self, symbol: &'a str, start_time: u64, end_time: u64) -> impl Stream<Item = Result<Trade>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            AGG_TRADES_WINDOW,
+            TRADES_PAGE_LIMIT,
+            true,
+            |t: &Trade| (t.time, t.id),
+            move |query, limit| self.get_agg_trades(symbol, query, limit),
+        )
+    }
+
+    pub async fn get_agg_trades(&self, symbol: &str, query: PageQuery, limit: u64) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        query.apply(&mut params);
+        let end_point = "/api/v3/aggTrades";
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let trades: Vec<RawAggTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
+    // [start_time, end_time) 内的成交, 按成交时间升序
+    // 每次最多查询24小时, 一页装不下时改用 fromId 翻页
+    pub fn fills<'a>(&'a self, symbol: &'a str, start_time: u64, end_time: u64) -> impl Stream<Item = Result<Fill>> + 'a {
+        id_range(
+            start_time,
+            end_time,
+            MY_TRADES_WINDOW,
+            MY_TRADES_PAGE_LIMIT,
+            true,
+            |f: &Fill| (f.time, f.id),
+            move |query, limit| self.get_my_trades(symbol, query, limit),
+        )
+    }
+
+    pub async fn get_my_trades(&self, symbol: &str, query: PageQuery, limit: u64) -> Result<Vec<Fill>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("limit".to_string(), limit.to_string());
+        query.apply(&mut params);
+        let end_point = "/api/v3/myTrades";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let trades: Vec<RawMyTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Fill::from).collect())
+    }
+
+    // 不指定 symbol 时返回全部交易对
+    pub async fn get_trade_fee(&self, symbol: Option<&str>) -> Result<Vec<TradeFee>> {
+        let mut params = BTreeMap::new();
+        if let Some(s) = symbol {
+            params.insert("symbol".to_string(), s.to_string());
+        }
+        let end_point = "/sapi/v1/asset/tradeFee";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let fees: Vec<TradeFee> = serde_json::from_str(&resp)?;
+        Ok(fees)
+    }
+
+    // 只校验参数和签名, 不会下单
+    pub async fn test_order(&self, order: &OrderRequest) -> Result<()> {
+        let end_point = "/api/v3/order/test";
+        let url = self.rest_client.build_request_string(end_point, order.to_params(), true)?;
+        self.rest_client.post_sign(url).await?;
+        Ok(())
+    }
+}
+
+fn symbol_param(symbol: &str) -> BTreeMap<String, String> {
+    let mut params = BTreeMap::new();
+    params.insert("symbol".to_string(), symbol.to_string());
+    params
 }
 
-impl<'a> BinanceSpotBuilder<'a> {
-    fn base_url(&mut self, base_url: &'a str) -> &mut Self {
-        self.base_url = base_url;
+// 订单列表的撤销/查询参数, orderListId 优先
+fn order_list_params(
+    order_list_id: Option<u64>,
+    list_client_order_id: Option<&str>,
+) -> Result<BTreeMap<String, String>> {
+    let mut params = BTreeMap::new();
+    match (order_list_id, list_client_order_id) {
+        (Some(id), _) => {
+            params.insert("orderListId".to_string(), id.to_string());
+        }
+        (None, Some(id)) => {
+            params.insert("listClientOrderId".to_string(), id.to_string());
+        }
+        (None, None) => {
+            return Err(EdpError::InvalidOrder(
+                "order_list_id or list_client_order_id is required".to_string(),
+            ))
+        }
+    }
+    Ok(params)
+}
+
+const MY_TRADES_WINDOW: u64 = 24 * 3_600_000;
+const MY_TRADES_PAGE_LIMIT: u64 = 1000;
+const AGG_TRADES_WINDOW: u64 = 3_600_000;
+const ROLLING_TICKER_SYMBOL_LIMIT: usize = 100;
+const TRADES_PAGE_LIMIT: u64 = 1000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMyTrade {
+    symbol: String,
+    id: u64,
+    order_id: u64,
+    #[serde(with = "string_or_float")]
+    price: f64,
+    #[serde(with = "string_or_float")]
+    qty: f64,
+    #[serde(with = "string_or_float")]
+    quote_qty: f64,
+    #[serde(with = "string_or_float")]
+    commission: f64,
+    commission_asset: String,
+    time: u64,
+    is_buyer: bool,
+    is_maker: bool,
+}
+
+impl From<RawMyTrade> for Fill {
+    fn from(raw: RawMyTrade) -> Self {
+        Self {
+            symbol: raw.symbol,
+            id: raw.id,
+            order_id: raw.order_id,
+            side: if raw.is_buyer { Side::Buy } else { Side::Sell },
+            price: raw.price,
+            qty: raw.qty,
+            quote_qty: raw.quote_qty,
+            commission: raw.commission,
+            commission_asset: raw.commission_asset,
+            is_maker: raw.is_maker,
+            time: raw.time,
+            realized_pnl: None,
+            position_side: None,
+        }
+    }
+}
+
+// cancelReplace 的结果, 撤单和下单各自可能失败
+#[derive(Debug)]
+pub struct CancelReplaceResult {
+    pub cancel_result: CancelReplaceStatus,
+    pub new_order_result: CancelReplaceStatus,
+    pub canceled: Result<Order>,
+    // 撤单失败且为 StopOnFailure 时没有下新单
+    pub new_order: Option<Result<OrderResp>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCancelReplace {
+    cancel_result: CancelReplaceStatus,
+    new_order_result: CancelReplaceStatus,
+    cancel_response: ApiResult<CancelOrderResult>,
+    new_order_response: Option<ApiResult<OrderResp>>,
+}
+
+impl From<RawCancelReplace> for CancelReplaceResult {
+    fn from(raw: RawCancelReplace) -> Self {
+        Self {
+            cancel_result: raw.cancel_result,
+            new_order_result: raw.new_order_result,
+            canceled: raw.cancel_response.into_result().map(Order::from),
+            new_order: raw.new_order_response.map(ApiResult::into_result),
+        }
+    }
+}
+
+impl BinanceSpotBuilder {
+    pub fn base_url(&mut self, base_url: &str) -> &mut Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    pub fn credential(&mut self, api_key: &str, sec_key: &str) -> &mut Self {
+        self.credential = Some((api_key.to_string(), sec_key.to_string()));
+        self
+    }
+
+    pub fn config(&mut self, config: ClientConfig) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    pub fn limiter(&mut self, limiter: Arc<RateLimiter>) -> &mut Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    pub fn retry(&mut self, retry: RetryPolicy) -> &mut Self {
+        self.retry = retry;
         self
     }
 
-    fn credential(&mut self, api_key: &'a str, sec_key: &'a str) -> &mut Self {
-        self.credential = Some((api_key, sec_key));
+    pub fn recv_window(&mut self, recv_window: u64) -> &mut Self {
+        self.recv_window = Some(recv_window);
         self
     }
 
-    fn build(&self) -> BinanceSpot {
-        BinanceSpot {
-            base_url: self.base_url.clone(),
-            credential: self.credential.clone()
+    pub fn build(&self) -> Result<BinanceSpot> {
+        let mut rest_client =
+            RestClient::with_config(self.base_url.clone(), self.credential.clone(), &self.config)?
+                .with_retry(self.retry.clone())
+                .with_time_sync(TIME_END_POINT);
+        if let Some(ref limiter) = self.limiter {
+            // 额度为空时第一次请求前从 exchangeInfo 读取
+            rest_client = rest_client.with_limiter(limiter.clone()).with_rate_limits("/api/v3/exchangeInfo");
         }
+        if let Some(recv_window) = self.recv_window {
+            rest_client = rest_client.with_recv_window(recv_window);
+        }
+        Ok(BinanceSpot { rest_client })
+    }
+}
+
+#[async_trait]
+impl SpotAPI for BinanceSpot {
+    async fn get_rate_limits(&self) -> Result<Vec<RateLimit>> {
+        let end_point = "/api/v3/exchangeInfo";
+        let url = self.rest_client.build_request_string(end_point, BTreeMap::new(), false)?;
+        let resp = self.rest_client.get(url).await?;
+        let raw_symbol_info: RawSymbolInfoResp = serde_json::from_str(&resp)?;
+        Ok(raw_symbol_info.rate_limits)
     }
 }
 
 #[async_trait]
-impl<'a> PublicAPI for BinanceSpot<'a> {
+impl MarketDataAPI for BinanceSpot {
 
     async fn ping(&self) -> Result<()> {
         let end_point = "/api/v3/ping";
-        let url = format!("{}{}", self.base_url, end_point);
-        let _ = reqwest::get(&url).await?;
+        let url = self.rest_client.build_request_string(end_point, BTreeMap::new(), false)?;
+        self.rest_client.get(url).await?;
         Ok(())
     }
 
     async fn get_symbols(&self) -> Result<Vec<SymbolInfo>> {
         let end_point = "/api/v3/exchangeInfo";
-        let url = format!("{}{}", self.base_url, end_point);
-        let resp =  reqwest::get(&url).await?; 
-        let resp_text = resp.text().await?;
-        let raw_symbol_info: RawSymbolInfoResp= serde_json::from_str(&resp_text)?;
+        let url = self.rest_client.build_request_string(end_point, BTreeMap::new(), false)?;
+        let resp = self.rest_client.get(url).await?;
+        let raw_symbol_info: RawSymbolInfoResp = serde_json::from_str(&resp)?;
         Ok(Vec::<SymbolInfo>::from(raw_symbol_info))
-
     }
 
     async fn get_ticker(&self, symbol: &str) -> Result<Ticker> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
         let end_point = "/api/v3/ticker/bookTicker";
-        let url = format!("{}{}?symbol={}", self.base_url, end_point, symbol);
-        let resp = reqwest::get(&url).await?;
-        let resp_text = resp.text().await?;
-        let ticker: Ticker = serde_json::from_str(&resp_text)?;
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let ticker: Ticker = serde_json::from_str(&resp)?;
         Ok(ticker)
     }
 
+    async fn get_order_book(&self, symbol: &str, limit: Option<u64>) -> Result<OrderBook> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        if let Some(lim) = limit {
+            params.insert("limit".to_string(), lim.to_string());
+        }
+        let end_point = "/api/v3/depth";
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let order_book: OrderBook = serde_json::from_str(&resp)?;
+        Ok(order_book)
+    }
+
+    async fn get_trades(&self, symbol: &str, limit: Option<u64>) -> Result<Vec<Trade>> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        if let Some(lim) = limit {
+            params.insert("limit".to_string(), lim.to_string());
+        }
+        let end_point = "/api/v3/trades";
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let trades: Vec<RawTrade> = serde_json::from_str(&resp)?;
+        Ok(trades.into_iter().map(Trade::from).collect())
+    }
+
     async fn get_klines(
         &self,
         symbol: &str,
@@ -88,42 +536,77 @@ impl<'a> PublicAPI for BinanceSpot<'a> {
         end_time: Option<u64>,
         limit: Option<u64>,
     ) -> Result<Vec<KData>> {
-        let end_point = "/api/v3/klines";
-        let mut url = format!("{}{}?symbol={}&interval={}", self.base_url, end_point, symbol, interval);
-        println!("{}", url);
-        start_time.map_or_else(||{}, |start_ts| url.push_str(format!("startTime={}", start_ts).as_str()));
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), symbol.to_string());
+        params.insert("interval".to_string(), interval.to_string());
+        if let Some(start_ts) = start_time {
+            params.insert("startTime".to_string(), start_ts.to_string());
+        }
         if let Some(end_ts) = end_time {
-            url.push_str(format!("&endTime={}", end_ts).as_str())
+            params.insert("endTime".to_string(), end_ts.to_string());
         }
         if let Some(lim) = limit {
-            url.push_str(format!("&limit={}", lim).as_str());
+            params.insert("limit".to_string(), lim.to_string());
         }
-        println!("{}", url);
-        let resp_text = reqwest::get(&url).await?.text().await?;
-        let kline: Vec<RawKResp> = serde_json::from_str(&resp_text)?;
+        let end_point = "/api/v3/klines";
+        let url = self.rest_client.build_request_string(end_point, params, false)?;
+        let resp = self.rest_client.get(url).await?;
+        let kline: Vec<RawKResp> = serde_json::from_str(&resp)?;
         Ok(kline.iter().map(|rkp| KData::from(*rkp)).collect())
     }
-
 }
 
 #[async_trait]
-impl<'a> PrivateAPI for BinanceSpot<'a> {
-    async fn new_order(&self, symbol: &str, qty: f64, price: f64, type_: &str, side: &str) -> Result<OrderResp> {
+impl TradingAPI for BinanceSpot {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderResp> {
         let end_point = "/api/v3/order";
-        
-        unimplemented!()
+        let url = self.rest_client.build_request_string(end_point, order.to_params(), true)?;
+        let resp = self.rest_client.post_sign(url).await?;
+        let order_resp: OrderResp = serde_json::from_str(&resp)?;
+        Ok(order_resp)
     }
 
-    async fn cancel_order(&self, symbol: &str, order_id: u32) -> Result<CancelOrderResult> {
-        unimplemented!()
+    async fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order> {
+        let params = order_id_params(symbol, order_id, client_order_id)?;
+        let end_point = "/api/v3/order";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.delete_sign(url).await?;
+        let result: CancelOrderResult = serde_json::from_str(&resp)?;
+        Ok(Order::from(result))
     }
 
-    async fn query_order(&self, symbol: &str, order_id: u32) -> Result<QueryOrderResult> {
-        unimplemented!()
+    async fn query_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order> {
+        let params = order_id_params(symbol, order_id, client_order_id)?;
+        let end_point = "/api/v3/order";
+        let url = self.rest_client.build_request_string(end_point, params, true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let result: QueryOrderResult = serde_json::from_str(&resp)?;
+        Ok(Order::from(result))
     }
+}
 
-    async fn query_balance(&self) -> Result<Balance> {
-        unimplemented!()
+#[async_trait]
+impl AccountAPI for BinanceSpot {
+    async fn get_balances(&self) -> Result<Vec<Balance>> {
+        let end_point = "/api/v3/account";
+        let url = self.rest_client.build_request_string(end_point, BTreeMap::new(), true)?;
+        let resp = self.rest_client.get_sign(url).await?;
+        let account: RawAccountResp = serde_json::from_str(&resp)?;
+        Ok(account
+            .balances
+            .into_iter()
+            .filter(|b| b.free != 0. || b.locked != 0.)
+            .collect())
     }
 }
 
@@ -134,12 +617,14 @@ impl From<RawSymbolInfoResp> for Vec<SymbolInfo> {
         for raw_symbol in raw.symbols {
             let symbol_info = SymbolInfo {
                 symbol: raw_symbol.symbol,
+                market: Market::Spot,
                 base: raw_symbol.base_asset,
                 quote: raw_symbol.quote_asset,
                 price_precision: raw_symbol.quote_precision,
                 quantity_precision: raw_symbol.base_asset_precision,
                 base_precision: raw_symbol.base_asset_precision,
                 quote_precision: raw_symbol.quote_precision,
+                filters: SymbolFilters::from(raw_symbol.filters.as_slice()),
             };
             info_vec.push(symbol_info);
         }
@@ -147,6 +632,33 @@ impl From<RawSymbolInfoResp> for Vec<SymbolInfo> {
     }
 }
 
+impl From<&[Filter]> for SymbolFilters {
+    fn from(filters: &[Filter]) -> Self {
+        let num = |v: &Option<String>| v.as_ref().and_then(|s| s.parse::<f64>().ok()).unwrap_or(0.);
+        let mut sf = SymbolFilters::default();
+        for f in filters {
+            match f.filter_type.as_str() {
+                "PRICE_FILTER" => {
+                    sf.min_price = num(&f.min_price);
+                    sf.max_price = num(&f.max_price);
+                    sf.tick_size = num(&f.tick_size);
+                }
+                "LOT_SIZE" => {
+                    sf.min_qty = num(&f.min_qty);
+                    sf.max_qty = num(&f.max_qty);
+                    sf.step_size = num(&f.step_size);
+                }
+                // 现货为 MIN_NOTIONAL/NOTIONAL 的 minNotional, 合约为 MIN_NOTIONAL 的 notional
+                "MIN_NOTIONAL" | "NOTIONAL" => {
+                    sf.min_notional = num(&f.min_notional).max(num(&f.notional));
+                }
+                _ => {}
+            }
+        }
+        sf
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawSymbolInfoResp {
@@ -157,15 +669,6 @@ pub struct RawSymbolInfoResp {
     pub symbols: Vec<Symbol>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct RateLimit {
-    pub rate_limit_type: String,
-    pub interval: String,
-    pub interval_num: i64,
-    pub limit: i64,
-}
-
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Symbol {
@@ -202,12 +705,91 @@ pub struct Filter {
     pub max_qty: Option<String>,
     pub step_size: Option<String>,
     pub min_notional: Option<String>,
+    pub notional: Option<String>,
     pub apply_to_market: Option<bool>,
     pub limit: Option<i64>,
     pub max_num_orders: Option<i64>,
     pub max_num_algo_orders: Option<i64>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawAccountResp {
+    pub can_trade: bool,
+    pub can_withdraw: bool,
+    pub can_deposit: bool,
+    pub update_time: i64,
+    pub balances: Vec<Balance>,
+}
+
+// 现货与合约的 trades/historicalTrades 格式相同
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawTrade {
+    pub id: u64,
+    #[serde(with = "string_or_float")]
+    pub price: f64,
+    #[serde(with = "string_or_float")]
+    pub qty: f64,
+    pub time: u64,
+    pub is_buyer_maker: bool,
+}
+
+impl From<RawTrade> for Trade {
+    fn from(raw: RawTrade) -> Self {
+        Self {
+            id: raw.id,
+            price: raw.price,
+            qty: raw.qty,
+            time: raw.time,
+            side: taker_side(raw.is_buyer_maker),
+            first_trade_id: raw.id,
+            last_trade_id: raw.id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawAggTrade {
+    #[serde(rename = "a")]
+    pub agg_trade_id: u64,
+    #[serde(rename = "p", with = "string_or_float")]
+    pub price: f64,
+    #[serde(rename = "q", with = "string_or_float")]
+    pub qty: f64,
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
+    #[serde(rename = "T")]
+    pub time: u64,
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+impl From<RawAggTrade> for Trade {
+    fn from(raw: RawAggTrade) -> Self {
+        Self {
+            id: raw.agg_trade_id,
+            price: raw.price,
+            qty: raw.qty,
+            time: raw.time,
+            side: taker_side(raw.is_buyer_maker),
+            first_trade_id: raw.first_trade_id,
+            last_trade_id: raw.last_trade_id,
+        }
+    }
+}
+
+// 买方是maker时主动方为卖方
+fn taker_side(is_buyer_maker: bool) -> Side {
+    if is_buyer_maker {
+        Side::Sell
+    } else {
+        Side::Buy
+    }
+}
+
 #[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RawKResp {
     ts: u64,
@@ -280,23 +862,489 @@ mod string_or_float {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{ApiErrorCode, EdpError};
+    use crate::model::{ListOrderStatus, ListStatusType, OrderStatus, OrderType};
+    use crate::rest::stub::{StubRequest, StubResponse, StubServer};
+    use futures::TryStreamExt;
+    use ring::hmac;
+
+    const API_KEY: &str = "test-api-key";
+    const SEC_KEY: &str = "test-sec-key";
+
+    fn get_client(server: &StubServer) -> BinanceSpot {
+        BinanceSpot::with_key(server.url(), (API_KEY.to_string(), SEC_KEY.to_string()))
+    }
 
+    fn assert_signed(req: &StubRequest) {
+        assert_eq!(req.headers.get("x-mbx-apikey").map(String::as_str), Some(API_KEY));
+        let (payload, signature) = req.query.split_at(req.query.find("&signature=").unwrap());
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SEC_KEY.as_bytes());
+        let expected = hex::encode(hmac::sign(&key, payload.as_bytes()));
+        assert_eq!(&signature["&signature=".len()..], expected);
+        assert!(req.param("timestamp").is_some());
+    }
 
     #[tokio::test]
-    async fn test_kline() {
-        let binance = BinanceSpot {
-        base_url: "https://api.binance.com",
-        credential: None
+    async fn test_place_order() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"{"symbol":"BTCUSDT","orderId":28,"orderListId":-1,"clientOrderId":"6gCrw2kRUAF9CvJDGP16IP","transactTime":1507725176595}"#)
+        })
+        .await;
+        let binance = get_client(&server);
+        let order = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.5)
+            .price(9000.5)
+            .build_unchecked();
+        let resp = binance.place_order(&order).await.unwrap();
+        assert_eq!(resp.order_id, 28);
+        assert_eq!(resp.client_order_id, "6gCrw2kRUAF9CvJDGP16IP");
+
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 1);
+        assert_eq!(reqs[0].method, "POST");
+        assert_eq!(reqs[0].path, "/api/v3/order");
+        assert_eq!(reqs[0].param("price").as_deref(), Some("9000.5"));
+        assert_eq!(reqs[0].param("timeInForce").as_deref(), Some("GTC"));
+        assert_signed(&reqs[0]);
+    }
+
+    #[tokio::test]
+    async fn test_market_order_has_no_price() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"{"symbol":"BTCUSDT","orderId":29,"clientOrderId":"x","transactTime":1}"#)
+        })
+        .await;
+        let binance = get_client(&server);
+        let order = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Market)
+            .quantity(1.)
+            .build_unchecked();
+        binance.place_order(&order).await.unwrap();
+        let req = &server.requests()[0];
+        assert_eq!(req.param("price"), None);
+        assert_eq!(req.param("timeInForce"), None);
+        assert_eq!(req.param("side").as_deref(), Some("SELL"));
+    }
+
+    #[tokio::test]
+    async fn test_place_validated_order() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/api/v3/exchangeInfo" => StubResponse::ok(r#"{"timezone":"UTC","serverTime":1565246363776,"rateLimits":[],"exchangeFilters":[],"symbols":[{"symbol":"ETHBTC","status":"TRADING","baseAsset":"ETH","baseAssetPrecision":8,"quoteAsset":"BTC","quotePrecision":8,"quoteAssetPrecision":8,"baseCommissionPrecision":8,"quoteCommissionPrecision":8,"orderTypes":["LIMIT","MARKET"],"icebergAllowed":true,"ocoAllowed":true,"quoteOrderQtyMarketAllowed":true,"isSpotTradingAllowed":true,"isMarginTradingAllowed":true,"filters":[{"filterType":"PRICE_FILTER","minPrice":"0.00000100","maxPrice":"100000.00000000","tickSize":"0.00000100"},{"filterType":"LOT_SIZE","minQty":"0.00100000","maxQty":"100000.00000000","stepSize":"0.00100000"},{"filterType":"MIN_NOTIONAL","minNotional":"0.00010000","applyToMarket":true,"avgPriceMins":5}],"permissions":["SPOT","MARGIN"]}]}"#),
+            _ => StubResponse::ok(r#"{"symbol":"ETHBTC","orderId":7,"clientOrderId":"abc","transactTime":1}"#),
+        })
+        .await;
+        let binance = get_client(&server);
+        let symbols = binance.get_symbols().await.unwrap();
+        assert_eq!(symbols[0].filters.tick_size, 0.000001);
+        assert_eq!(symbols[0].filters.min_notional, 0.0001);
+
+        let order = OrderRequest::builder("ETHBTC", Side::Buy, OrderType::Limit)
+            .quantity(1.23456)
+            .price(0.0712345678)
+            .client_order_id("abc")
+            .build(&symbols[0])
+            .unwrap();
+        let resp = binance.place_order(&order).await.unwrap();
+        assert_eq!(resp.order_id, 7);
+
+        let req = &server.requests()[1];
+        assert_eq!(req.param("quantity").as_deref(), Some("1.234"));
+        assert_eq!(req.param("price").as_deref(), Some("0.071235"));
+        assert_eq!(req.param("newClientOrderId").as_deref(), Some("abc"));
+        assert_signed(req);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_order() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"{"symbol":"LTCBTC","origClientOrderId":"myOrder1","orderId":4,"orderListId":-1,"clientOrderId":"cancelMyOrder1","price":"2.00000000","origQty":"1.00000000","executedQty":"0.00000000","cummulativeQuoteQty":"0.00000000","status":"CANCELED","timeInForce":"GTC","type":"LIMIT","side":"BUY"}"#)
+        })
+        .await;
+        let binance = get_client(&server);
+        let resp = binance.cancel_order("LTCBTC", Some(4), None).await.unwrap();
+        assert_eq!(resp.status, OrderStatus::Canceled);
+        assert_eq!(resp.client_order_id, "myOrder1");
+        assert_eq!(resp.price, 2.);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.method, "DELETE");
+        assert_eq!(req.path, "/api/v3/order");
+        assert_eq!(req.param("orderId").as_deref(), Some("4"));
+        assert_signed(req);
+    }
+
+    const OCO_JSON: &str = r#"{"orderListId":0,"contingencyType":"OCO","listStatusType":"EXEC_STARTED","listOrderStatus":"EXECUTING","listClientOrderId":"JYVpp3F0f5CAG15DhtrqLp","transactionTime":1563417480525,"symbol":"LTCBTC","orders":[{"symbol":"LTCBTC","orderId":2,"clientOrderId":"Kk7sqHb9J6mJWTMDVW7Vos"},{"symbol":"LTCBTC","orderId":3,"clientOrderId":"xTXKaGYd4bluPVp78IVRvl"}],"orderReports":[{"symbol":"LTCBTC","orderId":2,"orderListId":0,"clientOrderId":"Kk7sqHb9J6mJWTMDVW7Vos","transactTime":1563417480525,"price":"0.000000","origQty":"0.624363","executedQty":"0.000000","cummulativeQuoteQty":"0.000000","status":"NEW","timeInForce":"GTC","type":"STOP_LOSS","side":"BUY","stopPrice":"0.960664"},{"symbol":"LTCBTC","orderId":3,"orderListId":0,"clientOrderId":"xTXKaGYd4bluPVp78IVRvl","transactTime":1563417480525,"price":"0.036435","origQty":"0.624363","executedQty":"0.000000","cummulativeQuoteQty":"0.000000","status":"NEW","timeInForce":"GTC","type":"LIMIT_MAKER","side":"BUY"}]}"#;
+
+    #[tokio::test]
+    async fn test_place_oco() {
+        let server = StubServer::start(|_| StubResponse::ok(OCO_JSON)).await;
+        let binance = get_client(&server);
+        let oco = OcoRequest::builder("LTCBTC", Side::Buy)
+            .quantity(0.624363)
+            .price(0.036435)
+            .stop_price(0.960664)
+            .build_unchecked();
+        let list = binance.place_oco(&oco).await.unwrap();
+        assert_eq!(list.list_order_status, ListOrderStatus::Executing);
+        assert_eq!(list.orders.len(), 2);
+        assert_eq!(list.order_reports[0].stop_price, Some(0.960664));
+        assert_eq!(list.order_reports[1].stop_price, None);
+        let limit = Order::from(list.order_reports[1].clone());
+        assert_eq!((limit.type_field, limit.price), (OrderType::LimitMaker, 0.036435));
+
+        let req = &server.requests()[0];
+        assert_eq!((req.method.as_str(), req.path.as_str()), ("POST", "/api/v3/order/oco"));
+        assert_eq!(req.param("stopPrice").as_deref(), Some("0.960664"));
+        assert_eq!(req.param("stopLimitPrice"), None);
+        assert_signed(req);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_query_order_list() {
+        let server = StubServer::start(|req| match req.method.as_str() {
+            "DELETE" => StubResponse::ok(r#"{"orderListId":0,"contingencyType":"OCO","listStatusType":"ALL_DONE","listOrderStatus":"ALL_DONE","listClientOrderId":"C3wyj4WVEktd7u9aVBRXcN","transactionTime":1574040868128,"symbol":"LTCBTC","orders":[{"symbol":"LTCBTC","orderId":2,"clientOrderId":"pO9ufTiFGg3nw2fOdgeOXa"}],"orderReports":[{"symbol":"LTCBTC","origClientOrderId":"pO9ufTiFGg3nw2fOdgeOXa","orderId":2,"orderListId":0,"clientOrderId":"unfWT8ig8i0uj6lPuYLez6","transactTime":1688005070874,"price":"1.00000000","origQty":"10.00000000","executedQty":"0.00000000","cummulativeQuoteQty":"0.00000000","status":"CANCELED","timeInForce":"GTC","type":"STOP_LOSS_LIMIT","side":"SELL","stopPrice":"1.00000000"}]}"#),
+            _ => StubResponse::ok(r#"{"orderListId":27,"contingencyType":"OCO","listStatusType":"EXEC_STARTED","listOrderStatus":"EXECUTING","listClientOrderId":"h2USkA5YQpaXHPIrkd96xE","transactionTime":1565245656253,"symbol":"LTCBTC","orders":[{"symbol":"LTCBTC","orderId":4,"clientOrderId":"qD1gy3kc3Gx0rihm9Y3xwS"},{"symbol":"LTCBTC","orderId":5,"clientOrderId":"ARzZ9I00CPM8i3NhmU9Ega"}]}"#),
+        })
+        .await;
+        let binance = get_client(&server);
+        let list = binance.cancel_order_list("LTCBTC", None, Some("C3wyj4WVEktd7u9aVBRXcN")).await.unwrap();
+        assert_eq!(list.list_status_type, ListStatusType::AllDone);
+        let canceled = Order::from(list.order_reports[0].clone());
+        assert_eq!((canceled.client_order_id.as_str(), canceled.status), ("pO9ufTiFGg3nw2fOdgeOXa", OrderStatus::Canceled));
+        let list = binance.query_order_list(Some(27), None).await.unwrap();
+        assert!(list.order_reports.is_empty());
+        assert!(matches!(binance.query_order_list(None, None).await, Err(EdpError::InvalidOrder(_))));
+
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].param("listClientOrderId").as_deref(), Some("C3wyj4WVEktd7u9aVBRXcN"));
+        assert_eq!(reqs[0].param("symbol").as_deref(), Some("LTCBTC"));
+        assert_eq!((reqs[1].method.as_str(), reqs[1].path.as_str()), ("GET", "/api/v3/orderList"));
+        assert_eq!(reqs[1].param("orderListId").as_deref(), Some("27"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_replace() {
+        let server = StubServer::start(|req| match req.param("cancelOrderId").as_deref() {
+            Some("9") => StubResponse::ok(r#"{"cancelResult":"SUCCESS","newOrderResult":"SUCCESS","cancelResponse":{"symbol":"BTCUSDT","origClientOrderId":"DnLo3vTAQcjha43lAZhZ0y","orderId":9,"orderListId":-1,"clientOrderId":"osxN3JXAtJvKvCqGeMWMVR","transactTime":1684804350068,"price":"0.01000000","origQty":"0.000100","executedQty":"0.00000000","cummulativeQuoteQty":"0.00000000","status":"CANCELED","timeInForce":"GTC","type":"LIMIT","side":"SELL"},"newOrderResponse":{"symbol":"BTCUSDT","orderId":10,"orderListId":-1,"clientOrderId":"wOceeeOzNORyLiQfw7jd8S","transactTime":1652928801803,"price":"0.02000000","origQty":"0.040000","executedQty":"0.00000000","cummulativeQuoteQty":"0.00000000","status":"NEW","timeInForce":"GTC","type":"LIMIT","side":"BUY","fills":[]}}"#),
+            _ => StubResponse::with_status(400, r#"{"code":-2022,"msg":"Order cancel-replace failed.","data":{"cancelResult":"FAILURE","newOrderResult":"NOT_ATTEMPTED","cancelResponse":{"code":-2011,"msg":"Unknown order sent."},"newOrderResponse":null}}"#),
+        })
+        .await;
+        let binance = get_client(&server);
+        let order = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.04)
+            .price(0.02)
+            .build_unchecked();
+        let result = binance.cancel_replace(Some(9), None, &order, CancelReplaceMode::StopOnFailure).await.unwrap();
+        assert_eq!(result.canceled.unwrap().client_order_id, "DnLo3vTAQcjha43lAZhZ0y");
+        assert_eq!(result.new_order.unwrap().unwrap().order_id, 10);
+
+        let result = binance.cancel_replace(Some(1), None, &order, CancelReplaceMode::StopOnFailure).await.unwrap();
+        assert_eq!((result.cancel_result, result.new_order_result), (CancelReplaceStatus::Failure, CancelReplaceStatus::NotAttempted));
+        assert_eq!(result.canceled.unwrap_err().api_code(), Some(ApiErrorCode::CancelRejected));
+        assert!(result.new_order.is_none());
+
+        let req = &server.requests()[0];
+        assert_eq!(req.path, "/api/v3/order/cancelReplace");
+        assert_eq!(req.param("cancelReplaceMode").as_deref(), Some("STOP_ON_FAILURE"));
+        assert_eq!(req.param("price").as_deref(), Some("0.02"));
+        assert_signed(req);
+    }
+
+    // 每小时一笔成交, 按 startTime/endTime 返回
+    fn serve_my_trades(req: &StubRequest) -> StubResponse {
+        let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+        let end: u64 = req.param("endTime").unwrap().parse().unwrap();
+        let trades: Vec<String> = (start.div_ceil(3_600_000)..=end / 3_600_000)
+            .map(|i| {
+                format!(
+                    r#"{{"symbol":"BNBBTC","id":{},"orderId":100234,"orderListId":-1,"price":"4.00000100","qty":"12.00000000","quoteQty":"48.000012","commission":"10.10000000","commissionAsset":"BNB","time":{},"isBuyer":{},"isMaker":false,"isBestMatch":true}}"#,
+                    i,
+                    i * 3_600_000,
+                    i % 2 == 0
+                )
+            })
+            .collect();
+        StubResponse::ok(&format!("[{}]", trades.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_fills() {
+        let server = StubServer::start(serve_my_trades).await;
+        let binance = get_client(&server);
+        let day = 24 * 3_600_000;
+        let fills: Vec<Fill> = binance.fills("BNBBTC", day, 3 * day).try_collect().await.unwrap();
+        assert_eq!(fills.len(), 48);
+        assert_eq!((fills[0].id, fills[0].side, fills[1].side), (24, Side::Buy, Side::Sell));
+        assert_eq!((fills[0].commission, fills[0].commission_asset.as_str()), (10.1, "BNB"));
+        assert!(!fills[0].is_maker);
+        assert_eq!(fills[0].realized_pnl, None);
+
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        for (i, req) in reqs.iter().enumerate() {
+            assert_eq!(req.path, "/api/v3/myTrades");
+            let start = (i as u64 + 1) * day;
+            assert_eq!(req.param("startTime"), Some(start.to_string()));
+            assert_eq!(req.param("endTime"), Some((start + day - 1).to_string()));
+            assert_signed(req);
+        }
+    }
+
+    // 从2小时开始每秒一笔成交, 每笔单独归集
+    fn serve_public_trades(req: &StubRequest) -> StubResponse {
+        let limit: usize = req.param("limit").unwrap().parse().unwrap();
+        let time = |i: u64| 7_200_000 + i * 1000;
+        let ids: Vec<u64> = match req.param("fromId") {
+            Some(from) => (from.parse().unwrap()..20_000).take(limit).collect(),
+            None => {
+                let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+                let end: u64 = req.param("endTime").unwrap().parse().unwrap();
+                (0..20_000).filter(|i| (start..=end).contains(&time(*i))).take(limit).collect()
+            }
         };
-        let data = binance.get_klines("BTCUSDT", "1m", None, None, Some(10)).await;
-        match data {
-            Ok(d) => {
+        let trades: Vec<String> = ids
+            .iter()
+            .map(|i| match req.path.as_str() {
+                "/api/v3/aggTrades" => format!(
+                    r#"{{"a":{},"p":"0.01633102","q":"4.70443515","f":{},"l":{},"T":{},"m":true,"M":true}}"#,
+                    i + 100,
+                    i,
+                    i,
+                    time(*i)
+                ),
+                _ => format!(
+                    r#"{{"id":{},"price":"4.00000100","qty":"12.00000000","quoteQty":"48.000012","time":{},"isBuyerMaker":{},"isBestMatch":true}}"#,
+                    i,
+                    time(*i),
+                    i % 2 == 0
+                ),
+            })
+            .collect();
+        StubResponse::ok(&format!("[{}]", trades.join(",")))
+    }
 
-                println!("{:?}", d)
-            },
-            Err(err) => {
-                println!("err {:?}", err)
+    #[tokio::test]
+    async fn test_public_trades() {
+        let server = StubServer::start(serve_public_trades).await;
+        let binance = get_client(&server);
+        let trades: Vec<Trade> = binance.trades("BNBBTC", 0, 7_200_000 + 4500 * 1000).try_collect().await.unwrap();
+        assert_eq!(trades.len(), 4500);
+        assert!(trades.windows(2).all(|w| w[1].id == w[0].id + 1));
+        assert_eq!((trades[0].side, trades[1].side), (Side::Sell, Side::Buy));
+        assert_eq!((trades[1].first_trade_id, trades[1].last_trade_id), (1, 1));
+
+        // 前两个窗口没有成交, 第三个窗口找到起始id后按 fromId 翻页
+        let reqs = server.requests();
+        let paths: Vec<&str> = reqs.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths[..4], ["/api/v3/aggTrades", "/api/v3/aggTrades", "/api/v3/aggTrades", "/api/v3/historicalTrades"]);
+        assert_eq!(reqs.len(), 8);
+        assert_eq!(reqs[2].param("endTime").as_deref(), Some("10799999"));
+        assert_eq!(reqs[2].param("limit").as_deref(), Some("1"));
+        let historical = &reqs[3];
+        assert_eq!(historical.param("fromId").as_deref(), Some("0"));
+        assert_eq!(historical.headers.get("x-mbx-apikey").map(String::as_str), Some(API_KEY));
+        assert_eq!(historical.param("signature"), None);
+
+        let server = StubServer::start(serve_public_trades).await;
+        let binance = get_client(&server);
+        let agg: Vec<Trade> = binance.agg_trades("BNBBTC", 7_200_000, 7_205_000).try_collect().await.unwrap();
+        assert_eq!(agg.iter().map(|t| t.id).collect::<Vec<_>>(), vec![100, 101, 102, 103, 104]);
+        assert_eq!((agg[0].price, agg[0].first_trade_id, agg[0].side), (0.01633102, 0, Side::Sell));
+        assert_eq!(server.requests()[0].headers.get("x-mbx-apikey"), None);
+    }
+
+    const TICKER_24HR_JSON: &str = r#"{"symbol":"BNBBTC","priceChange":"-94.99999800","priceChangePercent":"-95.960","weightedAvgPrice":"0.29628482","prevClosePrice":"0.10002000","lastPrice":"4.00000200","lastQty":"200.00000000","bidPrice":"4.00000000","bidQty":"100.00000000","askPrice":"4.00000200","askQty":"100.00000000","openPrice":"99.00000000","highPrice":"100.00000000","lowPrice":"0.10000000","volume":"8913.30000000","quoteVolume":"15.30000000","openTime":1499783499040,"closeTime":1499869899040,"firstId":-1,"lastId":-1,"count":0}"#;
+
+    #[tokio::test]
+    async fn test_ticker_stats() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/api/v3/ticker/24hr" if req.param("symbol").is_some() => StubResponse::ok(TICKER_24HR_JSON),
+            "/api/v3/ticker/24hr" => StubResponse::ok(&format!("[{}]", TICKER_24HR_JSON)),
+            "/api/v3/ticker/price" => StubResponse::ok(r#"[{"symbol":"LTCBTC","price":"4.00000200"},{"symbol":"ETHBTC","price":"0.07946600"}]"#),
+            "/api/v3/avgPrice" => StubResponse::ok(r#"{"mins":5,"price":"9.35751834","closeTime":1694061154503}"#),
+            _ => {
+                let symbols: Vec<String> = serde_json::from_str(&req.param("symbols").unwrap()).unwrap();
+                let tickers: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!(r#"{{"symbol":"{}","priceChange":"-8.00000000","priceChangePercent":"-88.889","weightedAvgPrice":"2.60427807","openPrice":"9.00000000","highPrice":"9.00000000","lowPrice":"1.00000000","lastPrice":"1.00000000","volume":"187.00000000","quoteVolume":"487.00000000","openTime":1641859200000,"closeTime":1642031999999,"firstId":0,"lastId":60,"count":61}}"#, s))
+                    .collect();
+                StubResponse::ok(&format!("[{}]", tickers.join(",")))
             }
-        }
+        })
+        .await;
+        let binance = get_client(&server);
+        let stats = binance.get_24h_stats("BNBBTC").await.unwrap();
+        assert_eq!((stats.quote_volume, stats.prev_close_price, stats.bid_price), (15.3, Some(0.10002), Some(4.)));
+        assert_eq!((stats.first_id, stats.count), (-1, 0));
+        assert_eq!(binance.get_all_24h_stats().await.unwrap()[0], stats);
+        let prices = binance.get_all_prices().await.unwrap();
+        assert_eq!((prices[1].symbol.as_str(), prices[1].price, prices[1].time), ("ETHBTC", 0.079466, None));
+        let avg = binance.get_avg_price("BNBBTC").await.unwrap();
+        assert_eq!((avg.mins, avg.price), (5, 9.35751834));
+
+        // 超过100个交易对时分两次请求
+        let symbols: Vec<String> = (0..150).map(|i| format!("S{}USDT", i)).collect();
+        let symbols: Vec<&str> = symbols.iter().map(String::as_str).collect();
+        let tickers = binance.get_rolling_tickers(&symbols, "1d").await.unwrap();
+        assert_eq!(tickers.len(), 150);
+        assert_eq!((tickers[149].symbol.as_str(), tickers[149].quote_volume), ("S149USDT", 487.));
+        let reqs: Vec<StubRequest> = server.requests().into_iter().filter(|r| r.path == "/api/v3/ticker").collect();
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].param("windowSize").as_deref(), Some("1d"));
+        assert!(reqs[1].param("symbols").unwrap().starts_with(r#"["S100USDT","#));
+    }
+
+    #[tokio::test]
+    async fn test_trade_fee() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"[{"symbol":"ADABNB","makerCommission":"0.001","takerCommission":"0.001"},{"symbol":"BNBBTC","makerCommission":"0.00075","takerCommission":"0.001"}]"#)
+        })
+        .await;
+        let binance = get_client(&server);
+        let fees = binance.get_trade_fee(None).await.unwrap();
+        assert_eq!((fees[1].maker, fees[1].taker), (0.00075, 0.001));
+        assert_eq!(server.requests()[0].path, "/sapi/v1/asset/tradeFee");
+    }
+
+    #[tokio::test]
+    async fn test_test_order() {
+        let server = StubServer::start(|_| StubResponse::ok("{}")).await;
+        let binance = get_client(&server);
+        let order = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Market)
+            .quantity(1.)
+            .build_unchecked();
+        binance.test_order(&order).await.unwrap();
+        let req = &server.requests()[0];
+        assert_eq!((req.method.as_str(), req.path.as_str()), ("POST", "/api/v3/order/test"));
+        assert_signed(req);
+    }
+
+    #[tokio::test]
+    async fn test_query_order() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"{"symbol":"LTCBTC","orderId":1,"orderListId":-1,"clientOrderId":"myOrder1","price":"0.1","origQty":"1.0","executedQty":"0.0","cummulativeQuoteQty":"0.0","status":"NEW","timeInForce":"GTC","type":"LIMIT","side":"BUY","stopPrice":"0.0","icebergQty":"0.0","time":1499827319559,"updateTime":1499827319559,"isWorking":true,"origQuoteOrderQty":"0.000000"}"#)
+        })
+        .await;
+        let binance = get_client(&server);
+        let resp = binance.query_order("LTCBTC", None, Some("myOrder1")).await.unwrap();
+        assert_eq!(resp.order_id, 1);
+        assert_eq!(resp.side, Side::Buy);
+        assert_eq!(resp.type_field, OrderType::Limit);
+        assert_eq!(resp.orig_qty, 1.);
+        assert_eq!(resp.update_time, 1499827319559);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/api/v3/order");
+        assert_eq!(req.param("origClientOrderId").as_deref(), Some("myOrder1"));
+        assert_eq!(req.param("orderId"), None);
+        assert_signed(req);
+    }
+
+    #[tokio::test]
+    async fn test_get_balances_skips_empty_assets() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"{"makerCommission":15,"takerCommission":15,"buyerCommission":0,"sellerCommission":0,"canTrade":true,"canWithdraw":true,"canDeposit":true,"updateTime":123456789,"accountType":"SPOT","balances":[{"asset":"BTC","free":"4723846.89208129","locked":"0.00000000"},{"asset":"LTC","free":"0.00000000","locked":"0.00000000"},{"asset":"ETH","free":"0.00000000","locked":"1.50000000"}],"permissions":["SPOT"]}"#)
+        })
+        .await;
+        let binance = get_client(&server);
+        let balances = binance.get_balances().await.unwrap();
+        let assets: Vec<&str> = balances.iter().map(|b| b.asset.as_str()).collect();
+        assert_eq!(assets, vec!["BTC", "ETH"]);
+        assert_eq!(balances[1].locked, 1.5);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.path, "/api/v3/account");
+        assert_signed(req);
+    }
+
+    #[tokio::test]
+    async fn test_builder_public_api() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"[[1499040000000,"0.01634790","0.80000000","0.01575800","0.01577100","148976.11427815",1499644799999,"2434.19055334",308,"1756.87402397","28.46694368","17928899.62484339"]]"#)
+        })
+        .await;
+        let binance = BinanceSpot::builder()
+            .base_url(&server.url())
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+        let klines = binance
+            .get_klines("BNBBTC", "1h", Some(1499040000000), None, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 1);
+        assert_eq!(klines[0].close, 0.015771);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.path, "/api/v3/klines");
+        assert_eq!(req.param("startTime").as_deref(), Some("1499040000000"));
+        assert_eq!(req.param("interval").as_deref(), Some("1h"));
+        assert!(!req.headers.contains_key("x-mbx-apikey"));
+    }
+
+    #[tokio::test]
+    async fn test_get_order_book() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok(r#"{"lastUpdateId":1027024,"bids":[["4.00000000","431.00000000"]],"asks":[["4.00000200","12.00000000"]]}"#)
+        })
+        .await;
+        let binance = BinanceSpot::new(server.url());
+        let book = binance.get_order_book("BNBBTC", Some(5)).await.unwrap();
+        assert_eq!(book.last_update_id, 1027024);
+        assert_eq!(book.event_time, 0);
+
+        let req = &server.requests()[0];
+        assert_eq!(req.path, "/api/v3/depth");
+        assert_eq!(req.param("limit").as_deref(), Some("5"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_needs_order_id() {
+        let server = StubServer::start(|_| StubResponse::ok("{}")).await;
+        let binance = get_client(&server);
+        let err = binance.cancel_order("LTCBTC", None, None).await.unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(_)));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_klines_range_uses_spot_page_limit() {
+        let server = StubServer::start(|req| {
+            let start: u64 = req.param("startTime").unwrap().parse().unwrap();
+            let end: u64 = req.param("endTime").unwrap().parse().unwrap();
+            let limit: u64 = req.param("limit").unwrap().parse().unwrap();
+            let first = start.div_ceil(1000) * 1000;
+            let bars: Vec<String> = (0..limit)
+                .map(|i| first + i * 1000)
+                .take_while(|ts| *ts <= end)
+                .map(|ts| format!(r#"[{},"1","1","1","1","1",0,"1",1,"1","1","0"]"#, ts))
+                .collect();
+            StubResponse::ok(&format!("[{}]", bars.join(",")))
+        })
+        .await;
+        let binance = BinanceSpot::new(server.url());
+        let klines = binance.get_klines_range("BTCUSDT", "1s", 0, 1_500_000).await.unwrap();
+        assert_eq!(klines.len(), 1500);
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].param("limit").as_deref(), Some("1000"));
+        assert_eq!(reqs[1].param("startTime").as_deref(), Some("999001"));
+    }
+
+    #[tokio::test]
+    async fn test_private_api_needs_credential() {
+        let binance = BinanceSpot::new("http://127.0.0.1:1".to_string());
+        let err = binance.get_balances().await.unwrap_err();
+        assert!(matches!(err, EdpError::MissingCredentials));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs network access to api.binance.com"]
+    async fn test_kline() {
+        let binance = BinanceSpot::new("https://api.binance.com".to_string());
+        let klines = binance.get_klines("BTCUSDT", "1m", None, None, Some(10)).await.unwrap();
+        assert_eq!(klines.len(), 10);
+        assert!(klines.windows(2).all(|w| w[1].ts - w[0].ts == 60_000));
     }
 }
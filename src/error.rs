@@ -1 +1,288 @@
-// todo impl error types
\ No newline at end of file
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, EdpError>;
+
+#[derive(Debug, Error)]
+pub enum EdpError {
+    // 网络层错误: 连接失败, 超时, 连接被重置等
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+    // 非200且响应体不是交易所的错误格式
+    #[error("http error {status}: {body}")]
+    Http { status: StatusCode, body: String },
+    // 交易所返回的 {"code": -1021, "msg": "..."}
+    // 少数接口失败时在 data 中带有详细结果, 例如现货 cancelReplace
+    #[error("api error {code} ({status}): {msg}")]
+    Api {
+        status: StatusCode,
+        code: ApiErrorCode,
+        msg: String,
+        data: Option<serde_json::Value>,
+    },
+    #[error("deserialize error: {0}")]
+    Deserialize(#[from] serde_json::Error),
+    #[error("api keys not set")]
+    MissingCredentials,
+    // 本地限频器拒绝了请求
+    #[error("rate limited, retry after {retry_after:?}")]
+    RateLimited { retry_after: Duration },
+    // 下单前本地校验未通过, 请求没有发出
+    #[error("invalid order: {0}")]
+    InvalidOrder(String),
+    // tungstenite的错误类型较大, 装箱避免撑大 Result
+    #[error("websocket error: {0}")]
+    WebSocket(Box<async_tungstenite::tungstenite::Error>),
+    // SUBSCRIBE/UNSUBSCRIBE 等请求返回的 {"error": {"code": 2, "msg": "..."}, "id": 1}
+    #[error("stream request error {code}: {msg}")]
+    StreamRequest { code: i64, msg: String },
+    // 连接已断开, 后台任务已退出
+    #[error("stream closed")]
+    StreamClosed,
+}
+
+impl EdpError {
+    // 根据非200响应构造错误, 能解析出交易所错误码时优先返回Api
+    pub fn from_response(status: StatusCode, body: &str) -> Self {
+        match serde_json::from_str::<RawApiError>(body) {
+            Ok(raw) => EdpError::Api {
+                status,
+                code: ApiErrorCode::from(raw.code),
+                msg: raw.msg,
+                data: raw.data,
+            },
+            Err(_) => EdpError::Http {
+                status,
+                body: body.to_string(),
+            },
+        }
+    }
+
+    pub fn api_code(&self) -> Option<ApiErrorCode> {
+        match self {
+            EdpError::Api { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            EdpError::Http { status, .. } | EdpError::Api { status, .. } => Some(*status),
+            EdpError::Transport(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    // 网络错误, 限频, 交易所繁忙等, 稍后重试可能成功
+    pub fn is_transient(&self) -> bool {
+        match self {
+            EdpError::Transport(_) | EdpError::RateLimited { .. } => true,
+            EdpError::Http { status, .. } | EdpError::Api { status, .. }
+                if status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS =>
+            {
+                true
+            }
+            EdpError::Api { code, .. } => matches!(
+                code,
+                ApiErrorCode::Disconnected
+                    | ApiErrorCode::TooManyRequests
+                    | ApiErrorCode::UnexpectedResponse
+                    | ApiErrorCode::Timeout
+                    | ApiErrorCode::ServiceShuttingDown
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl From<async_tungstenite::tungstenite::Error> for EdpError {
+    fn from(err: async_tungstenite::tungstenite::Error) -> Self {
+        EdpError::WebSocket(Box::new(err))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawApiError {
+    code: i64,
+    msg: String,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+}
+
+// 批量接口等在200的响应中逐项返回结果, 失败的项为 {"code": -2022, "msg": "..."}
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ApiResult<T> {
+    Err(RawApiError),
+    Ok(T),
+}
+
+impl<T> ApiResult<T> {
+    pub(crate) fn into_result(self) -> Result<T> {
+        match self {
+            ApiResult::Ok(t) => Ok(t),
+            ApiResult::Err(raw) => Err(EdpError::Api {
+                status: StatusCode::OK,
+                code: ApiErrorCode::from(raw.code),
+                msg: raw.msg,
+                data: raw.data,
+            }),
+        }
+    }
+}
+
+// https://binance-docs.github.io/apidocs/spot/en/#error-codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiErrorCode {
+    Unknown,
+    Disconnected,
+    Unauthorized,
+    TooManyRequests,
+    UnexpectedResponse,
+    Timeout,
+    TooManyOrders,
+    ServiceShuttingDown,
+    UnsupportedOperation,
+    InvalidTimestamp,
+    InvalidSignature,
+    IllegalChars,
+    TooManyParameters,
+    MandatoryParamEmptyOrMalformed,
+    UnknownParam,
+    BadPrecision,
+    InvalidParameter,
+    InvalidListenKey,
+    FilterFailure,
+    NewOrderRejected,
+    CancelRejected,
+    NoSuchOrder,
+    BadApiKeyFmt,
+    RejectedMbxKey,
+    MarginNotSufficient,
+    Other(i64),
+}
+
+impl ApiErrorCode {
+    pub fn code(&self) -> i64 {
+        match self {
+            ApiErrorCode::Unknown => -1000,
+            ApiErrorCode::Disconnected => -1001,
+            ApiErrorCode::Unauthorized => -1002,
+            ApiErrorCode::TooManyRequests => -1003,
+            ApiErrorCode::UnexpectedResponse => -1006,
+            ApiErrorCode::Timeout => -1007,
+            ApiErrorCode::TooManyOrders => -1015,
+            ApiErrorCode::ServiceShuttingDown => -1016,
+            ApiErrorCode::UnsupportedOperation => -1020,
+            ApiErrorCode::InvalidTimestamp => -1021,
+            ApiErrorCode::InvalidSignature => -1022,
+            ApiErrorCode::IllegalChars => -1100,
+            ApiErrorCode::TooManyParameters => -1101,
+            ApiErrorCode::MandatoryParamEmptyOrMalformed => -1102,
+            ApiErrorCode::UnknownParam => -1103,
+            ApiErrorCode::BadPrecision => -1111,
+            ApiErrorCode::InvalidParameter => -1130,
+            ApiErrorCode::InvalidListenKey => -1125,
+            ApiErrorCode::FilterFailure => -1013,
+            ApiErrorCode::NewOrderRejected => -2010,
+            ApiErrorCode::CancelRejected => -2011,
+            ApiErrorCode::NoSuchOrder => -2013,
+            ApiErrorCode::BadApiKeyFmt => -2014,
+            ApiErrorCode::RejectedMbxKey => -2015,
+            ApiErrorCode::MarginNotSufficient => -2019,
+            ApiErrorCode::Other(code) => *code,
+        }
+    }
+}
+
+impl From<i64> for ApiErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            -1000 => ApiErrorCode::Unknown,
+            -1001 => ApiErrorCode::Disconnected,
+            -1002 => ApiErrorCode::Unauthorized,
+            -1003 => ApiErrorCode::TooManyRequests,
+            -1006 => ApiErrorCode::UnexpectedResponse,
+            -1007 => ApiErrorCode::Timeout,
+            -1013 => ApiErrorCode::FilterFailure,
+            -1015 => ApiErrorCode::TooManyOrders,
+            -1016 => ApiErrorCode::ServiceShuttingDown,
+            -1020 => ApiErrorCode::UnsupportedOperation,
+            -1021 => ApiErrorCode::InvalidTimestamp,
+            -1022 => ApiErrorCode::InvalidSignature,
+            -1100 => ApiErrorCode::IllegalChars,
+            -1101 => ApiErrorCode::TooManyParameters,
+            -1102 => ApiErrorCode::MandatoryParamEmptyOrMalformed,
+            -1103 => ApiErrorCode::UnknownParam,
+            -1111 => ApiErrorCode::BadPrecision,
+            -1125 => ApiErrorCode::InvalidListenKey,
+            -1130 => ApiErrorCode::InvalidParameter,
+            -2010 => ApiErrorCode::NewOrderRejected,
+            -2011 => ApiErrorCode::CancelRejected,
+            -2013 => ApiErrorCode::NoSuchOrder,
+            -2014 => ApiErrorCode::BadApiKeyFmt,
+            -2015 => ApiErrorCode::RejectedMbxKey,
+            -2019 => ApiErrorCode::MarginNotSufficient,
+            other => ApiErrorCode::Other(other),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_error() {
+        let err = EdpError::from_response(
+            StatusCode::BAD_REQUEST,
+            r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#,
+        );
+        assert_eq!(err.api_code(), Some(ApiErrorCode::InvalidTimestamp));
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+
+        let err = EdpError::from_response(
+            StatusCode::BAD_REQUEST,
+            r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#,
+        );
+        assert_eq!(err.api_code(), Some(ApiErrorCode::NewOrderRejected));
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let err = EdpError::from_response(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+        assert!(err.is_transient());
+        let err = EdpError::from_response(StatusCode::BAD_REQUEST, r#"{"code":-1007,"msg":"Timeout waiting for response from backend server."}"#);
+        assert!(err.is_transient());
+        let err = EdpError::from_response(StatusCode::BAD_REQUEST, r#"{"code":-2013,"msg":"Order does not exist."}"#);
+        assert!(!err.is_transient());
+        assert!(!EdpError::MissingCredentials.is_transient());
+    }
+
+    #[test]
+    fn test_unknown_code_is_kept() {
+        let err = EdpError::from_response(StatusCode::BAD_REQUEST, r#"{"code":-4164,"msg":"notional"}"#);
+        assert_eq!(err.api_code(), Some(ApiErrorCode::Other(-4164)));
+        assert_eq!(ApiErrorCode::from(-4164).code(), -4164);
+    }
+
+    #[test]
+    fn test_non_api_body() {
+        let err = EdpError::from_response(StatusCode::BAD_GATEWAY, "<html>bad gateway</html>");
+        match err {
+            EdpError::Http { status, body } => {
+                assert_eq!(status, StatusCode::BAD_GATEWAY);
+                assert!(body.contains("bad gateway"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -1,12 +0,0 @@
-
-pub(crate) trait Indicator {
-
-}
-
-pub(crate) struct KlineBucket {
-
-}
-
-pub(crate) struct EdpOrderBook {
-    
-}
\ No newline at end of file
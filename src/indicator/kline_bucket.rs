@@ -0,0 +1,405 @@
+use crate::model::KData;
+use crate::ws::event::{AggTradeEvent, TradeEvent};
+use std::collections::BTreeMap;
+
+// 聚合K线用的成交, id 用于同一毫秒内的排序
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeTick {
+    pub id: u64,
+    pub ts: u64,
+    pub price: f64,
+    pub qty: f64,
+}
+
+impl From<&AggTradeEvent> for TradeTick {
+    fn from(t: &AggTradeEvent) -> Self {
+        Self {
+            id: t.agg_trade_id,
+            ts: t.trade_time as u64,
+            price: t.price,
+            qty: t.qty,
+        }
+    }
+}
+
+impl From<&TradeEvent> for TradeTick {
+    fn from(t: &TradeEvent) -> Self {
+        Self {
+            id: t.trade_id,
+            ts: t.trade_time as u64,
+            price: t.price,
+            qty: t.qty,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarKind {
+    // 按时间切分, 单位毫秒, 与交易所一样从 0 开始对齐, 例如 7_000, 180_000
+    Time(u64),
+    // 每 n 笔成交
+    Tick(u64),
+    // 成交量(base)达到阈值
+    Volume(f64),
+    // 成交额(quote)达到阈值
+    Dollar(f64),
+}
+
+// 聚合中的K线, 乱序到达时 open/close 按 (ts, id) 取最早/最晚的成交
+#[derive(Debug, Clone)]
+struct Bar {
+    ts: u64,
+    open: (u64, u64, f64),
+    close: (u64, u64, f64),
+    high: f64,
+    low: f64,
+    vol: f64,
+    turnover: f64,
+    trades: u64,
+}
+
+impl Bar {
+    fn new(ts: u64, t: &TradeTick) -> Self {
+        Self {
+            ts,
+            open: (t.ts, t.id, t.price),
+            close: (t.ts, t.id, t.price),
+            high: t.price,
+            low: t.price,
+            vol: t.qty,
+            turnover: t.qty * t.price,
+            trades: 1,
+        }
+    }
+
+    // 没有成交的时间段, 价格沿用上一根的收盘价
+    fn empty(ts: u64, price: f64) -> Self {
+        Self {
+            ts,
+            open: (ts, 0, price),
+            close: (ts, 0, price),
+            high: price,
+            low: price,
+            vol: 0.,
+            turnover: 0.,
+            trades: 0,
+        }
+    }
+
+    fn add(&mut self, t: &TradeTick) {
+        if (t.ts, t.id) < (self.open.0, self.open.1) {
+            self.open = (t.ts, t.id, t.price);
+        }
+        if (t.ts, t.id) >= (self.close.0, self.close.1) {
+            self.close = (t.ts, t.id, t.price);
+        }
+        self.high = self.high.max(t.price);
+        self.low = self.low.min(t.price);
+        self.vol += t.qty;
+        self.turnover += t.qty * t.price;
+        self.trades += 1;
+    }
+
+    fn to_kdata(&self) -> KData {
+        KData {
+            ts: self.ts,
+            open: self.open.2,
+            high: self.high,
+            low: self.low,
+            close: self.close.2,
+            vol: self.vol,
+            turnover: self.turnover,
+        }
+    }
+}
+
+// 由逐笔成交合成K线
+// 时间K线在 结束时间 + grace_ms 之前仍接受迟到的成交, 之后到达的成交被丢弃并计入 late_trades
+pub struct KlineBucket {
+    kind: BarKind,
+    grace_ms: u64,
+    // 时间K线: 按开始时间排列的未收盘K线, 其它类型只有一根
+    open_bars: BTreeMap<u64, Bar>,
+    // 已收盘的时间K线的结束时间, 早于它的成交视为迟到
+    closed_until: u64,
+    last_close: Option<f64>,
+    // 已见过的最大成交时间
+    watermark: u64,
+    pub late_trades: u64,
+}
+
+impl KlineBucket {
+    pub fn new(kind: BarKind) -> Self {
+        Self {
+            kind,
+            grace_ms: 0,
+            open_bars: BTreeMap::new(),
+            closed_until: 0,
+            last_close: None,
+            watermark: 0,
+            late_trades: 0,
+        }
+    }
+
+    pub fn with_grace(mut self, grace_ms: u64) -> Self {
+        self.grace_ms = grace_ms;
+        self
+    }
+
+    pub fn kind(&self) -> BarKind {
+        self.kind
+    }
+
+    // 返回因此收盘的K线, 按时间顺序
+    pub fn push(&mut self, trade: &TradeTick) -> Vec<KData> {
+        match self.kind {
+            BarKind::Time(interval) => self.push_time(trade, interval),
+            BarKind::Tick(n) => self.push_threshold(trade, |bar| bar.trades >= n),
+            BarKind::Volume(v) => self.push_threshold(trade, |bar| bar.vol >= v),
+            BarKind::Dollar(d) => self.push_threshold(trade, |bar| bar.turnover >= d),
+        }
+    }
+
+    // 没有新成交时按时钟收盘, now_ms 为交易所时间
+    pub fn advance(&mut self, now_ms: u64) -> Vec<KData> {
+        match self.kind {
+            BarKind::Time(interval) => {
+                self.watermark = self.watermark.max(now_ms);
+                self.close_time_bars(interval)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // 当前未收盘的K线(最新的一根)
+    pub fn current(&self) -> Option<KData> {
+        self.open_bars.values().next_back().map(Bar::to_kdata)
+    }
+
+    fn push_time(&mut self, trade: &TradeTick, interval: u64) -> Vec<KData> {
+        let start = trade.ts - trade.ts % interval;
+        if start < self.closed_until {
+            self.late_trades += 1;
+            log::debug!("drop late trade {} at {}", trade.id, trade.ts);
+            return Vec::new();
+        }
+        match self.open_bars.get_mut(&start) {
+            Some(bar) => bar.add(trade),
+            None => {
+                self.open_bars.insert(start, Bar::new(start, trade));
+            }
+        }
+        self.watermark = self.watermark.max(trade.ts);
+        self.close_time_bars(interval)
+    }
+
+    fn close_time_bars(&mut self, interval: u64) -> Vec<KData> {
+        let mut closed = Vec::new();
+        while let Some((&start, _)) = self.open_bars.iter().next() {
+            if start + interval + self.grace_ms > self.watermark {
+                break;
+            }
+            let bar = self.open_bars.remove(&start).unwrap();
+            // 补齐中间没有成交的K线
+            if let Some(price) = self.last_close {
+                let mut ts = self.closed_until;
+                while ts < start {
+                    closed.push(Bar::empty(ts, price).to_kdata());
+                    ts += interval;
+                }
+            }
+            self.last_close = Some(bar.close.2);
+            self.closed_until = start + interval;
+            closed.push(bar.to_kdata());
+        }
+        // 到时间没有成交也收盘, 与交易所一致
+        if let Some(price) = self.last_close {
+            while self.closed_until > 0 && self.closed_until + interval + self.grace_ms <= self.watermark {
+                closed.push(Bar::empty(self.closed_until, price).to_kdata());
+                self.closed_until += interval;
+            }
+        }
+        closed
+    }
+
+    fn push_threshold<F: Fn(&Bar) -> bool>(&mut self, trade: &TradeTick, is_full: F) -> Vec<KData> {
+        match self.open_bars.values_mut().next() {
+            Some(bar) => bar.add(trade),
+            None => {
+                self.open_bars.insert(trade.ts, Bar::new(trade.ts, trade));
+            }
+        }
+        // 触发阈值的成交整笔计入当前K线, 不拆分
+        let bar = self.open_bars.values().next().unwrap();
+        if is_full(bar) {
+            let bar = std::mem::take(&mut self.open_bars).into_values().next().unwrap();
+            self.last_close = Some(bar.close.2);
+            return vec![bar.to_kdata()];
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(id: u64, ts: u64, price: f64, qty: f64) -> TradeTick {
+        TradeTick { id, ts, price, qty }
+    }
+
+    // 构造的 aggTrades 与按交易所规则算出的 1m K线, 格式与 /api/v3/aggTrades 和 /api/v3/klines 相同
+    // 中间两分钟没有成交, 交易所返回 open=high=low=close=上一根收盘价, 成交量为0
+    // 与交易所实际数据的对比见 test_matches_live_exchange_klines
+    const AGG_TRADES: &str = r#"[
+        {"a":26129,"p":"100.10","q":"2.0","f":27781,"l":27781,"T":1600000020000,"m":true},
+        {"a":26130,"p":"100.50","q":"1.0","f":27782,"l":27783,"T":1600000035500,"m":false},
+        {"a":26131,"p":"99.90","q":"0.5","f":27784,"l":27784,"T":1600000050000,"m":true},
+        {"a":26132,"p":"100.20","q":"1.5","f":27785,"l":27785,"T":1600000079999,"m":false},
+        {"a":26133,"p":"100.40","q":"4.0","f":27786,"l":27787,"T":1600000200000,"m":false},
+        {"a":26134,"p":"100.40","q":"1.0","f":27788,"l":27788,"T":1600000200000,"m":true},
+        {"a":26135,"p":"100.30","q":"0.25","f":27789,"l":27789,"T":1600000230000,"m":true},
+        {"a":26136,"p":"101.00","q":"1.0","f":27790,"l":27790,"T":1600000260001,"m":false}
+    ]"#;
+
+    const KLINES_1M: &str = r#"[
+        [1600000020000,"100.10","100.50","99.90","100.20","5.0",1600000079999,"500.95",4,"2.5","250.80","0"],
+        [1600000080000,"100.20","100.20","100.20","100.20","0",1600000139999,"0",0,"0","0","0"],
+        [1600000140000,"100.20","100.20","100.20","100.20","0",1600000199999,"0",0,"0","0","0"],
+        [1600000200000,"100.40","100.40","100.30","100.30","5.25",1600000259999,"527.075",3,"4.0","401.60","0"]
+    ]"#;
+
+    #[derive(serde::Deserialize)]
+    struct RawAggTrade {
+        a: u64,
+        p: String,
+        q: String,
+        #[serde(rename = "T")]
+        t: u64,
+    }
+
+    fn fixture_trades() -> Vec<TradeTick> {
+        let raw: Vec<RawAggTrade> = serde_json::from_str(AGG_TRADES).unwrap();
+        raw.iter()
+            .map(|r| tick(r.a, r.t, r.p.parse().unwrap(), r.q.parse().unwrap()))
+            .collect()
+    }
+
+    fn fixture_klines() -> Vec<KData> {
+        let raw: Vec<crate::binance::spot::RawKResp> = serde_json::from_str(KLINES_1M).unwrap();
+        raw.iter().map(|k| KData::from(*k)).collect()
+    }
+
+    fn assert_kdata_eq(a: &KData, b: &KData) {
+        assert_eq!(a.ts, b.ts);
+        assert_eq!((a.open, a.high, a.low, a.close), (b.open, b.high, b.low, b.close));
+        assert!((a.vol - b.vol).abs() < 1e-9, "{:?} {:?}", a, b);
+        assert!((a.turnover - b.turnover).abs() < 1e-6, "{:?} {:?}", a, b);
+    }
+
+    #[test]
+    fn test_matches_exchange_klines() {
+        // 交易所的分钟K线从 0 对齐, 最后一笔成交开启新的一分钟, 使前面的K线收盘
+        let mut bucket = KlineBucket::new(BarKind::Time(60_000));
+        let mut closed = Vec::new();
+        for t in fixture_trades() {
+            closed.extend(bucket.push(&t));
+        }
+        let expected = fixture_klines();
+        assert_eq!(closed.len(), expected.len());
+        for (a, b) in closed.iter().zip(expected.iter()) {
+            assert_kdata_eq(a, b);
+        }
+        let current = bucket.current().unwrap();
+        assert_eq!((current.ts, current.close), (1600000260000, 101.));
+    }
+
+    // 在成交稀少的交易对上找一段包含无成交分钟的时间, 用真实的 aggTrades 合成K线并与交易所的K线比较
+    #[tokio::test]
+    #[ignore = "needs network access to api.binance.com"]
+    async fn test_matches_live_exchange_klines() {
+        use crate::binance::spot::BinanceSpot;
+        use crate::traits::MarketDataAPI;
+        use futures::TryStreamExt;
+
+        let symbol = "LTCBNB";
+        let binance = BinanceSpot::new("https://api.binance.com".to_string());
+        // 2024-01-01 00:00 UTC 开始的1000分钟
+        let day = 1704067200000;
+        let scan = binance.get_klines_range(symbol, "1m", day, day + 1000 * 60_000).await.unwrap();
+        let empty = scan
+            .windows(2)
+            .position(|w| w[0].vol > 0. && w[1].vol == 0.)
+            .expect("no minute without trades in the scanned range") + 1;
+        let start = scan[empty.saturating_sub(3)].ts;
+        let end = scan[(empty + 4).min(scan.len() - 1)].ts;
+
+        let trades: Vec<_> = binance.agg_trades(symbol, start, end).try_collect().await.unwrap();
+        let mut bucket = KlineBucket::new(BarKind::Time(60_000));
+        let mut closed = Vec::new();
+        for t in &trades {
+            closed.extend(bucket.push(&tick(t.id, t.time, t.price, t.qty)));
+        }
+        closed.extend(bucket.advance(end));
+        let expected: Vec<&KData> = scan.iter().filter(|k| k.ts >= closed[0].ts && k.ts < end).collect();
+        assert!(expected.iter().any(|k| k.vol == 0.));
+        assert_eq!(closed.len(), expected.len());
+        for (a, b) in closed.iter().zip(expected) {
+            assert_kdata_eq(a, b);
+        }
+    }
+
+    #[test]
+    fn test_custom_interval_and_advance() {
+        let mut bucket = KlineBucket::new(BarKind::Time(7_000));
+        assert!(bucket.push(&tick(1, 7_000, 10., 1.)).is_empty());
+        assert!(bucket.push(&tick(2, 13_999, 12., 1.)).is_empty());
+        let closed = bucket.push(&tick(3, 14_000, 11., 1.));
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].ts, closed[0].open, closed[0].close, closed[0].vol), (7_000, 10., 12., 2.));
+        let closed = bucket.advance(28_000);
+        assert_eq!(closed.iter().map(|k| k.ts).collect::<Vec<_>>(), vec![14_000, 21_000]);
+        assert_eq!(closed[1].close, 11.);
+        assert!(bucket.current().is_none());
+    }
+
+    #[test]
+    fn test_out_of_order_within_grace() {
+        let mut bucket = KlineBucket::new(BarKind::Time(60_000)).with_grace(2_000);
+        bucket.push(&tick(2, 30_000, 10., 1.));
+        // 下一分钟的成交先到, 上一分钟还在 grace 内
+        assert!(bucket.push(&tick(4, 60_500, 20., 1.)).is_empty());
+        assert!(bucket.push(&tick(1, 10_000, 9., 1.)).is_empty());
+        assert!(bucket.push(&tick(3, 59_999, 11., 1.)).is_empty());
+        let closed = bucket.push(&tick(5, 62_000, 21., 1.));
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].open, closed[0].high, closed[0].low, closed[0].close), (9., 11., 9., 11.));
+        assert_eq!(closed[0].vol, 3.);
+        // grace 之后到达的成交被丢弃
+        assert!(bucket.push(&tick(6, 59_000, 1., 1.)).is_empty());
+        assert_eq!(bucket.late_trades, 1);
+        assert_eq!(bucket.current().unwrap().low, 20.);
+    }
+
+    #[test]
+    fn test_tick_volume_and_dollar_bars() {
+        let trades: Vec<TradeTick> = (0..6).map(|i| tick(i, i * 100, 10. + i as f64, 1. + i as f64)).collect();
+
+        let mut bucket = KlineBucket::new(BarKind::Tick(2));
+        let bars: Vec<KData> = trades.iter().flat_map(|t| bucket.push(t)).collect();
+        assert_eq!(bars.len(), 3);
+        assert_eq!((bars[1].ts, bars[1].open, bars[1].close, bars[1].vol), (200, 12., 13., 7.));
+
+        let mut bucket = KlineBucket::new(BarKind::Volume(5.));
+        let bars: Vec<KData> = trades.iter().flat_map(|t| bucket.push(t)).collect();
+        let vols: Vec<f64> = bars.iter().map(|k| k.vol).collect();
+        assert_eq!(vols, vec![6., 9., 6.]);
+        assert!(bucket.current().is_none());
+
+        let mut bucket = KlineBucket::new(BarKind::Dollar(150.));
+        let bars: Vec<KData> = trades.iter().flat_map(|t| bucket.push(t)).collect();
+        assert_eq!(bars.len(), 1);
+        assert_eq!((bars[0].open, bars[0].close, bars[0].turnover), (10., 14., 190.));
+        assert_eq!(bucket.current().unwrap().vol, 6.);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::indicator::{impl_series_indicator, SeriesIndicator, Source};
+use std::collections::VecDeque;
+
+// 简单移动平均
+pub struct Sma {
+    period: usize,
+    source: Source,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            period,
+            source: Source::Close,
+            window: VecDeque::with_capacity(period + 1),
+            sum: 0.,
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    fn current(&self) -> Option<f64> {
+        (self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+impl SeriesIndicator for Sma {
+    fn update_value(&mut self, v: f64) {
+        self.window.push_back(v);
+        self.sum += v;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap();
+        }
+    }
+}
+
+// 指数移动平均, alpha = 2 / (period + 1), 以前 period 个值的 SMA 作为初值
+pub struct Ema {
+    period: usize,
+    source: Source,
+    alpha: f64,
+    count: usize,
+    value: f64,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            period,
+            source: Source::Close,
+            alpha: 2. / (period as f64 + 1.),
+            count: 0,
+            value: 0.,
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    fn current(&self) -> Option<f64> {
+        (self.count >= self.period).then_some(self.value)
+    }
+}
+
+impl SeriesIndicator for Ema {
+    fn update_value(&mut self, v: f64) {
+        self.count += 1;
+        if self.count <= self.period {
+            self.value += (v - self.value) / self.count as f64;
+        } else {
+            self.value += (v - self.value) * self.alpha;
+        }
+    }
+}
+
+// 线性加权移动平均, 最新的值权重为 period
+pub struct Wma {
+    period: usize,
+    source: Source,
+    window: VecDeque<f64>,
+    sum: f64,
+    weighted_sum: f64,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            period,
+            source: Source::Close,
+            window: VecDeque::with_capacity(period),
+            sum: 0.,
+            weighted_sum: 0.,
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    fn current(&self) -> Option<f64> {
+        let n = self.period as f64;
+        (self.window.len() == self.period).then(|| self.weighted_sum / (n * (n + 1.) / 2.))
+    }
+}
+
+impl SeriesIndicator for Wma {
+    fn update_value(&mut self, v: f64) {
+        if self.window.len() == self.period {
+            // 每个旧值的权重减一, 等价于减去窗口和
+            self.weighted_sum += self.period as f64 * v - self.sum;
+            self.sum += v - self.window.pop_front().unwrap();
+        } else {
+            self.weighted_sum += (self.window.len() + 1) as f64 * v;
+            self.sum += v;
+        }
+        self.window.push_back(v);
+    }
+}
+
+impl_series_indicator!(Sma => f64, Ema => f64, Wma => f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicator::tests::{assert_close, bar, close};
+    use crate::indicator::Indicator;
+
+    // StockCharts 的 10 日 SMA/EMA 示例
+    const CLOSES: [f64; 30] = [
+        22.27, 22.19, 22.08, 22.17, 22.18, 22.13, 22.23, 22.43, 22.24, 22.29, 22.15, 22.39, 22.38, 22.61, 23.36,
+        24.05, 23.75, 23.83, 23.95, 23.63, 23.82, 23.87, 23.65, 23.19, 23.10, 23.33, 22.68, 23.10, 22.40, 22.17,
+    ];
+    const SMA_10: [f64; 21] = [
+        22.22, 22.21, 22.23, 22.26, 22.30, 22.42, 22.61, 22.77, 22.91, 23.08, 23.21, 23.38, 23.52, 23.65, 23.71,
+        23.68, 23.61, 23.51, 23.43, 23.28, 23.13,
+    ];
+    const EMA_10: [f64; 21] = [
+        22.22, 22.21, 22.24, 22.27, 22.33, 22.52, 22.80, 22.97, 23.13, 23.28, 23.34, 23.43, 23.51, 23.53, 23.47,
+        23.40, 23.39, 23.26, 23.23, 23.08, 22.92,
+    ];
+
+    fn run<I: Indicator<Output = f64>>(mut ind: I, data: &[f64]) -> Vec<f64> {
+        data.iter()
+            .filter_map(|c| {
+                ind.update(&close(*c));
+                ind.value()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sma() {
+        let values = run(Sma::new(10), &CLOSES);
+        assert_eq!(values.len(), SMA_10.len());
+        for (a, b) in values.iter().zip(SMA_10.iter()) {
+            assert_close(*a, *b, 0.006);
+        }
+    }
+
+    #[test]
+    fn test_ema() {
+        let values = run(Ema::new(10), &CLOSES);
+        assert_eq!(values.len(), EMA_10.len());
+        for (a, b) in values.iter().zip(EMA_10.iter()) {
+            assert_close(*a, *b, 0.006);
+        }
+    }
+
+    #[test]
+    fn test_wma() {
+        assert_eq!(run(Wma::new(3), &[1., 2.]), Vec::<f64>::new());
+        // (1*1 + 2*2 + 3*3) / 6, (2*1 + 3*2 + 4*3) / 6, (3*1 + 4*2 + 1*3) / 6
+        let values = run(Wma::new(3), &[1., 2., 3., 4., 1.]);
+        assert_eq!(values.len(), 3);
+        assert_close(values[0], 14. / 6., 1e-12);
+        assert_close(values[1], 20. / 6., 1e-12);
+        assert_close(values[2], 14. / 6., 1e-12);
+
+        // 与逐窗口重新计算一致
+        let mut naive = Vec::new();
+        for w in CLOSES.windows(5) {
+            naive.push(w.iter().enumerate().map(|(i, v)| (i + 1) as f64 * v).sum::<f64>() / 15.);
+        }
+        for (a, b) in run(Wma::new(5), &CLOSES).iter().zip(naive.iter()) {
+            assert_close(*a, *b, 1e-9);
+        }
+
+        let mut wma = Wma::new(3).with_source(Source::High);
+        for h in [3., 6., 9.] {
+            wma.update(&bar(0., h, 0., 0., 0.));
+        }
+        assert_close(wma.value().unwrap(), 7., 1e-12);
+    }
+}
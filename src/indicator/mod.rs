@@ -0,0 +1,255 @@
+mod kline_bucket;
+mod ma;
+mod order_book;
+mod oscillator;
+mod volatility;
+mod volume;
+
+use crate::model::KData;
+use std::collections::VecDeque;
+
+pub use kline_bucket::{BarKind, KlineBucket, TradeTick};
+pub use ma::{Ema, Sma, Wma};
+pub use order_book::{DiffResult, EdpOrderBook};
+pub use oscillator::{Macd, MacdValue, Rsi, Stochastic, StochasticValue};
+pub use volatility::{Atr, BollingerBands, Donchian};
+pub use volume::{Obv, Vwap};
+
+// 流式指标, 每根K线更新一次, 复杂度 O(1)
+// 数据不足时 value() 返回 None
+pub trait Indicator {
+    type Output;
+
+    fn update(&mut self, k: &KData);
+
+    fn value(&self) -> Option<Self::Output>;
+
+    fn is_ready(&self) -> bool {
+        self.value().is_some()
+    }
+
+    // 把本指标的输出作为下一个指标的输入, 例如 Rsi::new(14).then(Ema::new(9))
+    fn then<B: SeriesIndicator>(self, next: B) -> Chain<Self, B>
+    where
+        Self: Indicator<Output = f64> + Sized,
+    {
+        Chain { first: self, next }
+    }
+}
+
+// 只依赖单个数值序列的指标, 可以接在其它指标后面
+pub trait SeriesIndicator: Indicator {
+    fn update_value(&mut self, v: f64);
+}
+
+// 序列指标从K线中取的值
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Source {
+    Open,
+    High,
+    Low,
+    #[default]
+    Close,
+    Volume,
+    // (high + low) / 2
+    Hl2,
+    // (high + low + close) / 3
+    Hlc3,
+    // (open + high + low + close) / 4
+    Ohlc4,
+}
+
+impl Source {
+    pub fn of(&self, k: &KData) -> f64 {
+        match self {
+            Source::Open => k.open,
+            Source::High => k.high,
+            Source::Low => k.low,
+            Source::Close => k.close,
+            Source::Volume => k.vol,
+            Source::Hl2 => (k.high + k.low) / 2.,
+            Source::Hlc3 => (k.high + k.low + k.close) / 3.,
+            Source::Ohlc4 => (k.open + k.high + k.low + k.close) / 4.,
+        }
+    }
+}
+
+// 上中下三条线, 布林带和唐奇安通道共用
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bands {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+pub struct Chain<A, B> {
+    first: A,
+    next: B,
+}
+
+impl<A: Indicator<Output = f64>, B: SeriesIndicator> Indicator for Chain<A, B> {
+    type Output = B::Output;
+
+    fn update(&mut self, k: &KData) {
+        self.first.update(k);
+        if let Some(v) = self.first.value() {
+            self.next.update_value(v);
+        }
+    }
+
+    fn value(&self) -> Option<Self::Output> {
+        self.next.value()
+    }
+}
+
+impl<A: SeriesIndicator<Output = f64>, B: SeriesIndicator> SeriesIndicator for Chain<A, B> {
+    fn update_value(&mut self, v: f64) {
+        self.first.update_value(v);
+        if let Some(v) = self.first.value() {
+            self.next.update_value(v);
+        }
+    }
+}
+
+// 序列指标的 update 按 source 取值
+macro_rules! impl_series_indicator {
+    ($($t:ty => $out:ty),*) => {
+        $(
+            impl crate::indicator::Indicator for $t {
+                type Output = $out;
+
+                fn update(&mut self, k: &crate::model::KData) {
+                    let v = self.source.of(k);
+                    crate::indicator::SeriesIndicator::update_value(self, v);
+                }
+
+                fn value(&self) -> Option<$out> {
+                    self.current()
+                }
+            }
+        )*
+    };
+}
+pub(crate) use impl_series_indicator;
+
+// 滑动窗口的最大值/最小值, 单调队列, 均摊 O(1)
+pub(crate) struct RollingExtreme {
+    period: usize,
+    count: usize,
+    max: VecDeque<(usize, f64)>,
+    min: VecDeque<(usize, f64)>,
+}
+
+impl RollingExtreme {
+    pub(crate) fn new(period: usize) -> Self {
+        Self {
+            period,
+            count: 0,
+            max: VecDeque::new(),
+            min: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, high: f64, low: f64) {
+        let i = self.count;
+        self.count += 1;
+        while self.max.back().is_some_and(|(_, v)| *v <= high) {
+            self.max.pop_back();
+        }
+        self.max.push_back((i, high));
+        while self.min.back().is_some_and(|(_, v)| *v >= low) {
+            self.min.pop_back();
+        }
+        self.min.push_back((i, low));
+        while self.max.front().is_some_and(|(j, _)| j + self.period <= i) {
+            self.max.pop_front();
+        }
+        while self.min.front().is_some_and(|(j, _)| j + self.period <= i) {
+            self.min.pop_front();
+        }
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.count >= self.period
+    }
+
+    pub(crate) fn highest(&self) -> f64 {
+        self.max.front().map_or(f64::NAN, |(_, v)| *v)
+    }
+
+    pub(crate) fn lowest(&self) -> f64 {
+        self.min.front().map_or(f64::NAN, |(_, v)| *v)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn bar(open: f64, high: f64, low: f64, close: f64, vol: f64) -> KData {
+        KData {
+            ts: 0,
+            open,
+            high,
+            low,
+            close,
+            vol,
+            turnover: vol * close,
+        }
+    }
+
+    pub(crate) fn close(c: f64) -> KData {
+        bar(c, c, c, c, 1.)
+    }
+
+    pub(crate) fn assert_close(a: f64, b: f64, eps: f64) {
+        assert!((a - b).abs() <= eps, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_ema_of_rsi() {
+        let closes = [44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03, 45.61, 46.28, 46.28, 46.00, 46.03];
+        let mut rsi = Rsi::new(14);
+        let mut smoothed = Rsi::new(14).then(Ema::new(3));
+        let mut values = Vec::new();
+        for c in closes {
+            rsi.update(&close(c));
+            smoothed.update(&close(c));
+            if let Some(v) = rsi.value() {
+                values.push(v);
+            }
+            // 需要 15 个收盘价出第一个 RSI, 再要 3 个 RSI 才有 EMA
+            assert_eq!(smoothed.is_ready(), values.len() >= 3);
+        }
+        assert_eq!(values.len(), 3);
+        assert_close(smoothed.value().unwrap(), values.iter().sum::<f64>() / 3., 1e-9);
+
+        // 链式指标也可以继续接
+        let mut chain = Sma::new(2).then(Sma::new(2)).then(Sma::new(2));
+        for c in [1., 2., 3., 4.] {
+            chain.update(&close(c));
+        }
+        assert_close(chain.value().unwrap(), 2.5, 1e-12);
+    }
+
+    #[test]
+    fn test_rolling_extreme() {
+        let mut r = RollingExtreme::new(3);
+        let data = [5., 3., 8., 1., 2., 2., 9., 4.];
+        for (i, v) in data.iter().enumerate() {
+            r.push(*v, *v);
+            let window = &data[i.saturating_sub(2)..=i];
+            assert_eq!(r.highest(), window.iter().cloned().fold(f64::MIN, f64::max));
+            assert_eq!(r.lowest(), window.iter().cloned().fold(f64::MAX, f64::min));
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        let k = bar(1., 4., 0., 2., 10.);
+        assert_eq!(Source::Hl2.of(&k), 2.);
+        assert_eq!(Source::Hlc3.of(&k), 2.);
+        assert_eq!(Source::Ohlc4.of(&k), 1.75);
+        assert_eq!(Source::Volume.of(&k), 10.);
+    }
+}
@@ -0,0 +1,505 @@
+use crate::error::Result;
+use crate::model::{OrderBook, Side};
+use crate::traits::MarketDataAPI;
+use crate::ws::event::DepthEvent;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+// 用于BTreeMap排序的价格
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Price(f64);
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// 一次增量推送的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffResult {
+    Applied,
+    // 已被快照包含, 丢弃
+    Stale,
+    // 还没有快照, 先缓存
+    Buffered,
+    // 序号不连续, 订单簿已失效, 需要重新获取快照
+    Gap,
+}
+
+// 由REST快照和 depth@100ms 增量维护的本地订单簿
+// https://binance-docs.github.io/apidocs/futures/en/#how-to-manage-a-local-order-book-correctly
+pub struct EdpOrderBook {
+    symbol: String,
+    bids: BTreeMap<Price, f64>,
+    asks: BTreeMap<Price, f64>,
+    // 最后应用的 u, None 表示还没有可用的快照
+    last_update_id: Option<i64>,
+    // 快照之后还没有应用过增量时为快照的 lastUpdateId, 下一条增量按快照规则检查
+    snapshot_id: Option<i64>,
+    // 快照之前收到的增量
+    buffer: Vec<DepthEvent>,
+    // 获取快照时的档位数量
+    pub snapshot_limit: u64,
+}
+
+impl EdpOrderBook {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update_id: None,
+            snapshot_id: None,
+            buffer: Vec::new(),
+            snapshot_limit: 1000,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.last_update_id.is_some()
+    }
+
+    pub fn last_update_id(&self) -> Option<i64> {
+        self.last_update_id
+    }
+
+    // 断线重连后调用, 清空订单簿等待新快照
+    pub fn reset(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.last_update_id = None;
+        self.snapshot_id = None;
+        self.buffer.clear();
+    }
+
+    // 应用快照并重放缓存的增量, 返回是否同步成功
+    // 快照比缓存的第一条增量还旧时返回false, 需要重新获取快照
+    pub fn apply_snapshot(&mut self, snapshot: &OrderBook) -> bool {
+        self.bids = snapshot.bids.iter().filter(|l| l.qty > 0.).map(|l| (Price(l.price), l.qty)).collect();
+        self.asks = snapshot.asks.iter().filter(|l| l.qty > 0.).map(|l| (Price(l.price), l.qty)).collect();
+        let last = snapshot.last_update_id;
+        let mut buffer = std::mem::take(&mut self.buffer).into_iter();
+        let mut prev: Option<i64> = None;
+        while let Some(event) = buffer.next() {
+            let in_sequence = match prev {
+                None if is_stale(&event, last) => continue,
+                None => bridges_snapshot(&event, last),
+                Some(prev_u) => follows(&event, prev_u),
+            };
+            if !in_sequence {
+                self.reset();
+                self.buffer.push(event);
+                self.buffer.extend(buffer);
+                return false;
+            }
+            self.apply_levels(&event);
+            prev = Some(event.final_update_id);
+        }
+        self.last_update_id = Some(prev.unwrap_or(last));
+        self.snapshot_id = if prev.is_none() { Some(last) } else { None };
+        true
+    }
+
+    pub fn apply_diff(&mut self, event: &DepthEvent) -> DiffResult {
+        let last = match self.last_update_id {
+            Some(last) => last,
+            None => {
+                self.buffer.push(event.clone());
+                return DiffResult::Buffered;
+            }
+        };
+        let in_sequence = match self.snapshot_id.take() {
+            // 快照后的第一条增量
+            Some(snapshot_id) => {
+                if is_stale(event, snapshot_id) {
+                    self.snapshot_id = Some(snapshot_id);
+                    return DiffResult::Stale;
+                }
+                bridges_snapshot(event, snapshot_id)
+            }
+            None => follows(event, last),
+        };
+        if !in_sequence {
+            log::warn!(
+                "{} depth gap: last u {}, got U {} u {} pu {:?}",
+                self.symbol,
+                last,
+                event.first_update_id,
+                event.final_update_id,
+                event.prev_final_update_id
+            );
+            self.reset();
+            self.buffer.push(event.clone());
+            return DiffResult::Gap;
+        }
+        self.apply_levels(event);
+        self.last_update_id = Some(event.final_update_id);
+        DiffResult::Applied
+    }
+
+    // 应用增量, 未同步或出现缺口时通过REST重新获取快照
+    pub async fn update<M: MarketDataAPI + ?Sized>(&mut self, api: &M, event: &DepthEvent) -> Result<DiffResult> {
+        let result = self.apply_diff(event);
+        if matches!(result, DiffResult::Buffered | DiffResult::Gap) {
+            let snapshot = api.get_order_book(&self.symbol, Some(self.snapshot_limit)).await?;
+            self.apply_snapshot(&snapshot);
+        }
+        Ok(result)
+    }
+
+    fn apply_levels(&mut self, event: &DepthEvent) {
+        for l in &event.bids {
+            update_level(&mut self.bids, l.price, l.qty);
+        }
+        for l in &event.asks {
+            update_level(&mut self.asks, l.price, l.qty);
+        }
+    }
+
+    // (价格, 数量)
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.iter().next_back().map(|(p, q)| (p.0, *q))
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.iter().next().map(|(p, q)| (p.0, *q))
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some((b, _)), Some((a, _))) => Some((b + a) / 2.),
+            _ => None,
+        }
+    }
+
+    // 从最优价开始的前n档
+    pub fn top_bids(&self, n: usize) -> Vec<(f64, f64)> {
+        self.bids.iter().rev().take(n).map(|(p, q)| (p.0, *q)).collect()
+    }
+
+    pub fn top_asks(&self, n: usize) -> Vec<(f64, f64)> {
+        self.asks.iter().take(n).map(|(p, q)| (p.0, *q)).collect()
+    }
+
+    // 某个价格上的挂单量, Buy 为买盘
+    pub fn depth_at(&self, side: Side, price: f64) -> f64 {
+        self.levels(side).get(&Price(price)).copied().unwrap_or(0.)
+    }
+
+    // 从最优价到 price(含)的累计挂单量
+    pub fn cumulative_volume(&self, side: Side, price: f64) -> f64 {
+        match side {
+            Side::Buy => self.bids.range(Price(price)..).map(|(_, q)| q).sum(),
+            Side::Sell => self.asks.range(..=Price(price)).map(|(_, q)| q).sum(),
+        }
+    }
+
+    fn levels(&self, side: Side) -> &BTreeMap<Price, f64> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+}
+
+fn update_level(levels: &mut BTreeMap<Price, f64>, price: f64, qty: f64) {
+    if qty == 0. {
+        levels.remove(&Price(price));
+    } else {
+        levels.insert(Price(price), qty);
+    }
+}
+
+// 合约推送带 pu, 规则与现货不同
+// 现货: 丢弃 u <= lastUpdateId, 第一条满足 U <= lastUpdateId + 1 <= u
+// 合约: 丢弃 u < lastUpdateId, 第一条满足 U <= lastUpdateId <= u
+fn is_stale(event: &DepthEvent, snapshot_id: i64) -> bool {
+    match event.prev_final_update_id {
+        Some(_) => event.final_update_id < snapshot_id,
+        None => event.final_update_id <= snapshot_id,
+    }
+}
+
+fn bridges_snapshot(event: &DepthEvent, snapshot_id: i64) -> bool {
+    let target = match event.prev_final_update_id {
+        Some(_) => snapshot_id,
+        None => snapshot_id + 1,
+    };
+    event.first_update_id <= target && event.final_update_id >= target
+}
+
+// 现货: U == 上一条u + 1, 合约: pu == 上一条u
+fn follows(event: &DepthEvent, prev_u: i64) -> bool {
+    match event.prev_final_update_id {
+        Some(pu) => pu == prev_u,
+        None => event.first_update_id == prev_u + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::spot::BinanceSpot;
+    use crate::model::{Asks, Bids};
+    use crate::rest::stub::{StubResponse, StubServer};
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicI64, Ordering as AtomicOrdering};
+
+    // (是否买盘, 价格序号, 数量)
+    type Level = (bool, u8, u8);
+    type Levels = Vec<(f64, f64)>;
+
+    fn bids(levels: &[(f64, f64)]) -> Vec<Bids> {
+        levels.iter().map(|&(price, qty)| Bids { price, qty }).collect()
+    }
+
+    fn asks(levels: &[(f64, f64)]) -> Vec<Asks> {
+        levels.iter().map(|&(price, qty)| Asks { price, qty }).collect()
+    }
+
+    fn snapshot(last_update_id: i64, b: &[(f64, f64)], a: &[(f64, f64)]) -> OrderBook {
+        OrderBook {
+            last_update_id,
+            event_time: 0,
+            trade_order_time: 0,
+            bids: bids(b),
+            asks: asks(a),
+        }
+    }
+
+    fn diff(first: i64, last: i64, pu: Option<i64>, b: &[(f64, f64)], a: &[(f64, f64)]) -> DepthEvent {
+        DepthEvent {
+            event_time: 0,
+            symbol: "BTCUSDT".to_string(),
+            first_update_id: first,
+            final_update_id: last,
+            prev_final_update_id: pu,
+            bids: bids(b),
+            asks: asks(a),
+        }
+    }
+
+    #[test]
+    fn test_spot_sequence() {
+        let mut book = EdpOrderBook::new("BTCUSDT");
+        assert_eq!(book.apply_diff(&diff(98, 100, None, &[(9., 1.)], &[])), DiffResult::Buffered);
+        assert_eq!(book.apply_diff(&diff(101, 103, None, &[(10., 2.)], &[(11., 1.)])), DiffResult::Buffered);
+        assert!(book.apply_snapshot(&snapshot(101, &[(10., 1.), (9., 5.)], &[(11., 3.), (12., 4.)])));
+        // 98-100 已包含在快照中, 101-103 覆盖了 lastUpdateId + 1
+        assert_eq!(book.last_update_id(), Some(103));
+        assert_eq!(book.best_bid(), Some((10., 2.)));
+        assert_eq!(book.depth_at(Side::Buy, 9.), 5.);
+
+        assert_eq!(book.apply_diff(&diff(104, 104, None, &[(10., 0.)], &[(11.5, 2.)])), DiffResult::Applied);
+        assert_eq!(book.best_bid(), Some((9., 5.)));
+        assert_eq!(book.best_ask(), Some((11., 1.)));
+        assert_eq!(book.top_asks(2), vec![(11., 1.), (11.5, 2.)]);
+        assert_eq!(book.cumulative_volume(Side::Sell, 11.9), 3.);
+        assert_eq!(book.cumulative_volume(Side::Buy, 9.), 5.);
+        assert_eq!(book.mid_price(), Some(10.));
+
+        assert_eq!(book.apply_diff(&diff(106, 107, None, &[], &[])), DiffResult::Gap);
+        assert!(!book.is_synced());
+        assert_eq!(book.best_bid(), None);
+    }
+
+    #[test]
+    fn test_futures_sequence() {
+        let mut book = EdpOrderBook::new("BTCUSDT");
+        assert!(book.apply_snapshot(&snapshot(158, &[(10., 1.)], &[(11., 1.)])));
+        assert_eq!(book.apply_diff(&diff(140, 149, Some(139), &[(10., 7.)], &[])), DiffResult::Stale);
+        assert_eq!(book.apply_diff(&diff(157, 160, Some(149), &[(10., 2.)], &[])), DiffResult::Applied);
+        assert_eq!(book.apply_diff(&diff(165, 170, Some(160), &[], &[(11., 0.)])), DiffResult::Applied);
+        assert_eq!(book.best_ask(), None);
+        assert_eq!(book.best_bid(), Some((10., 2.)));
+        assert_eq!(book.apply_diff(&diff(175, 180, Some(171), &[], &[])), DiffResult::Gap);
+        assert!(!book.is_synced());
+    }
+
+    #[test]
+    fn test_snapshot_older_than_buffer() {
+        let mut book = EdpOrderBook::new("BTCUSDT");
+        book.apply_diff(&diff(110, 120, None, &[(10., 1.)], &[]));
+        assert!(!book.apply_snapshot(&snapshot(100, &[(10., 5.)], &[])));
+        assert!(!book.is_synced());
+        assert!(book.apply_snapshot(&snapshot(115, &[(10., 5.)], &[])));
+        assert_eq!(book.best_bid(), Some((10., 1.)));
+    }
+
+    #[tokio::test]
+    async fn test_update_resyncs_from_rest() {
+        let calls = AtomicI64::new(0);
+        let server = StubServer::start(move |_| {
+            let last_update_id = 200 + 5 * calls.fetch_add(1, AtomicOrdering::SeqCst);
+            StubResponse::ok(&format!(
+                r#"{{"lastUpdateId":{},"bids":[["10.0","1.0"]],"asks":[["11.0","1.0"]]}}"#,
+                last_update_id
+            ))
+        })
+        .await;
+        let api = BinanceSpot::new(server.url());
+        let mut book = EdpOrderBook::new("BTCUSDT");
+        book.snapshot_limit = 100;
+        let result = book.update(&api, &diff(195, 201, None, &[(10., 3.)], &[])).await.unwrap();
+        assert_eq!(result, DiffResult::Buffered);
+        assert!(book.is_synced());
+        assert_eq!(book.best_bid(), Some((10., 3.)));
+
+        // 出现缺口后重新获取快照(lastUpdateId 205), 缺口之后的增量已包含在快照中
+        let result = book.update(&api, &diff(203, 203, None, &[(10., 9.)], &[])).await.unwrap();
+        assert_eq!(result, DiffResult::Gap);
+        assert_eq!(book.best_bid(), Some((10., 1.)));
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].path, "/api/v3/depth");
+        assert_eq!(reqs[0].param("limit").as_deref(), Some("100"));
+    }
+
+    // 朴素实现: 按顺序应用所有档位后排序
+    #[derive(Default, Clone)]
+    struct NaiveBook {
+        bids: HashMap<u8, f64>,
+        asks: HashMap<u8, f64>,
+    }
+
+    impl NaiveBook {
+        fn apply(&mut self, levels: &[Level]) {
+            for &(is_bid, idx, qty) in levels {
+                let side = if is_bid { &mut self.bids } else { &mut self.asks };
+                if qty == 0 {
+                    side.remove(&idx);
+                } else {
+                    side.insert(idx, qty as f64);
+                }
+            }
+        }
+
+        fn sorted(side: &HashMap<u8, f64>, descending: bool) -> Levels {
+            let mut levels: Levels = side.iter().map(|(&i, &q)| (price(i), q)).collect();
+            levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+            if descending {
+                levels.reverse();
+            }
+            levels
+        }
+    }
+
+    fn price(idx: u8) -> f64 {
+        100. + idx as f64 * 0.5
+    }
+
+    fn split(levels: &[Level]) -> (Levels, Levels) {
+        let b = levels.iter().filter(|l| l.0).map(|l| (price(l.1), l.2 as f64)).collect();
+        let a = levels.iter().filter(|l| !l.0).map(|l| (price(l.1), l.2 as f64)).collect();
+        (b, a)
+    }
+
+    fn levels() -> impl Strategy<Value = Vec<Level>> {
+        prop::collection::vec((any::<bool>(), 0u8..20, 0u8..4), 0..8)
+    }
+
+    proptest! {
+        // 任意时刻获取快照, 在快照前后收到的增量都应被正确处理, 结果与朴素重建一致
+        #[test]
+        fn prop_matches_naive_rebuild(
+            updates in prop::collection::vec((levels(), 1i64..4, 1i64..3), 1..30),
+            snapshot_at in any::<prop::sample::Index>(),
+            received_before in any::<prop::sample::Index>(),
+            futures in any::<bool>(),
+        ) {
+            let n = updates.len();
+            let mut events = Vec::new();
+            let mut prev_u = 1000i64;
+            for (lv, width, gap) in &updates {
+                let (b, a) = split(lv);
+                let (first, pu) = if futures { (prev_u + gap, Some(prev_u)) } else { (prev_u + 1, None) };
+                let last = first + width - 1;
+                events.push(diff(first, last, pu, &b, &a));
+                prev_u = last;
+            }
+
+            // 快照包含前k条增量, 合约的快照id落在第k条增量的区间内
+            let k = if futures { snapshot_at.index(n) } else { snapshot_at.index(n + 1) };
+            let mut naive = NaiveBook::default();
+            for (lv, _, _) in &updates[..k] {
+                naive.apply(lv);
+            }
+            let snapshot_id = if futures {
+                events[k].first_update_id
+            } else if k == 0 {
+                1000
+            } else {
+                events[k - 1].final_update_id
+            };
+            let snap = snapshot(
+                snapshot_id,
+                &NaiveBook::sorted(&naive.bids, true),
+                &NaiveBook::sorted(&naive.asks, false),
+            );
+            for (lv, _, _) in &updates[k..] {
+                naive.apply(lv);
+            }
+
+            let m = received_before.index(n + 1);
+            let mut book = EdpOrderBook::new("BTCUSDT");
+            for ev in &events[..m] {
+                prop_assert_eq!(book.apply_diff(ev), DiffResult::Buffered);
+            }
+            prop_assert!(book.apply_snapshot(&snap));
+            for ev in &events[m..] {
+                let result = book.apply_diff(ev);
+                prop_assert!(result == DiffResult::Applied || result == DiffResult::Stale);
+            }
+
+            prop_assert_eq!(book.top_bids(usize::MAX), NaiveBook::sorted(&naive.bids, true));
+            prop_assert_eq!(book.top_asks(usize::MAX), NaiveBook::sorted(&naive.asks, false));
+            for idx in 0u8..20 {
+                let p = price(idx);
+                let bid_cum: f64 = naive.bids.iter().filter(|(&i, _)| price(i) >= p).map(|(_, q)| q).sum();
+                let ask_cum: f64 = naive.asks.iter().filter(|(&i, _)| price(i) <= p).map(|(_, q)| q).sum();
+                prop_assert_eq!(book.cumulative_volume(Side::Buy, p), bid_cum);
+                prop_assert_eq!(book.cumulative_volume(Side::Sell, p), ask_cum);
+                prop_assert_eq!(book.depth_at(Side::Buy, p), naive.bids.get(&idx).copied().unwrap_or(0.));
+            }
+        }
+
+        // 丢失任意一条快照之后的增量都会被发现
+        #[test]
+        fn prop_detects_dropped_diff(
+            n in 2usize..20,
+            dropped in any::<prop::sample::Index>(),
+            futures in any::<bool>(),
+        ) {
+            let mut book = EdpOrderBook::new("BTCUSDT");
+            prop_assert!(book.apply_snapshot(&snapshot(1000, &[], &[])));
+            let dropped = dropped.index(n - 1) + 1;
+            let mut prev_u = 1000i64;
+            let mut results = Vec::new();
+            for i in 0..n {
+                let first = if futures { prev_u + 2 } else { prev_u + 1 };
+                let first = if i == 0 && futures { 1000 } else { first };
+                let pu = if futures { Some(prev_u) } else { None };
+                let ev = diff(first, first + 1, pu, &[(price(1), 1.)], &[]);
+                prev_u = first + 1;
+                if i != dropped {
+                    results.push(book.apply_diff(&ev));
+                }
+            }
+            prop_assert_eq!(results.iter().filter(|r| **r == DiffResult::Gap).count(), if dropped < n - 1 { 1 } else { 0 });
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::indicator::{impl_series_indicator, Ema, Indicator, RollingExtreme, SeriesIndicator, Sma, Source};
+use crate::model::KData;
+
+// 相对强弱指数, Wilder 平滑: 前 period 个涨跌幅取均值作为初值
+pub struct Rsi {
+    period: usize,
+    source: Source,
+    prev: Option<f64>,
+    count: usize,
+    avg_gain: f64,
+    avg_loss: f64,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            period,
+            source: Source::Close,
+            prev: None,
+            count: 0,
+            avg_gain: 0.,
+            avg_loss: 0.,
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    fn current(&self) -> Option<f64> {
+        if self.count < self.period {
+            return None;
+        }
+        if self.avg_loss == 0. {
+            return Some(if self.avg_gain == 0. { 50. } else { 100. });
+        }
+        Some(100. - 100. / (1. + self.avg_gain / self.avg_loss))
+    }
+}
+
+impl SeriesIndicator for Rsi {
+    fn update_value(&mut self, v: f64) {
+        let Some(prev) = self.prev.replace(v) else {
+            return;
+        };
+        let change = v - prev;
+        let (gain, loss) = (change.max(0.), (-change).max(0.));
+        self.count += 1;
+        let n = self.period as f64;
+        if self.count <= self.period {
+            self.avg_gain += gain / n;
+            self.avg_loss += loss / n;
+        } else {
+            self.avg_gain = (self.avg_gain * (n - 1.) + gain) / n;
+            self.avg_loss = (self.avg_loss * (n - 1.) + loss) / n;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdValue {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+// MACD = EMA(fast) - EMA(slow), signal 为 MACD 的 EMA
+pub struct Macd {
+    source: Source,
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        assert!(fast < slow, "fast period must be shorter than slow period");
+        Self {
+            source: Source::Close,
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    fn current(&self) -> Option<MacdValue> {
+        let macd = self.fast.value()? - self.slow.value()?;
+        let signal = self.signal.value()?;
+        Some(MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        })
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Self::new(12, 26, 9)
+    }
+}
+
+impl SeriesIndicator for Macd {
+    fn update_value(&mut self, v: f64) {
+        self.fast.update_value(v);
+        self.slow.update_value(v);
+        if let (Some(fast), Some(slow)) = (self.fast.value(), self.slow.value()) {
+            self.signal.update_value(fast - slow);
+        }
+    }
+}
+
+impl_series_indicator!(Rsi => f64, Macd => MacdValue);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StochasticValue {
+    pub k: f64,
+    pub d: f64,
+}
+
+// 随机指标, %K = (close - 最低价) / (最高价 - 最低价) * 100, %D 为 %K 的 SMA
+// 窗口内最高价等于最低价时 %K 取 50
+pub struct Stochastic {
+    range: RollingExtreme,
+    k: Option<f64>,
+    d: Sma,
+}
+
+impl Stochastic {
+    pub fn new(k_period: usize, d_period: usize) -> Self {
+        assert!(k_period > 0, "period must be positive");
+        Self {
+            range: RollingExtreme::new(k_period),
+            k: None,
+            d: Sma::new(d_period),
+        }
+    }
+}
+
+impl Indicator for Stochastic {
+    type Output = StochasticValue;
+
+    fn update(&mut self, k: &KData) {
+        self.range.push(k.high, k.low);
+        if !self.range.is_full() {
+            return;
+        }
+        let (high, low) = (self.range.highest(), self.range.lowest());
+        let value = if high > low { (k.close - low) / (high - low) * 100. } else { 50. };
+        self.k = Some(value);
+        self.d.update_value(value);
+    }
+
+    fn value(&self) -> Option<StochasticValue> {
+        Some(StochasticValue {
+            k: self.k?,
+            d: self.d.value()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicator::tests::{assert_close, bar, close};
+
+    // StockCharts 的 14 日 RSI 示例, 表格中间结果保留了两位小数, 与精确计算相差不到 0.1
+    const CLOSES: [f64; 33] = [
+        44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03, 45.61, 46.28, 46.28,
+        46.00, 46.03, 46.41, 46.22, 45.64, 46.21, 46.25, 45.71, 46.45, 45.78, 45.35, 44.03, 44.18, 44.22, 44.57,
+        43.42, 42.66, 43.13,
+    ];
+    const RSI_14: [f64; 19] = [
+        70.53, 66.32, 66.55, 69.41, 66.36, 57.97, 62.93, 63.26, 56.06, 62.38, 54.71, 50.42, 39.99, 41.46, 41.87,
+        45.46, 37.30, 33.08, 37.77,
+    ];
+
+    #[test]
+    fn test_rsi() {
+        let mut rsi = Rsi::new(14);
+        let mut values = Vec::new();
+        for c in CLOSES {
+            rsi.update(&close(c));
+            values.extend(rsi.value());
+        }
+        assert_eq!(values.len(), RSI_14.len());
+        for (a, b) in values.iter().zip(RSI_14.iter()) {
+            assert_close(*a, *b, 0.1);
+        }
+
+        let mut flat = Rsi::new(2);
+        for _ in 0..3 {
+            flat.update(&close(1.));
+        }
+        assert_eq!(flat.value(), Some(50.));
+    }
+
+    #[test]
+    fn test_macd() {
+        // 参考值按定义逐步计算: EMA 以 SMA 为初值
+        let mut macd = Macd::new(3, 5, 2);
+        let mut values = Vec::new();
+        for c in CLOSES.iter().take(8) {
+            macd.update(&close(*c));
+            values.extend(macd.value());
+        }
+        let expected = [(0.1269166667, 0.0693750000), (0.1891250000, 0.1492083333), (0.2316736111, 0.2041851852)];
+        assert_eq!(values.len(), expected.len());
+        for (v, (m, s)) in values.iter().zip(expected.iter()) {
+            assert_close(v.macd, *m, 1e-9);
+            assert_close(v.signal, *s, 1e-9);
+            assert_close(v.histogram, m - s, 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_stochastic() {
+        let mut stoch = Stochastic::new(3, 2);
+        let bars = [
+            bar(10., 12., 9., 11., 1.),
+            bar(11., 13., 10., 12., 1.),
+            bar(12., 14., 11., 13., 1.),
+            bar(13., 13., 8., 9., 1.),
+            bar(9., 10., 9., 10., 1.),
+        ];
+        let mut values = Vec::new();
+        for b in bars.iter() {
+            stoch.update(b);
+            values.push(stoch.value());
+        }
+        assert_eq!(values[..3], [None, None, None]);
+        // 第 4 根: 窗口最高 14 最低 8, (9 - 8) / 6; 第 3 根 (13 - 9) / 5
+        let v = values[3].unwrap();
+        assert_close(v.k, 100. / 6., 1e-9);
+        assert_close(v.d, (80. + 100. / 6.) / 2., 1e-9);
+        let v = values[4].unwrap();
+        assert_close(v.k, 200. / 6., 1e-9);
+        assert_close(v.d, (100. / 6. + 200. / 6.) / 2., 1e-9);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::indicator::{impl_series_indicator, Bands, Indicator, RollingExtreme, SeriesIndicator, Source};
+use crate::model::KData;
+use std::collections::VecDeque;
+
+// 布林带, 中轨为 SMA, 上下轨为中轨 ± k 倍总体标准差
+pub struct BollingerBands {
+    period: usize,
+    k: f64,
+    source: Source,
+    window: VecDeque<f64>,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, k: f64) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            period,
+            k,
+            source: Source::Close,
+            window: VecDeque::with_capacity(period + 1),
+            sum: 0.,
+            sum_sq: 0.,
+        }
+    }
+
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    fn current(&self) -> Option<Bands> {
+        if self.window.len() < self.period {
+            return None;
+        }
+        let n = self.period as f64;
+        let mean = self.sum / n;
+        // 浮点误差可能让方差略小于 0
+        let std = (self.sum_sq / n - mean * mean).max(0.).sqrt();
+        Some(Bands {
+            upper: mean + self.k * std,
+            middle: mean,
+            lower: mean - self.k * std,
+        })
+    }
+}
+
+impl SeriesIndicator for BollingerBands {
+    fn update_value(&mut self, v: f64) {
+        self.window.push_back(v);
+        self.sum += v;
+        self.sum_sq += v * v;
+        if self.window.len() > self.period {
+            let old = self.window.pop_front().unwrap();
+            self.sum -= old;
+            self.sum_sq -= old * old;
+        }
+    }
+}
+
+impl_series_indicator!(BollingerBands => Bands);
+
+// 平均真实波幅, Wilder 平滑, 第一根的真实波幅为 high - low
+pub struct Atr {
+    period: usize,
+    prev_close: Option<f64>,
+    count: usize,
+    value: f64,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            period,
+            prev_close: None,
+            count: 0,
+            value: 0.,
+        }
+    }
+}
+
+impl Indicator for Atr {
+    type Output = f64;
+
+    fn update(&mut self, k: &KData) {
+        let tr = match self.prev_close.replace(k.close) {
+            Some(c) => (k.high - k.low).max((k.high - c).abs()).max((k.low - c).abs()),
+            None => k.high - k.low,
+        };
+        self.count += 1;
+        let n = self.period as f64;
+        if self.count <= self.period {
+            self.value += tr / n;
+        } else {
+            self.value = (self.value * (n - 1.) + tr) / n;
+        }
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.count >= self.period).then_some(self.value)
+    }
+}
+
+// 唐奇安通道, 上轨为 period 内最高价, 下轨为最低价
+pub struct Donchian {
+    range: RollingExtreme,
+}
+
+impl Donchian {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        Self {
+            range: RollingExtreme::new(period),
+        }
+    }
+}
+
+impl Indicator for Donchian {
+    type Output = Bands;
+
+    fn update(&mut self, k: &KData) {
+        self.range.push(k.high, k.low);
+    }
+
+    fn value(&self) -> Option<Bands> {
+        if !self.range.is_full() {
+            return None;
+        }
+        let (upper, lower) = (self.range.highest(), self.range.lowest());
+        Some(Bands {
+            upper,
+            middle: (upper + lower) / 2.,
+            lower,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicator::tests::{assert_close, bar, close};
+
+    #[test]
+    fn test_bollinger_bands() {
+        let mut bb = BollingerBands::new(4, 2.);
+        for c in [2., 4., 4., 4.] {
+            assert!(!bb.is_ready());
+            bb.update(&close(c));
+        }
+        // 均值 3.5, 总体标准差 sqrt(0.75)
+        let v = bb.value().unwrap();
+        assert_close(v.middle, 3.5, 1e-12);
+        assert_close(v.upper, 3.5 + 2. * 0.75f64.sqrt(), 1e-12);
+        assert_close(v.lower, 3.5 - 2. * 0.75f64.sqrt(), 1e-12);
+        for c in [5., 5., 7., 9.] {
+            bb.update(&close(c));
+        }
+        // 窗口 5, 5, 7, 9: 均值 6.5, 方差 2.75
+        let v = bb.value().unwrap();
+        assert_close(v.middle, 6.5, 1e-12);
+        assert_close(v.upper - v.middle, 2. * 2.75f64.sqrt(), 1e-9);
+    }
+
+    #[test]
+    fn test_atr() {
+        let mut atr = Atr::new(3);
+        let bars = [
+            bar(10., 12., 9., 11., 1.),
+            bar(11., 13., 10., 12., 1.),
+            // 跳空: |high - prev_close| = 4
+            bar(15., 16., 14., 15., 1.),
+            bar(15., 15., 11., 12., 1.),
+        ];
+        let mut values = Vec::new();
+        for b in bars.iter() {
+            atr.update(b);
+            values.push(atr.value());
+        }
+        assert_eq!(values[..2], [None, None]);
+        // TR: 3, 3, 4, 4
+        assert_close(values[2].unwrap(), 10. / 3., 1e-12);
+        assert_close(values[3].unwrap(), (10. / 3. * 2. + 4.) / 3., 1e-12);
+    }
+
+    #[test]
+    fn test_donchian() {
+        let mut dc = Donchian::new(2);
+        dc.update(&bar(0., 5., 1., 0., 0.));
+        assert!(dc.value().is_none());
+        dc.update(&bar(0., 3., 2., 0., 0.));
+        assert_eq!(dc.value().unwrap(), Bands { upper: 5., middle: 3., lower: 1. });
+        dc.update(&bar(0., 4., 3., 0., 0.));
+        assert_eq!(dc.value().unwrap(), Bands { upper: 4., middle: 3., lower: 2. });
+    }
+}
@@ -0,0 +1,122 @@
+use crate::indicator::Indicator;
+use crate::model::KData;
+
+// 能量潮, 收盘上涨累加成交量, 下跌减去, 从 0 开始
+#[derive(Default)]
+pub struct Obv {
+    prev_close: Option<f64>,
+    value: f64,
+}
+
+impl Obv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Indicator for Obv {
+    type Output = f64;
+
+    fn update(&mut self, k: &KData) {
+        if let Some(prev) = self.prev_close {
+            if k.close > prev {
+                self.value += k.vol;
+            } else if k.close < prev {
+                self.value -= k.vol;
+            }
+        }
+        self.prev_close = Some(k.close);
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.prev_close.map(|_| self.value)
+    }
+}
+
+// 成交量加权均价, 价格取 (high + low + close) / 3
+// 设置 session 后按 ts 每 session 毫秒重新累计, 例如 86_400_000 为 UTC 日内 VWAP
+#[derive(Default)]
+pub struct Vwap {
+    session: Option<u64>,
+    session_start: u64,
+    pv: f64,
+    vol: f64,
+}
+
+impl Vwap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_session(mut self, session_ms: u64) -> Self {
+        assert!(session_ms > 0, "session must be positive");
+        self.session = Some(session_ms);
+        self
+    }
+}
+
+impl Indicator for Vwap {
+    type Output = f64;
+
+    fn update(&mut self, k: &KData) {
+        if let Some(session) = self.session {
+            let start = k.ts - k.ts % session;
+            if start != self.session_start {
+                self.session_start = start;
+                self.pv = 0.;
+                self.vol = 0.;
+            }
+        }
+        self.pv += (k.high + k.low + k.close) / 3. * k.vol;
+        self.vol += k.vol;
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.vol > 0.).then(|| self.pv / self.vol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicator::tests::{assert_close, bar};
+
+    #[test]
+    fn test_obv() {
+        let mut obv = Obv::new();
+        assert!(!obv.is_ready());
+        let bars = [
+            bar(0., 10., 10., 10., 100.),
+            bar(0., 11., 11., 11., 50.),
+            bar(0., 11., 11., 11., 70.),
+            bar(0., 9., 9., 9., 30.),
+        ];
+        let values: Vec<f64> = bars
+            .iter()
+            .map(|b| {
+                obv.update(b);
+                obv.value().unwrap()
+            })
+            .collect();
+        assert_eq!(values, vec![0., 50., 50., 20.]);
+    }
+
+    #[test]
+    fn test_vwap() {
+        let mut vwap = Vwap::new().with_session(60_000);
+        assert!(vwap.value().is_none());
+        let mut k = bar(0., 12., 9., 9., 2.);
+        k.ts = 60_000;
+        vwap.update(&k);
+        let mut k = bar(0., 15., 12., 12., 1.);
+        k.ts = 90_000;
+        vwap.update(&k);
+        // (10 * 2 + 13 * 1) / 3
+        assert_close(vwap.value().unwrap(), 11., 1e-12);
+        // 新的 session 重新累计
+        let mut k = bar(0., 21., 18., 18., 4.);
+        k.ts = 120_000;
+        vwap.update(&k);
+        assert_close(vwap.value().unwrap(), 19., 1e-12);
+    }
+}
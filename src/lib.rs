@@ -5,9 +5,11 @@ pub mod rest;
 pub mod ws;
 pub mod error;
 pub mod model;
+pub mod order;
+pub mod oco;
 pub mod binance;
 pub mod traits;
-pub(crate) mod indicator;
+pub mod indicator;
 
 
 #[cfg(test)]
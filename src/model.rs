@@ -1,7 +1,160 @@
 use serde::{Serialize, Deserialize};
-use serde::de::{DeserializeOwned};
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+// 现货与合约的订单类型合集, 各市场支持的类型不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderType {
+    Limit,
+    Market,
+    // 现货
+    StopLoss,
+    StopLossLimit,
+    TakeProfitLimit,
+    LimitMaker,
+    // 合约
+    Stop,
+    StopMarket,
+    // 现货和合约都有: 现货触发后下市价单, 合约触发后下限价单
+    TakeProfit,
+    TakeProfitMarket,
+    TrailingStopMarket,
+    Liquidation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimeInForce {
+    Gtc,
+    Ioc,
+    Fok,
+    // Good Till Crossing, 只做maker
+    Gtx,
+    // 合约止盈止损单的返回值
+    GteGtc,
+    // 合约, 到 goodTillDate 自动撤销
+    Gtd,
+    // 交易所新增的类型, 避免整条消息解析失败
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PositionSide {
+    Both,
+    Long,
+    Short,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    PendingCancel,
+    Rejected,
+    Expired,
+    ExpiredInMatch,
+    NewInsurance,
+    NewAdl,
+    // 现货, 订单已接受但还未进入撮合, 例如 OTO 的挂起单
+    PendingNew,
+    // 交易所新增的状态, 避免整条消息解析失败
+    #[serde(other)]
+    Unknown,
+}
+
+// 用户数据流中订单更新的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExecutionType {
+    New,
+    Canceled,
+    Replaced,
+    Rejected,
+    Trade,
+    Expired,
+    TradePrevention,
+    // 合约
+    Calculated,
+    Amendment,
+}
+
+// 合约条件单的触发价格类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WorkingType {
+    MarkPrice,
+    ContractPrice,
+}
+
+// 逐仓/全仓, positionRisk 返回小写的 isolated/cross
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MarginType {
+    #[serde(alias = "isolated")]
+    Isolated,
+    #[serde(alias = "cross", alias = "crossed")]
+    Crossed,
+}
+
+// 合约类型, 交割合约在交割期间带 _DELIVERING 后缀
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ContractType {
+    Perpetual,
+    CurrentMonth,
+    NextMonth,
+    CurrentQuarter,
+    NextQuarter,
+    PerpetualDelivering,
+    CurrentQuarterDelivering,
+    NextQuarterDelivering,
+}
+
+impl OrderStatus {
+    // 订单已结束, 不会再有成交
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled
+                | OrderStatus::Canceled
+                | OrderStatus::Expired
+                | OrderStatus::ExpiredInMatch
+                | OrderStatus::Rejected
+        )
+    }
+}
+
+// 与serde的命名保持一致, 用于拼接请求参数
+macro_rules! impl_param_display {
+    ($($t:ty),*) => {
+        $(
+            impl fmt::Display for $t {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    match serde_json::to_value(self) {
+                        Ok(serde_json::Value::String(s)) => f.write_str(&s),
+                        _ => Err(fmt::Error),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_param_display!(Side, OrderType, TimeInForce, PositionSide, OrderStatus, ContractType, MarginType, WorkingType, CancelReplaceMode, IncomeType);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KData {
     pub ts: u64,
     pub open: f64,
@@ -28,25 +181,147 @@ pub struct Ticker {
     pub ask_qty: f64,
 }
 
+// 24小时价格变动统计, 现货和合约的 ticker/24hr
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TickerStats {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub price_change: f64,
+    #[serde(with = "string_or_float")]
+    pub price_change_percent: f64,
+    #[serde(with = "string_or_float")]
+    pub weighted_avg_price: f64,
+    #[serde(with = "string_or_float")]
+    pub open_price: f64,
+    #[serde(with = "string_or_float")]
+    pub high_price: f64,
+    #[serde(with = "string_or_float")]
+    pub low_price: f64,
+    #[serde(with = "string_or_float")]
+    pub last_price: f64,
+    #[serde(with = "string_or_float")]
+    pub last_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub volume: f64,
+    #[serde(with = "string_or_float")]
+    pub quote_volume: f64,
+    // 以下只有现货有
+    #[serde(with = "opt_string_or_float", default)]
+    pub prev_close_price: Option<f64>,
+    #[serde(with = "opt_string_or_float", default)]
+    pub bid_price: Option<f64>,
+    #[serde(with = "opt_string_or_float", default)]
+    pub ask_price: Option<f64>,
+    pub open_time: u64,
+    pub close_time: u64,
+    // 统计期间没有成交时为 -1
+    pub first_id: i64,
+    pub last_id: i64,
+    pub count: u64,
+}
+
+// 滚动窗口价格变动统计, 现货 ticker?windowSize=, 窗口为 1m-59m, 1h-23h, 1d-7d
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollingTicker {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub price_change: f64,
+    #[serde(with = "string_or_float")]
+    pub price_change_percent: f64,
+    #[serde(with = "string_or_float")]
+    pub weighted_avg_price: f64,
+    #[serde(with = "string_or_float")]
+    pub open_price: f64,
+    #[serde(with = "string_or_float")]
+    pub high_price: f64,
+    #[serde(with = "string_or_float")]
+    pub low_price: f64,
+    #[serde(with = "string_or_float")]
+    pub last_price: f64,
+    #[serde(with = "string_or_float")]
+    pub volume: f64,
+    #[serde(with = "string_or_float")]
+    pub quote_volume: f64,
+    pub open_time: u64,
+    pub close_time: u64,
+    pub first_id: i64,
+    pub last_id: i64,
+    pub count: u64,
+}
+
+// 最新成交价
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceTicker {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub price: f64,
+    // 只有合约有
+    #[serde(default)]
+    pub time: Option<u64>,
+}
+
+// 现货最近 mins 分钟的成交均价
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvgPrice {
+    pub mins: u64,
+    #[serde(with = "string_or_float")]
+    pub price: f64,
+    #[serde(default)]
+    pub close_time: Option<u64>,
+}
+
+// 交易对所属的市场, 同名的订单类型在现货和合约中参数可能不同
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Market {
+    #[default]
+    Spot,
+    Futures,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SymbolInfo {
     pub symbol: String,
+    #[serde(default)]
+    pub market: Market,
     pub base: String,
     pub quote: String,
     pub price_precision: u8,
     pub quantity_precision: u8,
     pub base_precision: u8,
     pub quote_precision: u8,
+    #[serde(default)]
+    pub filters: SymbolFilters,
+}
+
+// 下单规则, 0表示不限制
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolFilters {
+    pub min_price: f64,
+    pub max_price: f64,
+    pub tick_size: f64,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    pub step_size: f64,
+    pub min_notional: f64,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+// 当前持仓量, U本位单位为币, 币本位单位为张
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 pub struct OpenInterest {
     pub symbol: String,
+    #[serde(rename = "openInterest", with = "string_or_float")]
     pub amount: f64,
+    #[serde(default)]
+    pub time: i64,
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Balance {
     pub asset: String,
@@ -66,7 +341,62 @@ pub struct OrderResp {
     pub transact_time: i64,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+// 各市场统一的订单信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+    pub symbol: String,
+    pub order_id: u64,
+    pub client_order_id: String,
+    pub price: f64,
+    pub orig_qty: f64,
+    pub executed_qty: f64,
+    pub status: OrderStatus,
+    #[serde(rename = "type")]
+    pub type_field: OrderType,
+    pub side: Side,
+    pub time_in_force: TimeInForce,
+    pub update_time: i64,
+}
+
+impl From<QueryOrderResult> for Order {
+    fn from(q: QueryOrderResult) -> Self {
+        Self {
+            symbol: q.symbol,
+            order_id: q.order_id as u64,
+            client_order_id: q.client_order_id,
+            price: q.price,
+            orig_qty: q.orig_qty,
+            executed_qty: q.executed_qty,
+            status: q.status,
+            type_field: q.type_field,
+            side: q.side,
+            time_in_force: q.time_in_force,
+            update_time: q.update_time,
+        }
+    }
+}
+
+impl From<CancelOrderResult> for Order {
+    fn from(c: CancelOrderResult) -> Self {
+        Self {
+            symbol: c.symbol,
+            order_id: c.order_id as u64,
+            // 撤单返回的clientOrderId是撤单请求的id, 原订单的id在origClientOrderId
+            client_order_id: c.orig_client_order_id,
+            price: c.price,
+            orig_qty: c.orig_qty,
+            executed_qty: c.executed_qty,
+            status: c.status,
+            type_field: c.type_field,
+            side: c.side,
+            time_in_force: c.time_in_force,
+            update_time: c.transact_time.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CancelOrderResult {
     pub symbol: String,
@@ -74,53 +404,583 @@ pub struct CancelOrderResult {
     pub order_id: i64,
     pub order_list_id: i64,
     pub client_order_id: String,
-    pub price: String,
-    pub orig_qty: String,
-    pub executed_qty: String,
-    pub cummulative_quote_qty: String,
-    pub status: String,
-    pub time_in_force: String,
+    #[serde(with = "string_or_float")]
+    pub price: f64,
+    #[serde(with = "string_or_float")]
+    pub orig_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub executed_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub cummulative_quote_qty: f64,
+    pub status: OrderStatus,
+    pub time_in_force: TimeInForce,
     #[serde(rename = "type")]
-    pub type_field: String,
-    pub side: String,
+    pub type_field: OrderType,
+    pub side: Side,
+    pub transact_time: Option<i64>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryOrderResult {
     pub symbol: String,
     pub order_id: i64,
     pub order_list_id: i64,
     pub client_order_id: String,
-    pub price: String,
-    pub orig_qty: String,
-    pub executed_qty: String,
-    pub cummulative_quote_qty: String,
-    pub status: String,
-    pub time_in_force: String,
+    #[serde(with = "string_or_float")]
+    pub price: f64,
+    #[serde(with = "string_or_float")]
+    pub orig_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub executed_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub cummulative_quote_qty: f64,
+    pub status: OrderStatus,
+    pub time_in_force: TimeInForce,
     #[serde(rename = "type")]
-    pub type_field: String,
-    pub side: String,
-    pub stop_price: String,
-    pub iceberg_qty: String,
+    pub type_field: OrderType,
+    pub side: Side,
+    #[serde(with = "string_or_float")]
+    pub stop_price: f64,
+    #[serde(with = "string_or_float")]
+    pub iceberg_qty: f64,
     pub time: i64,
     pub update_time: i64,
     pub is_working: bool,
-    pub orig_quote_order_qty: String,
+    #[serde(with = "string_or_float")]
+    pub orig_quote_order_qty: f64,
+}
+
+// 现货订单列表(OCO)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ContingencyType {
+    Oco,
+    Oto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ListStatusType {
+    Response,
+    ExecStarted,
+    AllDone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ListOrderStatus {
+    Executing,
+    AllDone,
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderList {
+    pub order_list_id: i64,
+    pub contingency_type: ContingencyType,
+    pub list_status_type: ListStatusType,
+    pub list_order_status: ListOrderStatus,
+    pub list_client_order_id: String,
+    pub transaction_time: i64,
+    pub symbol: String,
+    pub orders: Vec<OrderListOrder>,
+    // 查询接口不返回
+    #[serde(default)]
+    pub order_reports: Vec<OrderReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderListOrder {
+    pub symbol: String,
+    pub order_id: u64,
+    pub client_order_id: String,
+}
+
+// 下单/撤单时返回的订单列表中各订单的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderReport {
+    pub symbol: String,
+    pub order_id: u64,
+    pub order_list_id: i64,
+    pub client_order_id: String,
+    // 撤单时为原订单的 clientOrderId
+    #[serde(default)]
+    pub orig_client_order_id: Option<String>,
+    pub transact_time: i64,
+    #[serde(with = "string_or_float")]
+    pub price: f64,
+    #[serde(with = "string_or_float")]
+    pub orig_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub executed_qty: f64,
+    #[serde(with = "string_or_float")]
+    pub cummulative_quote_qty: f64,
+    pub status: OrderStatus,
+    pub time_in_force: TimeInForce,
+    #[serde(rename = "type")]
+    pub type_field: OrderType,
+    pub side: Side,
+    #[serde(default, with = "opt_string_or_float")]
+    pub stop_price: Option<f64>,
+}
+
+impl From<OrderReport> for Order {
+    fn from(r: OrderReport) -> Self {
+        Self {
+            symbol: r.symbol,
+            order_id: r.order_id,
+            client_order_id: r.orig_client_order_id.unwrap_or(r.client_order_id),
+            price: r.price,
+            orig_qty: r.orig_qty,
+            executed_qty: r.executed_qty,
+            status: r.status,
+            type_field: r.type_field,
+            side: r.side,
+            time_in_force: r.time_in_force,
+            update_time: r.transact_time,
+        }
+    }
+}
+
+// 撤单失败时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CancelReplaceMode {
+    // 撤单失败时不下新单
+    StopOnFailure,
+    // 撤单失败时仍然下新单
+    AllowFailure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CancelReplaceStatus {
+    Success,
+    Failure,
+    NotAttempted,
+}
+
+// 公开成交记录, 逐笔成交 trades 和归集成交 aggTrades 统一为同一格式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+    // 逐笔成交为成交id, 归集成交为归集id
+    pub id: u64,
+    pub price: f64,
+    pub qty: f64,
+    pub time: u64,
+    // 主动成交方(taker)的方向
+    pub side: Side,
+    // 归集成交包含的逐笔成交id范围, 逐笔成交时都等于 id
+    pub first_trade_id: u64,
+    pub last_trade_id: u64,
+}
+
+// 各市场统一的成交记录, 用于核对手续费和盈亏
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fill {
+    pub symbol: String,
+    // 成交id, 同一交易对内递增
+    pub id: u64,
+    pub order_id: u64,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub quote_qty: f64,
+    // 合约的手续费返还为负数
+    pub commission: f64,
+    pub commission_asset: String,
+    pub is_maker: bool,
+    pub time: u64,
+    // 以下只有合约有
+    pub realized_pnl: Option<f64>,
+    pub position_side: Option<PositionSide>,
+}
+
+// 手续费率, 现货 tradeFee 和合约 commissionRate 的字段名不同
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeFee {
+    pub symbol: String,
+    #[serde(rename = "makerCommissionRate", alias = "makerCommission", with = "string_or_float")]
+    pub maker: f64,
+    #[serde(rename = "takerCommissionRate", alias = "takerCommission", with = "string_or_float")]
+    pub taker: f64,
+}
+
+// 合约资金流水类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IncomeType {
+    Transfer,
+    WelcomeBonus,
+    RealizedPnl,
+    FundingFee,
+    Commission,
+    InsuranceClear,
+    ReferralKickback,
+    CommissionRebate,
+    ApiRebate,
+    ContestReward,
+    InternalTransfer,
+    AutoExchange,
+    #[serde(other)]
+    Other,
+}
+
+// U本位合约资金流水, /fapi/v1/income
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Income {
+    // 划转等与交易对无关的流水为空
+    pub symbol: String,
+    pub income_type: IncomeType,
+    #[serde(with = "string_or_float")]
+    pub income: f64,
+    pub asset: String,
+    pub info: String,
+    pub time: u64,
+    #[serde(with = "string_or_parse")]
+    pub tran_id: u64,
+    // 与成交相关的流水(手续费, 已实现盈亏)对应的成交id, 其它为空
+    pub trade_id: String,
+}
+
+// exchangeInfo 中的 rateLimits
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    pub rate_limit_type: String,
+    pub interval: String,
+    pub interval_num: i64,
+    pub limit: i64,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderBook {
     pub last_update_id: i64,
-    #[serde(rename = "E")]
+    // 现货没有 E/T 字段
+    #[serde(rename = "E", default)]
     pub event_time: i64,
-    #[serde(rename = "T")]
+    #[serde(rename = "T", default)]
     pub trade_order_time: i64,
     pub bids: Vec<Bids>,
     pub asks: Vec<Asks>,
 }
 
+// 标记价格与指数价格, 交割合约没有资金费率
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PremiumIndex {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub mark_price: f64,
+    #[serde(with = "string_or_float")]
+    pub index_price: f64,
+    #[serde(with = "string_or_float")]
+    pub estimated_settle_price: f64,
+    #[serde(with = "opt_string_or_float")]
+    pub last_funding_rate: Option<f64>,
+    #[serde(with = "opt_string_or_float")]
+    pub interest_rate: Option<f64>,
+    pub next_funding_time: i64,
+    pub time: i64,
+}
+
+// U本位合约持仓, /fapi/v2/positionRisk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionRisk {
+    pub symbol: String,
+    // 空头为负数
+    #[serde(with = "string_or_float")]
+    pub position_amt: f64,
+    #[serde(with = "string_or_float")]
+    pub entry_price: f64,
+    #[serde(with = "string_or_float")]
+    pub mark_price: f64,
+    #[serde(rename = "unRealizedProfit", with = "string_or_float")]
+    pub unrealized_profit: f64,
+    #[serde(with = "string_or_float")]
+    pub liquidation_price: f64,
+    #[serde(with = "string_or_parse")]
+    pub leverage: u32,
+    #[serde(with = "string_or_float")]
+    pub max_notional_value: f64,
+    pub margin_type: MarginType,
+    #[serde(with = "string_or_float")]
+    pub isolated_margin: f64,
+    #[serde(with = "string_or_parse")]
+    pub is_auto_add_margin: bool,
+    pub position_side: PositionSide,
+    #[serde(with = "string_or_float")]
+    pub notional: f64,
+    #[serde(with = "string_or_float")]
+    pub isolated_wallet: f64,
+    pub update_time: i64,
+}
+
+// U本位合约账户, /fapi/v2/account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuturesAccount {
+    pub fee_tier: u32,
+    pub can_trade: bool,
+    pub can_deposit: bool,
+    pub can_withdraw: bool,
+    pub update_time: i64,
+    // 联合保证金模式
+    #[serde(default)]
+    pub multi_assets_margin: bool,
+    #[serde(with = "string_or_float")]
+    pub total_initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub total_maint_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub total_wallet_balance: f64,
+    #[serde(with = "string_or_float")]
+    pub total_unrealized_profit: f64,
+    #[serde(with = "string_or_float")]
+    pub total_margin_balance: f64,
+    #[serde(with = "string_or_float")]
+    pub total_position_initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub total_open_order_initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub total_cross_wallet_balance: f64,
+    #[serde(rename = "totalCrossUnPnl", with = "string_or_float")]
+    pub total_cross_unrealized_pnl: f64,
+    #[serde(with = "string_or_float")]
+    pub available_balance: f64,
+    #[serde(with = "string_or_float")]
+    pub max_withdraw_amount: f64,
+    pub assets: Vec<FuturesAsset>,
+    pub positions: Vec<AccountPosition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuturesAsset {
+    pub asset: String,
+    #[serde(with = "string_or_float")]
+    pub wallet_balance: f64,
+    #[serde(with = "string_or_float")]
+    pub unrealized_profit: f64,
+    #[serde(with = "string_or_float")]
+    pub margin_balance: f64,
+    #[serde(with = "string_or_float")]
+    pub maint_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub position_initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub open_order_initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub cross_wallet_balance: f64,
+    #[serde(rename = "crossUnPnl", with = "string_or_float")]
+    pub cross_unrealized_pnl: f64,
+    #[serde(with = "string_or_float")]
+    pub available_balance: f64,
+    #[serde(with = "string_or_float")]
+    pub max_withdraw_amount: f64,
+    #[serde(default)]
+    pub margin_available: Option<bool>,
+    pub update_time: i64,
+}
+
+// 账户信息中的持仓, 不含标记价格和强平价, 需要时用 positionRisk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountPosition {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub maint_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub unrealized_profit: f64,
+    #[serde(with = "string_or_float")]
+    pub position_initial_margin: f64,
+    #[serde(with = "string_or_float")]
+    pub open_order_initial_margin: f64,
+    #[serde(with = "string_or_parse")]
+    pub leverage: u32,
+    pub isolated: bool,
+    #[serde(with = "string_or_float")]
+    pub entry_price: f64,
+    #[serde(with = "string_or_float")]
+    pub max_notional: f64,
+    pub position_side: PositionSide,
+    #[serde(with = "string_or_float")]
+    pub position_amt: f64,
+    pub update_time: i64,
+}
+
+// 合约余额, U本位 /fapi/v2/balance 与币本位 /dapi/v1/balance 共用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuturesBalance {
+    #[serde(default)]
+    pub account_alias: String,
+    pub asset: String,
+    #[serde(with = "string_or_float")]
+    pub balance: f64,
+    #[serde(with = "string_or_float")]
+    pub cross_wallet_balance: f64,
+    #[serde(rename = "crossUnPnl", with = "string_or_float")]
+    pub cross_unrealized_pnl: f64,
+    #[serde(with = "string_or_float")]
+    pub available_balance: f64,
+    // 币本位没有该字段
+    #[serde(with = "opt_string_or_float", default)]
+    pub max_withdraw_amount: Option<f64>,
+    #[serde(default)]
+    pub margin_available: Option<bool>,
+    pub update_time: i64,
+}
+
+// 合约没有冻结字段, 用 balance - availableBalance 近似为占用的保证金
+impl From<FuturesBalance> for Balance {
+    fn from(raw: FuturesBalance) -> Self {
+        Balance {
+            asset: raw.asset,
+            free: raw.available_balance,
+            locked: raw.balance - raw.available_balance,
+        }
+    }
+}
+
+// 调整杠杆的返回
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeverageInfo {
+    pub symbol: String,
+    pub leverage: u32,
+    // 当前杠杆下的最大名义价值
+    #[serde(with = "string_or_float")]
+    pub max_notional_value: f64,
+}
+
+// 调整逐仓保证金的方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarginChange {
+    Add,
+    Reduce,
+}
+
+impl MarginChange {
+    // 接口参数 type: 1 增加, 2 减少
+    pub fn code(&self) -> u8 {
+        match self {
+            MarginChange::Add => 1,
+            MarginChange::Reduce => 2,
+        }
+    }
+}
+
+// 杠杆分层, 名义价值越大可用的最大杠杆越低
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeverageBracket {
+    pub symbol: String,
+    // 用户分层相对默认分层的倍数, 只有自定义分层的账户才有
+    #[serde(default)]
+    pub notional_coef: Option<f64>,
+    pub brackets: Vec<Bracket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bracket {
+    pub bracket: u32,
+    pub initial_leverage: u32,
+    pub notional_cap: f64,
+    pub notional_floor: f64,
+    pub maint_margin_ratio: f64,
+    // 速算数
+    pub cum: f64,
+}
+
+// 历史资金费率, /fapi/v1/fundingRate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRate {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub funding_rate: f64,
+    pub funding_time: u64,
+    // 较早的记录为空字符串
+    #[serde(with = "opt_string_or_float", default)]
+    pub mark_price: Option<f64>,
+}
+
+// 以下为 /futures/data 统计数据, 按 period(5m 到 1d) 聚合, 只保留最近30天
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenInterestHist {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub sum_open_interest: f64,
+    // 持仓价值, 单位为报价币种
+    #[serde(with = "string_or_float")]
+    pub sum_open_interest_value: f64,
+    pub timestamp: u64,
+}
+
+// 多空比, long_account/short_account 为多/空方占比
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LongShortRatio {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub long_short_ratio: f64,
+    #[serde(with = "string_or_float")]
+    pub long_account: f64,
+    #[serde(with = "string_or_float")]
+    pub short_account: f64,
+    pub timestamp: u64,
+}
+
+// 主动买入/卖出量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakerVolume {
+    #[serde(with = "string_or_float")]
+    pub buy_sell_ratio: f64,
+    #[serde(with = "string_or_float")]
+    pub buy_vol: f64,
+    #[serde(with = "string_or_float")]
+    pub sell_vol: f64,
+    pub timestamp: u64,
+}
+
+// 基差 = 合约价格 - 指数价格
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Basis {
+    pub pair: String,
+    pub contract_type: ContractType,
+    #[serde(with = "string_or_float")]
+    pub futures_price: f64,
+    #[serde(with = "string_or_float")]
+    pub index_price: f64,
+    #[serde(with = "string_or_float")]
+    pub basis: f64,
+    #[serde(with = "string_or_float")]
+    pub basis_rate: f64,
+    // 永续合约为空
+    #[serde(with = "opt_string_or_float", default)]
+    pub annualized_basis_rate: Option<f64>,
+    pub timestamp: u64,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Bids {
@@ -167,4 +1027,126 @@ mod string_or_float {
             StringOrFloat::Float(i) => Ok(i),
         }
     }
-}
\ No newline at end of file
+}
+// 空字符串表示没有该字段, 例如交割合约的 lastFundingRate
+pub(crate) mod opt_string_or_float {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        match value {
+            Some(v) => serializer.collect_str(v),
+            None => serializer.serialize_str(""),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+        where D: Deserializer<'de>
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StringOrFloat {
+            String(String),
+            Float(f64),
+        }
+
+        match Option::<StringOrFloat>::deserialize(deserializer)? {
+            Some(StringOrFloat::String(s)) if s.is_empty() => Ok(None),
+            Some(StringOrFloat::String(s)) => s.parse().map(Some).map_err(de::Error::custom),
+            Some(StringOrFloat::Float(f)) => Ok(Some(f)),
+            None => Ok(None),
+        }
+    }
+}
+
+// 字符串或原生值, 例如 leverage 为 "20", isAutoAddMargin 为 "false"
+pub(crate) mod string_or_parse {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::fmt;
+    use std::str::FromStr;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: fmt::Display,
+              S: Serializer
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where T: FromStr + Deserialize<'de>,
+              T::Err: fmt::Display,
+              D: Deserializer<'de>
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StringOrValue<T> {
+            String(String),
+            Value(T),
+        }
+
+        match StringOrValue::<T>::deserialize(deserializer)? {
+            StringOrValue::String(s) => s.parse().map_err(de::Error::custom),
+            StringOrValue::Value(v) => Ok(v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enum_params() {
+        assert_eq!(Side::Buy.to_string(), "BUY");
+        assert_eq!(OrderType::TrailingStopMarket.to_string(), "TRAILING_STOP_MARKET");
+        assert_eq!(OrderType::LimitMaker.to_string(), "LIMIT_MAKER");
+        assert_eq!(TimeInForce::Gtx.to_string(), "GTX");
+        assert_eq!(TimeInForce::GteGtc.to_string(), "GTE_GTC");
+        assert_eq!(PositionSide::Long.to_string(), "LONG");
+        assert_eq!(OrderStatus::PartiallyFilled.to_string(), "PARTIALLY_FILLED");
+    }
+
+    #[test]
+    fn test_enum_serde() {
+        let status: OrderStatus = serde_json::from_str(r#""EXPIRED_IN_MATCH""#).unwrap();
+        assert_eq!(status, OrderStatus::ExpiredInMatch);
+        assert!(status.is_final());
+        assert!(!OrderStatus::New.is_final());
+        assert!(!OrderStatus::PartiallyFilled.is_final());
+        let status: OrderStatus = serde_json::from_str(r#""PENDING_NEW""#).unwrap();
+        assert_eq!(status, OrderStatus::PendingNew);
+        assert!(!status.is_final());
+        let status: OrderStatus = serde_json::from_str(r#""SOME_NEW_STATUS""#).unwrap();
+        assert_eq!(status, OrderStatus::Unknown);
+        let tif: TimeInForce = serde_json::from_str(r#""GTD""#).unwrap();
+        assert_eq!(tif, TimeInForce::Gtd);
+        assert_eq!(serde_json::from_str::<TimeInForce>(r#""RPI""#).unwrap(), TimeInForce::Unknown);
+        let type_: OrderType = serde_json::from_str(r#""STOP_MARKET""#).unwrap();
+        assert_eq!(type_, OrderType::StopMarket);
+        assert!(serde_json::from_str::<Side>(r#""BUYY""#).is_err());
+        let contract: ContractType = serde_json::from_str(r#""CURRENT_QUARTER_DELIVERING""#).unwrap();
+        assert_eq!(contract, ContractType::CurrentQuarterDelivering);
+    }
+
+    const QUERY_ORDER_JSON: &str = r#"{"symbol":"LTCBTC","orderId":1,"orderListId":-1,"clientOrderId":"myOrder1","price":"0.1","origQty":"1.0","executedQty":"0.0","cummulativeQuoteQty":"0.0","status":"NEW","timeInForce":"GTC","type":"LIMIT","side":"BUY","stopPrice":"0.0","icebergQty":"0.0","time":1499827319559,"updateTime":1499827319559,"isWorking":true,"origQuoteOrderQty":"0.000000"}"#;
+
+    #[test]
+    fn test_order_numbers_are_parsed() {
+        let q: QueryOrderResult = serde_json::from_str(QUERY_ORDER_JSON).unwrap();
+        let order = Order::from(q);
+        assert_eq!((order.price, order.orig_qty, order.executed_qty), (0.1, 1.0, 0.0));
+        // 无法解析的数值返回错误, 而不是当作0
+        let bad = QUERY_ORDER_JSON.replace(r#""price":"0.1""#, r#""price":"abc""#);
+        assert!(serde_json::from_str::<QueryOrderResult>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_premium_index_without_funding() {
+        let pi: PremiumIndex = serde_json::from_str(r#"{"symbol":"BTCUSD_200925","pair":"BTCUSD","markPrice":"9271.37","indexPrice":"9270.94","estimatedSettlePrice":"9268.60","lastFundingRate":"","interestRate":"","nextFundingTime":0,"time":1591702613943}"#).unwrap();
+        assert_eq!(pi.last_funding_rate, None);
+        assert_eq!(pi.mark_price, 9271.37);
+        let pi: PremiumIndex = serde_json::from_str(r#"{"symbol":"BTCUSD_PERP","pair":"BTCUSD","markPrice":"9271.37","indexPrice":"9270.94","estimatedSettlePrice":"9268.60","lastFundingRate":"0.00010000","interestRate":"0.00010000","nextFundingTime":1596096000000,"time":1591702613943}"#).unwrap();
+        assert_eq!(pi.last_funding_rate, Some(0.0001));
+    }
+}
@@ -0,0 +1,475 @@
+use crate::error::{ApiErrorCode, EdpError, Result};
+use crate::model::{Order, OrderStatus};
+use crate::order::{format_decimal, Bracket, OrderRequest};
+use crate::traits::TradingAPI;
+use crate::ws::user::UserEvent;
+use crate::ws::wclient::StreamEvent;
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::time::Duration;
+
+// 下单/撤单遇到网络错误等临时错误时的重试次数和间隔
+const MAX_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+// 用两个独立订单模拟 OCO, 例如合约的止损单和止盈单, 按 client_order_id 识别
+// 任意一个结束(成交/撤销/过期/拒绝)后撤销另一个
+// 部分成交时另一个订单按剩余仓位(原数量减去两边的累计成交)撤单后用新的 client_order_id 重新下单
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcoPair {
+    pub legs: [OrderRequest; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcoOutcome {
+    // 先结束的订单
+    pub done: Order,
+    // 被撤销的另一个订单, 已经先一步结束时为其最终状态
+    pub sibling: Order,
+}
+
+impl OcoPair {
+    // 两个订单需要是同一个交易对, 并且都设置了 client_order_id
+    pub fn new(first: OrderRequest, second: OrderRequest) -> Result<Self> {
+        if first.symbol != second.symbol {
+            return Err(EdpError::InvalidOrder(format!(
+                "oco legs on different symbols: {} and {}",
+                first.symbol, second.symbol
+            )));
+        }
+        if first.new_client_order_id.is_none() || second.new_client_order_id.is_none() {
+            return Err(EdpError::InvalidOrder("oco legs need client order ids".to_string()));
+        }
+        Ok(Self { legs: [first, second] })
+    }
+
+    // 止损单和止盈单
+    pub fn from_bracket(bracket: &Bracket) -> Result<Self> {
+        Self::new(bracket.stop_loss.clone(), bracket.take_profit.clone())
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.legs[0].symbol
+    }
+
+    // 每 interval 查询一次两个订单的状态, 临时错误跳过, 下一轮再查
+    pub async fn watch_polling<T>(&self, api: &T, interval: Duration) -> Result<OcoOutcome>
+    where
+        T: TradingAPI + ?Sized,
+    {
+        let mut watch = Watch::new(self, api);
+        loop {
+            match watch.poll().await {
+                Ok(Some(outcome)) => return Ok(outcome),
+                Ok(None) => {}
+                Err(err) if err.is_transient() => {
+                    log::warn!("query error while watching oco {:?}: {}", watch.ids, err);
+                }
+                Err(err) => return Err(err),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    // 根据用户数据流的订单推送判断, 重连后查询一次补上断线期间可能丢失的推送
+    pub async fn watch_stream<T, S>(&self, api: &T, events: &mut S) -> Result<OcoOutcome>
+    where
+        T: TradingAPI + ?Sized,
+        S: Stream<Item = Result<StreamEvent<UserEvent>>> + Unpin,
+    {
+        let mut watch = Watch::new(self, api);
+        // 开始监听之前可能已经成交, 临时错误时等推送或者重连后的查询
+        match watch.poll().await {
+            Ok(Some(outcome)) => return Ok(outcome),
+            Ok(None) => {}
+            Err(err) if err.is_transient() => {
+                log::warn!("query error while watching oco {:?}: {}", watch.ids, err);
+            }
+            Err(err) => return Err(err),
+        }
+        while let Some(event) = events.next().await {
+            let outcome = match event {
+                Ok(StreamEvent::Data(UserEvent::OrderTradeUpdate(e))) if e.order.symbol == self.symbol() => {
+                    watch.on_event(&e.order.client_order_id, e.order.status, e.order.cum_filled_qty).await
+                }
+                // 现货撤单推送的 c 为撤单请求的id, 原订单id在 C
+                Ok(StreamEvent::Data(UserEvent::ExecutionReport(e))) if e.symbol == self.symbol() => {
+                    let id = if e.orig_client_order_id.is_empty() { &e.client_order_id } else { &e.orig_client_order_id };
+                    watch.on_event(id, e.status, e.cum_filled_qty).await
+                }
+                Ok(StreamEvent::Resubscribed(_)) => watch.poll().await,
+                Err(err) => {
+                    log::warn!("user stream error while watching oco {:?}: {}", watch.ids, err);
+                    watch.poll().await
+                }
+                _ => Ok(None),
+            };
+            match outcome {
+                Ok(Some(outcome)) => return Ok(outcome),
+                Ok(None) => {}
+                // 等下一个推送或者重连后的查询
+                Err(err) if err.is_transient() => {
+                    log::warn!("query error while watching oco {:?}: {}", watch.ids, err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(EdpError::StreamClosed)
+    }
+}
+
+// 监听过程中的状态: 订单重新下单后 client_order_id 会变化
+struct Watch<'a, T: ?Sized> {
+    pair: &'a OcoPair,
+    api: &'a T,
+    ids: [String; 2],
+    // 当前订单已经处理过的成交数量, 重新下单后从0开始
+    executed: [f64; 2],
+    // 被替换掉的订单的成交数量之和
+    prior: [f64; 2],
+    replaced: [u32; 2],
+    // 已撤单但还没有重新下单成功的订单, 下次查询前先补上
+    unplaced: Option<OrderRequest>,
+}
+
+impl<'a, T: TradingAPI + ?Sized> Watch<'a, T> {
+    fn new(pair: &'a OcoPair, api: &'a T) -> Self {
+        let ids = [0, 1].map(|i| pair.legs[i].new_client_order_id.clone().unwrap_or_default());
+        Self {
+            pair,
+            api,
+            ids,
+            executed: [0.; 2],
+            prior: [0.; 2],
+            replaced: [0; 2],
+            unplaced: None,
+        }
+    }
+
+    async fn poll(&mut self) -> Result<Option<OcoOutcome>> {
+        self.place_unplaced().await?;
+        for i in 0..2 {
+            let order = self.query(i).await?;
+            let (status, executed) = (order.status, order.executed_qty);
+            if let Some(outcome) = self.on_update(i, status, executed, Some(order)).await? {
+                return Ok(Some(outcome));
+            }
+        }
+        Ok(None)
+    }
+
+    // 推送中的id不是当前的两个订单时忽略, 包括被重新下单替换掉的旧订单
+    async fn on_event(&mut self, client_order_id: &str, status: OrderStatus, executed: f64) -> Result<Option<OcoOutcome>> {
+        self.place_unplaced().await?;
+        match self.ids.iter().position(|id| id == client_order_id) {
+            Some(i) => self.on_update(i, status, executed, None).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn on_update(&mut self, i: usize, status: OrderStatus, executed: f64, done: Option<Order>) -> Result<Option<OcoOutcome>> {
+        if status.is_final() {
+            return self.finish(i, done).await.map(Some);
+        }
+        if executed > self.executed[i] {
+            self.executed[i] = executed;
+            return self.shrink(i).await;
+        }
+        Ok(None)
+    }
+
+    // 第i个订单部分成交, 撤掉另一个订单后按剩余仓位重新下单
+    async fn shrink(&mut self, i: usize) -> Result<Option<OcoOutcome>> {
+        let j = 1 - i;
+        let leg = &self.pair.legs[j];
+        // 触发后全部平仓的订单不需要调整数量
+        if leg.close_position {
+            return Ok(None);
+        }
+        let quantity: f64 = leg
+            .quantity
+            .parse()
+            .map_err(|_| EdpError::InvalidOrder(format!("bad quantity {}", leg.quantity)))?;
+        // 剩余数量为0时第i个订单马上会变为 FILLED
+        if quantity - self.total_filled(i) - self.total_filled(j) <= 0. {
+            return Ok(None);
+        }
+        match retry(|| self.api.cancel_order(self.pair.symbol(), None, Some(&self.ids[j]))).await {
+            // 撤单返回的成交数量可能比已经处理过的更新
+            Ok(order) => self.executed[j] = self.executed[j].max(order.executed_qty),
+            // 另一个订单已经先结束了
+            Err(err) if err.api_code() == Some(ApiErrorCode::CancelRejected) => {
+                let order = self.query(j).await?;
+                return self.finish(j, Some(order)).await.map(Some);
+            }
+            Err(err) => return Err(err),
+        }
+        let remaining = quantity - self.total_filled(i) - self.total_filled(j);
+        if remaining <= 0. {
+            return Ok(None);
+        }
+        let base = leg.new_client_order_id.clone().unwrap_or_default();
+        let id = format!("{}-{}", base, self.replaced[j] + 1);
+        let mut req = leg.clone();
+        req.quantity = format_decimal(remaining);
+        req.new_client_order_id = Some(id.clone());
+        log::info!("oco leg {} partially filled {}, replacing {} with {} qty {}", self.ids[i], self.executed[i], self.ids[j], id, req.quantity);
+        self.ids[j] = id;
+        self.replaced[j] += 1;
+        self.prior[j] += self.executed[j];
+        self.executed[j] = 0.;
+        self.unplaced = Some(req);
+        self.place_unplaced().await?;
+        Ok(None)
+    }
+
+    fn total_filled(&self, i: usize) -> f64 {
+        self.prior[i] + self.executed[i]
+    }
+
+    async fn place_unplaced(&mut self) -> Result<()> {
+        if let Some(req) = &self.unplaced {
+            retry(|| self.api.place_order(req)).await?;
+            self.unplaced = None;
+        }
+        Ok(())
+    }
+
+    // 先撤另一个订单, 再查询结束的订单
+    async fn finish(&self, i: usize, done: Option<Order>) -> Result<OcoOutcome> {
+        let other = &self.ids[1 - i];
+        let sibling = match retry(|| self.api.cancel_order(self.pair.symbol(), None, Some(other))).await {
+            Ok(order) => order,
+            // 另一个订单也已经结束
+            Err(err) if err.api_code() == Some(ApiErrorCode::CancelRejected) => self.query(1 - i).await?,
+            Err(err) => return Err(err),
+        };
+        let done = match done {
+            Some(order) => order,
+            None => self.query(i).await?,
+        };
+        Ok(OcoOutcome { done, sibling })
+    }
+
+    async fn query(&self, i: usize) -> Result<Order> {
+        retry(|| self.api.query_order(self.pair.symbol(), None, Some(&self.ids[i]))).await
+    }
+}
+
+// 临时错误间隔一段时间后重试, 其他错误直接返回
+async fn retry<F, Fut, R>(mut f: F) -> Result<R>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Err(err) if err.is_transient() && attempt < MAX_RETRIES => {
+                attempt += 1;
+                log::warn!("transient error, retry {}/{}: {}", attempt, MAX_RETRIES, err);
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::perpetual::BinancePerpetual;
+    use crate::model::{OrderType, Side};
+    use crate::rest::stub::{StubRequest, StubResponse, StubServer};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    fn order_json(client_order_id: &str, status: &str, executed: &str) -> String {
+        format!(
+            r#"{{"clientOrderId":"{}","cumQty":"{}","cumQuote":"0","executedQty":"{}","orderId":1,"avgPrice":"0","origQty":"0.01","price":"0","reduceOnly":true,"side":"SELL","positionSide":"BOTH","status":"{}","stopPrice":"9000","closePosition":false,"symbol":"BTCUSDT","timeInForce":"GTC","type":"STOP_MARKET","origType":"STOP_MARKET","updateTime":1566818724722,"workingType":"MARK_PRICE"}}"#,
+            client_order_id, executed, executed, status
+        )
+    }
+
+    fn update(symbol: &str, client_order_id: &str, status: &str, executed: &str) -> Result<StreamEvent<UserEvent>> {
+        let json = format!(
+            r#"{{"e":"ORDER_TRADE_UPDATE","E":1,"T":1,"o":{{"s":"{}","c":"{}","S":"SELL","o":"STOP_MARKET","f":"GTC","q":"0.01","p":"0","ap":"0","sp":"9000","x":"TRADE","X":"{}","i":1,"l":"0","z":"{}","L":"0","T":1,"t":0,"m":false,"R":true,"ps":"BOTH","rp":"0"}}}}"#,
+            symbol, client_order_id, status, executed
+        );
+        Ok(StreamEvent::Data(serde_json::from_str(&json).unwrap()))
+    }
+
+    fn client(server: &StubServer) -> BinancePerpetual {
+        BinancePerpetual::with_key(server.url(), "wss://127.0.0.1:1".to_string(), ("key".to_string(), "secret".to_string()))
+    }
+
+    fn leg(id: &str, type_: OrderType, stop_price: f64) -> OrderRequest {
+        OrderRequest::builder("BTCUSDT", Side::Sell, type_)
+            .quantity(0.01)
+            .stop_price(stop_price)
+            .reduce_only(true)
+            .client_order_id(id)
+            .build_unchecked()
+    }
+
+    fn pair() -> OcoPair {
+        OcoPair::new(leg("tp", OrderType::TakeProfitMarket, 11000.), leg("sl", OrderType::StopMarket, 9000.)).unwrap()
+    }
+
+    // sl 的查询依次返回 script 中的 (状态, 已成交数量), 取完后一直返回最后一个
+    // 状态为数字时返回该http状态码; 其他订单撤单后为 CANCELED, cancel 不为空时撤单返回 cancel 并把订单置为 EXPIRED
+    fn serve(script: Vec<(&'static str, &'static str)>, cancel: Option<StubResponse>) -> impl Fn(&StubRequest) -> StubResponse {
+        let gets = Arc::new(AtomicUsize::new(0));
+        let orders = Arc::new(Mutex::new(HashMap::<String, &'static str>::new()));
+        move |req| {
+            let mut orders = orders.lock().unwrap();
+            if req.method == "POST" {
+                let id = req.param("newClientOrderId").unwrap();
+                orders.insert(id.clone(), "NEW");
+                return StubResponse::ok(&order_json(&id, "NEW", "0"));
+            }
+            let id = req.param("origClientOrderId").unwrap();
+            if req.method == "DELETE" {
+                return match &cancel {
+                    Some(resp) => {
+                        orders.insert(id, "EXPIRED");
+                        resp.clone()
+                    }
+                    None => {
+                        orders.insert(id.clone(), "CANCELED");
+                        StubResponse::ok(&order_json(&id, "CANCELED", "0"))
+                    }
+                };
+            }
+            if id != "sl" {
+                return StubResponse::ok(&order_json(&id, orders.get(&id).unwrap_or(&"NEW"), "0"));
+            }
+            let (status, executed) = script[gets.fetch_add(1, Ordering::SeqCst).min(script.len() - 1)];
+            match status.parse() {
+                Ok(code) => StubResponse::with_status(code, executed),
+                Err(_) => StubResponse::ok(&order_json(&id, status, executed)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let mut tp = leg("tp", OrderType::TakeProfitMarket, 11000.);
+        assert!(OcoPair::new(tp.clone(), leg("sl", OrderType::StopMarket, 9000.)).is_ok());
+        tp.new_client_order_id = None;
+        assert!(OcoPair::new(tp, leg("sl", OrderType::StopMarket, 9000.)).is_err());
+        let mut eth = leg("sl", OrderType::StopMarket, 9000.);
+        eth.symbol = "ETHUSDT".to_string();
+        assert!(OcoPair::new(leg("tp", OrderType::TakeProfitMarket, 11000.), eth).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_polling() {
+        // 查询遇到503时重试, 不结束监听
+        let script = vec![("NEW", "0"), ("503", "Service Unavailable"), ("FILLED", "0.01")];
+        let server = StubServer::start(serve(script, None)).await;
+        let outcome = pair().watch_polling(&client(&server), Duration::from_millis(10)).await.unwrap();
+        assert_eq!((outcome.done.client_order_id.as_str(), outcome.done.status), ("sl", OrderStatus::Filled));
+        assert_eq!((outcome.sibling.client_order_id.as_str(), outcome.sibling.status), ("tp", OrderStatus::Canceled));
+
+        let reqs = server.requests();
+        let cancels: Vec<_> = reqs.iter().filter(|r| r.method == "DELETE").collect();
+        assert_eq!(cancels.len(), 1);
+        assert_eq!(cancels[0].param("origClientOrderId").as_deref(), Some("tp"));
+
+        // 其他错误直接返回
+        let script = vec![("400", r#"{"code":-2013,"msg":"Order does not exist."}"#)];
+        let server = StubServer::start(serve(script, None)).await;
+        let err = pair().watch_polling(&client(&server), Duration::from_millis(10)).await.unwrap_err();
+        assert_eq!(err.api_code(), Some(ApiErrorCode::NoSuchOrder));
+    }
+
+    #[tokio::test]
+    async fn test_partial_fill_shrinks_sibling() {
+        let script = vec![("PARTIALLY_FILLED", "0.004"), ("PARTIALLY_FILLED", "0.004"), ("FILLED", "0.01")];
+        let server = StubServer::start(serve(script, None)).await;
+        let outcome = pair().watch_polling(&client(&server), Duration::from_millis(10)).await.unwrap();
+        assert_eq!(outcome.done.status, OrderStatus::Filled);
+        assert_eq!((outcome.sibling.client_order_id.as_str(), outcome.sibling.status), ("tp-1", OrderStatus::Canceled));
+
+        let reqs = server.requests();
+        let writes: Vec<_> = reqs.iter().filter(|r| r.method != "GET").collect();
+        assert_eq!(writes.len(), 3);
+        assert_eq!((writes[0].method.as_str(), writes[0].param("origClientOrderId").as_deref()), ("DELETE", Some("tp")));
+        // 同样的止盈单, 数量减去已成交的部分, 只重新下单一次
+        assert_eq!(writes[1].method, "POST");
+        assert_eq!(writes[1].param("newClientOrderId").as_deref(), Some("tp-1"));
+        assert_eq!(writes[1].param("quantity").as_deref(), Some("0.006"));
+        assert_eq!(writes[1].param("type").as_deref(), Some("TAKE_PROFIT_MARKET"));
+        assert_eq!(writes[1].param("reduceOnly").as_deref(), Some("true"));
+        assert_eq!((writes[2].method.as_str(), writes[2].param("origClientOrderId").as_deref()), ("DELETE", Some("tp-1")));
+
+        // 推送中被替换掉的旧订单的撤单消息需要忽略
+        let server = StubServer::start(serve(vec![("NEW", "0"), ("FILLED", "0.01")], None)).await;
+        let mut events = futures::stream::iter(vec![
+            update("BTCUSDT", "sl", "PARTIALLY_FILLED", "0.004"),
+            update("BTCUSDT", "tp", "CANCELED", "0"),
+            update("BTCUSDT", "sl", "FILLED", "0.01"),
+        ]);
+        let outcome = pair().watch_stream(&client(&server), &mut events).await.unwrap();
+        assert_eq!((outcome.sibling.client_order_id.as_str(), outcome.sibling.status), ("tp-1", OrderStatus::Canceled));
+    }
+
+    #[tokio::test]
+    async fn test_both_legs_partially_filled() {
+        let server = StubServer::start(serve(vec![("NEW", "0")], None)).await;
+        let mut events = futures::stream::iter(vec![
+            update("BTCUSDT", "sl", "PARTIALLY_FILLED", "0.003"),
+            update("BTCUSDT", "tp-1", "PARTIALLY_FILLED", "0.002"),
+            // 新订单的成交数量从0开始计算
+            update("BTCUSDT", "sl-1", "PARTIALLY_FILLED", "0.001"),
+            update("BTCUSDT", "tp-2", "FILLED", "0.004"),
+        ]);
+        let outcome = pair().watch_stream(&client(&server), &mut events).await.unwrap();
+        assert_eq!(outcome.sibling.client_order_id, "sl-1");
+
+        // 每次重新下单的数量为原数量减去两边的累计成交
+        let reqs = server.requests();
+        let placed: Vec<(Option<String>, Option<String>)> = reqs
+            .iter()
+            .filter(|r| r.method == "POST")
+            .map(|r| (r.param("newClientOrderId"), r.param("quantity")))
+            .collect();
+        let expected = [("tp-1", "0.007"), ("sl-1", "0.005"), ("tp-2", "0.004")];
+        let expected: Vec<_> = expected.iter().map(|(id, q)| (Some(id.to_string()), Some(q.to_string()))).collect();
+        assert_eq!(placed, expected);
+        let cancels: Vec<Option<String>> = reqs.iter().filter(|r| r.method == "DELETE").map(|r| r.param("origClientOrderId")).collect();
+        assert_eq!(cancels, vec![Some("tp".to_string()), Some("sl".to_string()), Some("tp-1".to_string()), Some("sl-1".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_watch_stream() {
+        let rejected = StubResponse::with_status(400, r#"{"code":-2011,"msg":"Unknown order sent."}"#);
+        let server = StubServer::start(serve(vec![("NEW", "0"), ("FILLED", "0.01")], Some(rejected))).await;
+        let oco = pair();
+        let mut events = futures::stream::iter(vec![
+            Ok(StreamEvent::Connected),
+            update("ETHUSDT", "sl", "FILLED", "0.01"),
+            update("BTCUSDT", "tp", "NEW", "0"),
+            update("BTCUSDT", "sl", "FILLED", "0.01"),
+        ]);
+        let outcome = oco.watch_stream(&client(&server), &mut events).await.unwrap();
+        assert_eq!(outcome.done.status, OrderStatus::Filled);
+        // 撤单被拒绝, 另一个订单已经先结束
+        assert_eq!((outcome.sibling.client_order_id.as_str(), outcome.sibling.status), ("tp", OrderStatus::Expired));
+        let methods: Vec<String> = server.requests().into_iter().map(|r| r.method).collect();
+        assert_eq!(methods, vec!["GET", "GET", "DELETE", "GET", "GET"]);
+
+        // 重连后查询补上丢失的推送
+        let server = StubServer::start(serve(vec![("NEW", "0"), ("FILLED", "0.01")], None)).await;
+        let mut events = futures::stream::iter(vec![Ok(StreamEvent::Resubscribed(vec![]))]);
+        let outcome = oco.watch_stream(&client(&server), &mut events).await.unwrap();
+        assert_eq!(outcome.sibling.status, OrderStatus::Canceled);
+
+        let server = StubServer::start(serve(vec![("NEW", "0")], None)).await;
+        let mut events = futures::stream::iter(vec![update("BTCUSDT", "tp", "NEW", "0")]);
+        let err = oco.watch_stream(&client(&server), &mut events).await.unwrap_err();
+        assert!(matches!(err, EdpError::StreamClosed));
+    }
+}
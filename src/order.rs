@@ -0,0 +1,986 @@
+use crate::error::{EdpError, Result};
+use crate::model::{Market, OrderType, PositionSide, Side, SymbolFilters, SymbolInfo, TimeInForce, WorkingType};
+use std::collections::BTreeMap;
+
+// 已按交易对规则取整并校验过的下单请求
+// 价格和数量保存为字符串, 避免 1.1000000000000001 这类浮点误差
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub symbol: String,
+    pub side: Side,
+    pub type_: OrderType,
+    // close_position 为 true 时不发送
+    pub quantity: String,
+    pub price: Option<String>,
+    pub time_in_force: Option<TimeInForce>,
+    pub new_client_order_id: Option<String>,
+    // 以下为条件单参数, 除 stop_price 外只用于合约
+    pub stop_price: Option<String>,
+    pub working_type: Option<WorkingType>,
+    pub price_protect: bool,
+    pub reduce_only: bool,
+    // 触发后平掉该方向的全部仓位, 只用于 STOP_MARKET/TAKE_PROFIT_MARKET
+    pub close_position: bool,
+    pub activation_price: Option<String>,
+    // 跟踪止损的回调比例, 单位为百分比, 1 表示 1%
+    pub callback_rate: Option<String>,
+    // 双向持仓模式下必须指定
+    pub position_side: Option<PositionSide>,
+}
+
+#[derive(Clone)]
+pub struct OrderRequestBuilder {
+    symbol: String,
+    side: Side,
+    type_: OrderType,
+    quantity: f64,
+    price: Option<f64>,
+    time_in_force: Option<TimeInForce>,
+    new_client_order_id: Option<String>,
+    stop_price: Option<f64>,
+    working_type: Option<WorkingType>,
+    price_protect: bool,
+    reduce_only: bool,
+    close_position: bool,
+    activation_price: Option<f64>,
+    callback_rate: Option<f64>,
+    position_side: Option<PositionSide>,
+}
+
+impl OrderRequest {
+    pub fn builder(symbol: &str, side: Side, type_: OrderType) -> OrderRequestBuilder {
+        OrderRequestBuilder {
+            symbol: symbol.to_string(),
+            side,
+            type_,
+            quantity: 0.,
+            price: None,
+            time_in_force: None,
+            new_client_order_id: None,
+            stop_price: None,
+            working_type: None,
+            price_protect: false,
+            reduce_only: false,
+            close_position: false,
+            activation_price: None,
+            callback_rate: None,
+            position_side: None,
+        }
+    }
+
+    // 价格达到 stop_price 后以市价止损
+    pub fn stop_market(symbol: &str, side: Side, stop_price: f64) -> OrderRequestBuilder {
+        let mut builder = Self::builder(symbol, side, OrderType::StopMarket);
+        builder.stop_price(stop_price);
+        builder
+    }
+
+    pub fn take_profit_market(symbol: &str, side: Side, stop_price: f64) -> OrderRequestBuilder {
+        let mut builder = Self::builder(symbol, side, OrderType::TakeProfitMarket);
+        builder.stop_price(stop_price);
+        builder
+    }
+
+    // 跟踪止损, 从最高(卖出)/最低(买入)价回调 callback_rate% 后以市价成交
+    pub fn trailing_stop(symbol: &str, side: Side, callback_rate: f64) -> OrderRequestBuilder {
+        let mut builder = Self::builder(symbol, side, OrderType::TrailingStopMarket);
+        builder.callback_rate(callback_rate);
+        builder
+    }
+
+    pub fn to_params(&self) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), self.symbol.clone());
+        params.insert("side".to_string(), self.side.to_string());
+        params.insert("type".to_string(), self.type_.to_string());
+        if self.close_position {
+            params.insert("closePosition".to_string(), "true".to_string());
+        } else {
+            params.insert("quantity".to_string(), self.quantity.clone());
+        }
+        if let Some(ref price) = self.price {
+            params.insert("price".to_string(), price.clone());
+        }
+        if let Some(tif) = self.time_in_force {
+            params.insert("timeInForce".to_string(), tif.to_string());
+        }
+        if let Some(ref id) = self.new_client_order_id {
+            params.insert("newClientOrderId".to_string(), id.clone());
+        }
+        if let Some(ref stop_price) = self.stop_price {
+            params.insert("stopPrice".to_string(), stop_price.clone());
+        }
+        if let Some(wt) = self.working_type {
+            params.insert("workingType".to_string(), wt.to_string());
+        }
+        if self.price_protect {
+            params.insert("priceProtect".to_string(), "TRUE".to_string());
+        }
+        if self.reduce_only {
+            params.insert("reduceOnly".to_string(), "true".to_string());
+        }
+        if let Some(ref price) = self.activation_price {
+            params.insert("activationPrice".to_string(), price.clone());
+        }
+        if let Some(ref rate) = self.callback_rate {
+            params.insert("callbackRate".to_string(), rate.clone());
+        }
+        if let Some(ps) = self.position_side {
+            params.insert("positionSide".to_string(), ps.to_string());
+        }
+        params
+    }
+}
+
+impl OrderRequestBuilder {
+    pub fn quantity(&mut self, quantity: f64) -> &mut Self {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn price(&mut self, price: f64) -> &mut Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn time_in_force(&mut self, time_in_force: TimeInForce) -> &mut Self {
+        self.time_in_force = Some(time_in_force);
+        self
+    }
+
+    pub fn client_order_id(&mut self, id: &str) -> &mut Self {
+        self.new_client_order_id = Some(id.to_string());
+        self
+    }
+
+    pub fn stop_price(&mut self, stop_price: f64) -> &mut Self {
+        self.stop_price = Some(stop_price);
+        self
+    }
+
+    pub fn working_type(&mut self, working_type: WorkingType) -> &mut Self {
+        self.working_type = Some(working_type);
+        self
+    }
+
+    // 标记价格与最新价偏离过大时不触发
+    pub fn price_protect(&mut self, price_protect: bool) -> &mut Self {
+        self.price_protect = price_protect;
+        self
+    }
+
+    // 双向持仓模式下不能使用, 由 position_side 决定开平仓
+    pub fn reduce_only(&mut self, reduce_only: bool) -> &mut Self {
+        self.reduce_only = reduce_only;
+        self
+    }
+
+    pub fn close_position(&mut self, close_position: bool) -> &mut Self {
+        self.close_position = close_position;
+        self
+    }
+
+    pub fn activation_price(&mut self, activation_price: f64) -> &mut Self {
+        self.activation_price = Some(activation_price);
+        self
+    }
+
+    pub fn callback_rate(&mut self, callback_rate: f64) -> &mut Self {
+        self.callback_rate = Some(callback_rate);
+        self
+    }
+
+    pub fn position_side(&mut self, position_side: PositionSide) -> &mut Self {
+        self.position_side = Some(position_side);
+        self
+    }
+
+    // 以当前订单为入场单, 附带数量相同的止损和止盈平仓单
+    pub fn bracket(&self, stop_loss: f64, take_profit: f64) -> BracketBuilder {
+        let exit_side = match self.side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let exit = |type_: OrderType, stop_price: f64, suffix: &str| {
+            let mut leg = OrderRequest::builder(&self.symbol, exit_side, type_);
+            leg.quantity(self.quantity).stop_price(stop_price);
+            match self.position_side {
+                Some(ps) if ps != PositionSide::Both => {
+                    leg.position_side(ps);
+                }
+                _ => {
+                    leg.reduce_only(true);
+                }
+            }
+            if let Some(ref id) = self.new_client_order_id {
+                leg.client_order_id(&format!("{}-{}", id, suffix));
+            }
+            leg
+        };
+        BracketBuilder {
+            stop_loss: exit(OrderType::StopMarket, stop_loss, "sl"),
+            take_profit: exit(OrderType::TakeProfitMarket, take_profit, "tp"),
+            entry: self.clone(),
+        }
+    }
+
+    // 不做取整和校验, 用于没有缓存交易对信息的场景
+    // 没有市场信息, TAKE_PROFIT 带价格时按合约处理, 否则按现货处理
+    pub fn build_unchecked(&self) -> OrderRequest {
+        let market = if self.price.is_some() { Market::Futures } else { Market::Spot };
+        self.finish(
+            market,
+            format_decimal(self.quantity),
+            self.price.map(format_decimal),
+            self.stop_price.map(format_decimal),
+            self.activation_price.map(format_decimal),
+        )
+    }
+
+    // 价格按tickSize四舍五入, 数量按stepSize向下取整, 然后校验上下限和最小名义价值
+    pub fn build(&self, info: &SymbolInfo) -> Result<OrderRequest> {
+        if self.symbol != info.symbol {
+            return Err(EdpError::InvalidOrder(format!(
+                "symbol {} does not match {}",
+                self.symbol, info.symbol
+            )));
+        }
+        self.check_conditional()?;
+        let filters = &info.filters;
+        let price = match (needs_price(self.type_, info.market), self.price) {
+            (true, None) => {
+                return Err(EdpError::InvalidOrder(format!("{} order needs a price", self.type_)))
+            }
+            (true, Some(p)) => Some(round_to_step(p, filters.tick_size, false)),
+            (false, _) => None,
+        };
+        let stop_price = self.stop_price.map(|p| round_to_step(p, filters.tick_size, false));
+        let activation_price = self.activation_price.map(|p| round_to_step(p, filters.tick_size, false));
+        let quantity = round_to_step(self.quantity, filters.step_size, true);
+        if !self.close_position {
+            check_filters(filters, price, quantity)?;
+        }
+
+        let format_price = |p: Option<f64>| p.map(|p| format_step(p, filters.tick_size));
+        Ok(self.finish(
+            info.market,
+            format_step(quantity, filters.step_size),
+            format_price(price),
+            format_price(stop_price),
+            format_price(activation_price),
+        ))
+    }
+
+    // 条件单参数之间的约束, 交易所会拒绝不满足的组合
+    fn check_conditional(&self) -> Result<()> {
+        let needs_stop = matches!(
+            self.type_,
+            OrderType::Stop
+                | OrderType::StopMarket
+                | OrderType::TakeProfit
+                | OrderType::TakeProfitMarket
+                | OrderType::StopLoss
+                | OrderType::StopLossLimit
+                | OrderType::TakeProfitLimit
+        );
+        if needs_stop && self.stop_price.is_none() {
+            return Err(EdpError::InvalidOrder(format!("{} order needs a stop price", self.type_)));
+        }
+        if self.type_ == OrderType::TrailingStopMarket {
+            match self.callback_rate {
+                Some(rate) if (MIN_CALLBACK_RATE..=MAX_CALLBACK_RATE).contains(&rate) => {}
+                Some(rate) => {
+                    return Err(EdpError::InvalidOrder(format!(
+                        "callback rate {} out of range [{}, {}]",
+                        rate, MIN_CALLBACK_RATE, MAX_CALLBACK_RATE
+                    )))
+                }
+                None => return Err(EdpError::InvalidOrder("trailing stop needs a callback rate".to_string())),
+            }
+        }
+        if self.close_position {
+            if !matches!(self.type_, OrderType::StopMarket | OrderType::TakeProfitMarket) {
+                return Err(EdpError::InvalidOrder(format!("{} order can not close position", self.type_)));
+            }
+            if self.reduce_only {
+                return Err(EdpError::InvalidOrder("close position can not be reduce only".to_string()));
+            }
+        }
+        if self.reduce_only && matches!(self.position_side, Some(PositionSide::Long) | Some(PositionSide::Short)) {
+            return Err(EdpError::InvalidOrder("reduce only is not allowed in hedge mode".to_string()));
+        }
+        Ok(())
+    }
+
+    fn finish(
+        &self,
+        market: Market,
+        quantity: String,
+        price: Option<String>,
+        stop_price: Option<String>,
+        activation_price: Option<String>,
+    ) -> OrderRequest {
+        let time_in_force = if has_time_in_force(self.type_, market) {
+            Some(self.time_in_force.unwrap_or(TimeInForce::Gtc))
+        } else {
+            self.time_in_force
+        };
+        OrderRequest {
+            symbol: self.symbol.clone(),
+            side: self.side,
+            type_: self.type_,
+            quantity,
+            price,
+            time_in_force,
+            new_client_order_id: self.new_client_order_id.clone(),
+            stop_price,
+            working_type: self.working_type,
+            price_protect: self.price_protect,
+            reduce_only: self.reduce_only,
+            close_position: self.close_position,
+            activation_price,
+            callback_rate: self.callback_rate.map(format_decimal),
+            position_side: self.position_side,
+        }
+    }
+}
+
+// 市价类订单不能带价格, 现货的 TAKE_PROFIT 触发后为市价单, 只需要 stopPrice
+fn needs_price(type_: OrderType, market: Market) -> bool {
+    match type_ {
+        OrderType::Market
+        | OrderType::StopMarket
+        | OrderType::TakeProfitMarket
+        | OrderType::TrailingStopMarket
+        | OrderType::StopLoss => false,
+        OrderType::TakeProfit => market == Market::Futures,
+        _ => true,
+    }
+}
+
+// 限价类订单必须带 timeInForce, LIMIT_MAKER 除外
+fn has_time_in_force(type_: OrderType, market: Market) -> bool {
+    match type_ {
+        OrderType::Limit | OrderType::StopLossLimit | OrderType::TakeProfitLimit | OrderType::Stop => true,
+        OrderType::TakeProfit => market == Market::Futures,
+        _ => false,
+    }
+}
+
+const MIN_CALLBACK_RATE: f64 = 0.1;
+const MAX_CALLBACK_RATE: f64 = 10.;
+
+// 入场单和两个平仓单, 可以用 BinancePerpetual::place_bracket 一次提交
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bracket {
+    pub entry: OrderRequest,
+    pub stop_loss: OrderRequest,
+    pub take_profit: OrderRequest,
+}
+
+pub struct BracketBuilder {
+    entry: OrderRequestBuilder,
+    stop_loss: OrderRequestBuilder,
+    take_profit: OrderRequestBuilder,
+}
+
+impl BracketBuilder {
+    // 平仓单的触发价格类型
+    pub fn working_type(&mut self, working_type: WorkingType) -> &mut Self {
+        self.stop_loss.working_type(working_type);
+        self.take_profit.working_type(working_type);
+        self
+    }
+
+    pub fn price_protect(&mut self, price_protect: bool) -> &mut Self {
+        self.stop_loss.price_protect(price_protect);
+        self.take_profit.price_protect(price_protect);
+        self
+    }
+
+    pub fn build_unchecked(&self) -> Bracket {
+        Bracket {
+            entry: self.entry.build_unchecked(),
+            stop_loss: self.stop_loss.build_unchecked(),
+            take_profit: self.take_profit.build_unchecked(),
+        }
+    }
+
+    // 买入时需要 止损 < 入场价 < 止盈, 卖出时相反
+    pub fn build(&self, info: &SymbolInfo) -> Result<Bracket> {
+        let stop_loss = self.stop_loss.stop_price.unwrap_or_default();
+        let take_profit = self.take_profit.stop_price.unwrap_or_default();
+        let (low, high) = match self.entry.side {
+            Side::Buy => (stop_loss, take_profit),
+            Side::Sell => (take_profit, stop_loss),
+        };
+        let in_order = match self.entry.price {
+            Some(p) => low < p && p < high,
+            None => low < high,
+        };
+        if !in_order {
+            return Err(EdpError::InvalidOrder(format!(
+                "{} entry needs stop loss {} and take profit {} on opposite sides",
+                self.entry.side, stop_loss, take_profit
+            )));
+        }
+        Ok(Bracket {
+            entry: self.entry.build(info)?,
+            stop_loss: self.stop_loss.build(info)?,
+            take_profit: self.take_profit.build(info)?,
+        })
+    }
+}
+
+// 现货 OCO: 一个限价单和一个止损(限价)单, 一个成交后交易所自动撤销另一个
+// 卖出时需要 price > 当前价 > stop_price, 买入时相反
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcoRequest {
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: String,
+    pub price: String,
+    pub stop_price: String,
+    // 不设置时止损腿为 STOP_LOSS 市价单
+    pub stop_limit_price: Option<String>,
+    pub stop_limit_time_in_force: Option<TimeInForce>,
+    pub list_client_order_id: Option<String>,
+    pub limit_client_order_id: Option<String>,
+    pub stop_client_order_id: Option<String>,
+}
+
+pub struct OcoRequestBuilder {
+    symbol: String,
+    side: Side,
+    quantity: f64,
+    price: Option<f64>,
+    stop_price: Option<f64>,
+    stop_limit_price: Option<f64>,
+    stop_limit_time_in_force: Option<TimeInForce>,
+    list_client_order_id: Option<String>,
+    limit_client_order_id: Option<String>,
+    stop_client_order_id: Option<String>,
+}
+
+impl OcoRequest {
+    pub fn builder(symbol: &str, side: Side) -> OcoRequestBuilder {
+        OcoRequestBuilder {
+            symbol: symbol.to_string(),
+            side,
+            quantity: 0.,
+            price: None,
+            stop_price: None,
+            stop_limit_price: None,
+            stop_limit_time_in_force: None,
+            list_client_order_id: None,
+            limit_client_order_id: None,
+            stop_client_order_id: None,
+        }
+    }
+
+    pub fn to_params(&self) -> BTreeMap<String, String> {
+        let mut params = BTreeMap::new();
+        params.insert("symbol".to_string(), self.symbol.clone());
+        params.insert("side".to_string(), self.side.to_string());
+        params.insert("quantity".to_string(), self.quantity.clone());
+        params.insert("price".to_string(), self.price.clone());
+        params.insert("stopPrice".to_string(), self.stop_price.clone());
+        if let Some(ref price) = self.stop_limit_price {
+            params.insert("stopLimitPrice".to_string(), price.clone());
+        }
+        if let Some(tif) = self.stop_limit_time_in_force {
+            params.insert("stopLimitTimeInForce".to_string(), tif.to_string());
+        }
+        if let Some(ref id) = self.list_client_order_id {
+            params.insert("listClientOrderId".to_string(), id.clone());
+        }
+        if let Some(ref id) = self.limit_client_order_id {
+            params.insert("limitClientOrderId".to_string(), id.clone());
+        }
+        if let Some(ref id) = self.stop_client_order_id {
+            params.insert("stopClientOrderId".to_string(), id.clone());
+        }
+        params
+    }
+}
+
+impl OcoRequestBuilder {
+    pub fn quantity(&mut self, quantity: f64) -> &mut Self {
+        self.quantity = quantity;
+        self
+    }
+
+    // 限价腿的价格
+    pub fn price(&mut self, price: f64) -> &mut Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn stop_price(&mut self, stop_price: f64) -> &mut Self {
+        self.stop_price = Some(stop_price);
+        self
+    }
+
+    pub fn stop_limit_price(&mut self, stop_limit_price: f64) -> &mut Self {
+        self.stop_limit_price = Some(stop_limit_price);
+        self
+    }
+
+    pub fn stop_limit_time_in_force(&mut self, time_in_force: TimeInForce) -> &mut Self {
+        self.stop_limit_time_in_force = Some(time_in_force);
+        self
+    }
+
+    pub fn list_client_order_id(&mut self, id: &str) -> &mut Self {
+        self.list_client_order_id = Some(id.to_string());
+        self
+    }
+
+    pub fn limit_client_order_id(&mut self, id: &str) -> &mut Self {
+        self.limit_client_order_id = Some(id.to_string());
+        self
+    }
+
+    pub fn stop_client_order_id(&mut self, id: &str) -> &mut Self {
+        self.stop_client_order_id = Some(id.to_string());
+        self
+    }
+
+    // 不做取整和校验, 用于没有缓存交易对信息的场景
+    pub fn build_unchecked(&self) -> OcoRequest {
+        self.finish(
+            format_decimal(self.quantity),
+            format_decimal(self.price.unwrap_or_default()),
+            format_decimal(self.stop_price.unwrap_or_default()),
+            self.stop_limit_price.map(format_decimal),
+        )
+    }
+
+    // 与 OrderRequestBuilder::build 相同的取整规则, 两个腿分别校验
+    pub fn build(&self, info: &SymbolInfo) -> Result<OcoRequest> {
+        if self.symbol != info.symbol {
+            return Err(EdpError::InvalidOrder(format!(
+                "symbol {} does not match {}",
+                self.symbol, info.symbol
+            )));
+        }
+        let (price, stop_price) = self.prices()?;
+        let filters = &info.filters;
+        let price = round_to_step(price, filters.tick_size, false);
+        let stop_price = round_to_step(stop_price, filters.tick_size, false);
+        let stop_limit_price = self.stop_limit_price.map(|p| round_to_step(p, filters.tick_size, false));
+        let quantity = round_to_step(self.quantity, filters.step_size, true);
+        check_filters(filters, Some(price), quantity)?;
+        check_filters(filters, Some(stop_limit_price.unwrap_or(stop_price)), quantity)?;
+
+        Ok(self.finish(
+            format_step(quantity, filters.step_size),
+            format_step(price, filters.tick_size),
+            format_step(stop_price, filters.tick_size),
+            stop_limit_price.map(|p| format_step(p, filters.tick_size)),
+        ))
+    }
+
+    fn prices(&self) -> Result<(f64, f64)> {
+        let (price, stop_price) = match (self.price, self.stop_price) {
+            (Some(p), Some(s)) => (p, s),
+            _ => return Err(EdpError::InvalidOrder("oco order needs a price and a stop price".to_string())),
+        };
+        let in_order = match self.side {
+            Side::Buy => price < stop_price,
+            Side::Sell => price > stop_price,
+        };
+        if !in_order {
+            return Err(EdpError::InvalidOrder(format!(
+                "{} oco needs price {} and stop price {} on opposite sides",
+                self.side, price, stop_price
+            )));
+        }
+        Ok((price, stop_price))
+    }
+
+    fn finish(&self, quantity: String, price: String, stop_price: String, stop_limit_price: Option<String>) -> OcoRequest {
+        // 止损限价腿必须指定 timeInForce
+        let stop_limit_time_in_force = stop_limit_price
+            .as_ref()
+            .map(|_| self.stop_limit_time_in_force.unwrap_or(TimeInForce::Gtc));
+        OcoRequest {
+            symbol: self.symbol.clone(),
+            side: self.side,
+            quantity,
+            price,
+            stop_price,
+            stop_limit_price,
+            stop_limit_time_in_force,
+            list_client_order_id: self.list_client_order_id.clone(),
+            limit_client_order_id: self.limit_client_order_id.clone(),
+            stop_client_order_id: self.stop_client_order_id.clone(),
+        }
+    }
+}
+
+// 撤单/查单参数, orderId 优先
+pub fn order_id_params(
+    symbol: &str,
+    order_id: Option<u64>,
+    client_order_id: Option<&str>,
+) -> Result<BTreeMap<String, String>> {
+    let mut params = BTreeMap::new();
+    params.insert("symbol".to_string(), symbol.to_string());
+    match (order_id, client_order_id) {
+        (Some(id), _) => {
+            params.insert("orderId".to_string(), id.to_string());
+        }
+        (None, Some(id)) => {
+            params.insert("origClientOrderId".to_string(), id.to_string());
+        }
+        (None, None) => {
+            return Err(EdpError::InvalidOrder("order_id or client_order_id is required".to_string()))
+        }
+    }
+    Ok(params)
+}
+
+fn check_filters(filters: &SymbolFilters, price: Option<f64>, quantity: f64) -> Result<()> {
+    if let Some(p) = price {
+        if p <= 0. || (filters.min_price > 0. && p < filters.min_price) {
+            return Err(EdpError::InvalidOrder(format!("price {} below min price {}", p, filters.min_price)));
+        }
+        if filters.max_price > 0. && p > filters.max_price {
+            return Err(EdpError::InvalidOrder(format!("price {} above max price {}", p, filters.max_price)));
+        }
+    }
+    if quantity <= 0. || (filters.min_qty > 0. && quantity < filters.min_qty) {
+        return Err(EdpError::InvalidOrder(format!("quantity {} below min qty {}", quantity, filters.min_qty)));
+    }
+    if filters.max_qty > 0. && quantity > filters.max_qty {
+        return Err(EdpError::InvalidOrder(format!("quantity {} above max qty {}", quantity, filters.max_qty)));
+    }
+    // 市价单没有价格, 名义价值由交易所按成交价检查
+    if let Some(p) = price {
+        if filters.min_notional > 0. && p * quantity < filters.min_notional {
+            return Err(EdpError::InvalidOrder(format!(
+                "notional {} below min notional {}",
+                p * quantity,
+                filters.min_notional
+            )));
+        }
+    }
+    Ok(())
+}
+
+// 小数位数, 例如 0.001 -> 3, 1 -> 0
+pub fn step_decimals(step: f64) -> usize {
+    let s = step.to_string();
+    match s.find('.') {
+        Some(i) => s[i + 1..].trim_end_matches('0').len(),
+        None => 0,
+    }
+}
+
+pub fn round_to_step(value: f64, step: f64, floor: bool) -> f64 {
+    if step <= 0. {
+        return value;
+    }
+    let units = value / step;
+    // 容忍除法带来的微小误差, 避免 0.3 / 0.1 = 2.9999999999999996 被向下取整为2
+    let units = if floor { (units + 1e-9).floor() } else { units.round() };
+    let decimals = step_decimals(step) as i32;
+    let scale = 10f64.powi(decimals);
+    (units * step * scale).round() / scale
+}
+
+pub fn format_step(value: f64, step: f64) -> String {
+    if step <= 0. {
+        return format_decimal(value);
+    }
+    format!("{:.*}", step_decimals(step), value)
+}
+
+// 没有步长时按交易所支持的最大精度8位小数格式化, 去掉末尾的0
+pub fn format_decimal(value: f64) -> String {
+    let s = format!("{:.*}", MAX_DECIMALS, value);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+const MAX_DECIMALS: usize = 8;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn btcusdt() -> SymbolInfo {
+        SymbolInfo {
+            symbol: "BTCUSDT".to_string(),
+            market: Market::Spot,
+            base: "BTC".to_string(),
+            quote: "USDT".to_string(),
+            price_precision: 8,
+            quantity_precision: 8,
+            base_precision: 8,
+            quote_precision: 8,
+            filters: SymbolFilters {
+                min_price: 0.01,
+                max_price: 1000000.,
+                tick_size: 0.01,
+                min_qty: 0.00001,
+                max_qty: 9000.,
+                step_size: 0.00001,
+                min_notional: 10.,
+            },
+        }
+    }
+
+    #[test]
+    fn test_round_and_format() {
+        let order = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.123456789)
+            .price(30000.126)
+            .build(&btcusdt())
+            .unwrap();
+        assert_eq!(order.quantity, "0.12345");
+        assert_eq!(order.price.as_deref(), Some("30000.13"));
+        assert_eq!(order.time_in_force, Some(TimeInForce::Gtc));
+    }
+
+    #[test]
+    fn test_no_float_artifacts() {
+        let mut info = btcusdt();
+        info.filters.step_size = 0.1;
+        info.filters.tick_size = 0.1;
+        info.filters.min_notional = 0.;
+        let order = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Limit)
+            .quantity(0.1 + 1.0)
+            .price(0.1 + 0.2)
+            .build(&info)
+            .unwrap();
+        assert_eq!(order.quantity, "1.1");
+        assert_eq!(order.price.as_deref(), Some("0.3"));
+        assert_eq!(format_step(0.1 + 0.2, 0.001), "0.300");
+        assert_eq!(format_step(12.0, 1.0), "12");
+        assert_eq!(format_step(0.1 + 0.2, 0.), "0.3");
+
+        // 不取整时同样没有浮点误差
+        let order = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Limit)
+            .quantity(0.1 + 1.0)
+            .price(0.1 + 0.2)
+            .build_unchecked();
+        assert_eq!((order.quantity.as_str(), order.price.as_deref()), ("1.1", Some("0.3")));
+        assert_eq!(format_decimal(30000.), "30000");
+        assert_eq!(format_decimal(0.000000016), "0.00000002");
+    }
+
+    #[test]
+    fn test_reject_filters() {
+        let info = btcusdt();
+        let err = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.0001)
+            .price(20000.)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("notional")));
+
+        let err = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.000001)
+            .price(20000.)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("min qty")));
+
+        let err = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(10000.)
+            .price(20000.)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("max qty")));
+
+        let err = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(1.)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(_)));
+    }
+
+    #[test]
+    fn test_limit_trigger_orders_default_to_gtc() {
+        for type_ in [OrderType::StopLossLimit, OrderType::TakeProfitLimit, OrderType::Stop] {
+            let order = OrderRequest::builder("BTCUSDT", Side::Sell, type_)
+                .quantity(0.01)
+                .price(29000.)
+                .stop_price(29100.)
+                .build(&btcusdt())
+                .unwrap();
+            assert_eq!(order.to_params().get("timeInForce").map(String::as_str), Some("GTC"));
+        }
+        let order = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::LimitMaker)
+            .quantity(0.01)
+            .price(29000.)
+            .build(&btcusdt())
+            .unwrap();
+        assert_eq!(order.time_in_force, None);
+    }
+
+    #[test]
+    fn test_take_profit_price_by_market() {
+        // 现货 TAKE_PROFIT 触发后为市价单, 只发送 stopPrice
+        let mut builder = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::TakeProfit);
+        builder.quantity(0.01).stop_price(31000.);
+        let order = builder.build(&btcusdt()).unwrap();
+        let params = order.to_params();
+        assert_eq!(params.get("stopPrice").map(String::as_str), Some("31000.00"));
+        assert_eq!((params.get("price"), params.get("timeInForce")), (None, None));
+        let order = builder.clone().price(31000.).build(&btcusdt()).unwrap();
+        assert_eq!(order.price, None);
+
+        // 合约 TAKE_PROFIT 为限价单
+        let mut futures = btcusdt();
+        futures.market = Market::Futures;
+        assert!(matches!(builder.build(&futures), Err(EdpError::InvalidOrder(_))));
+        let order = builder.clone().price(31000.).build(&futures).unwrap();
+        assert_eq!(order.price.as_deref(), Some("31000.00"));
+        assert_eq!(order.time_in_force, Some(TimeInForce::Gtc));
+
+        assert_eq!(builder.build_unchecked().time_in_force, None);
+        assert_eq!(builder.clone().price(31000.).build_unchecked().time_in_force, Some(TimeInForce::Gtc));
+    }
+
+    #[test]
+    fn test_market_order_params() {
+        let order = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Market)
+            .quantity(0.5)
+            .price(1.)
+            .client_order_id("my-id")
+            .build(&btcusdt())
+            .unwrap();
+        let params = order.to_params();
+        assert_eq!(params.get("price"), None);
+        assert_eq!(params.get("timeInForce"), None);
+        assert_eq!(params.get("quantity").map(String::as_str), Some("0.50000"));
+        assert_eq!(params.get("newClientOrderId").map(String::as_str), Some("my-id"));
+    }
+
+    #[test]
+    fn test_conditional_params() {
+        let order = OrderRequest::stop_market("BTCUSDT", Side::Sell, 29000.004)
+            .close_position(true)
+            .working_type(WorkingType::MarkPrice)
+            .price_protect(true)
+            .build(&btcusdt())
+            .unwrap();
+        let params = order.to_params();
+        assert_eq!(params.get("stopPrice").map(String::as_str), Some("29000.00"));
+        assert_eq!(params.get("closePosition").map(String::as_str), Some("true"));
+        assert_eq!(params.get("workingType").map(String::as_str), Some("MARK_PRICE"));
+        assert_eq!(params.get("priceProtect").map(String::as_str), Some("TRUE"));
+        assert_eq!(params.get("quantity"), None);
+        assert_eq!(params.get("price"), None);
+
+        let order = OrderRequest::trailing_stop("BTCUSDT", Side::Sell, 1.5)
+            .quantity(0.01)
+            .activation_price(31000.)
+            .position_side(PositionSide::Long)
+            .build(&btcusdt())
+            .unwrap();
+        let params = order.to_params();
+        assert_eq!(params.get("callbackRate").map(String::as_str), Some("1.5"));
+        assert_eq!(params.get("activationPrice").map(String::as_str), Some("31000.00"));
+        assert_eq!(params.get("positionSide").map(String::as_str), Some("LONG"));
+        assert_eq!(params.get("reduceOnly"), None);
+    }
+
+    #[test]
+    fn test_reject_conditional() {
+        let info = btcusdt();
+        let err = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::StopMarket)
+            .quantity(0.01)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("stop price")));
+
+        let err = OrderRequest::trailing_stop("BTCUSDT", Side::Sell, 20.).quantity(0.01).build(&info).unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("callback rate")));
+
+        let err = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Limit)
+            .quantity(0.01)
+            .price(30000.)
+            .close_position(true)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("close position")));
+
+        let err = OrderRequest::take_profit_market("BTCUSDT", Side::Sell, 32000.)
+            .quantity(0.01)
+            .reduce_only(true)
+            .position_side(PositionSide::Long)
+            .build(&info)
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("hedge mode")));
+    }
+
+    #[test]
+    fn test_bracket() {
+        let bracket = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.01)
+            .price(30000.)
+            .client_order_id("entry")
+            .bracket(29000., 32000.)
+            .working_type(WorkingType::MarkPrice)
+            .build(&btcusdt())
+            .unwrap();
+        let sl = &bracket.stop_loss;
+        assert_eq!((sl.side, sl.type_), (Side::Sell, OrderType::StopMarket));
+        assert_eq!(sl.stop_price.as_deref(), Some("29000.00"));
+        assert_eq!(sl.quantity, bracket.entry.quantity);
+        assert!(sl.reduce_only);
+        assert_eq!(sl.working_type, Some(WorkingType::MarkPrice));
+        assert_eq!(sl.new_client_order_id.as_deref(), Some("entry-sl"));
+        let tp = &bracket.take_profit;
+        assert_eq!((tp.side, tp.type_), (Side::Sell, OrderType::TakeProfitMarket));
+        assert_eq!(tp.new_client_order_id.as_deref(), Some("entry-tp"));
+
+        // 双向持仓时用 positionSide 代替 reduceOnly
+        let bracket = OrderRequest::builder("BTCUSDT", Side::Sell, OrderType::Market)
+            .quantity(0.01)
+            .position_side(PositionSide::Short)
+            .bracket(32000., 29000.)
+            .build_unchecked();
+        assert!(!bracket.stop_loss.reduce_only);
+        assert_eq!(bracket.take_profit.position_side, Some(PositionSide::Short));
+
+        let err = OrderRequest::builder("BTCUSDT", Side::Buy, OrderType::Limit)
+            .quantity(0.01)
+            .price(30000.)
+            .bracket(31000., 32000.)
+            .build(&btcusdt())
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("opposite sides")));
+    }
+
+    #[test]
+    fn test_oco_request() {
+        let oco = OcoRequest::builder("BTCUSDT", Side::Sell)
+            .quantity(0.123456)
+            .price(32000.004)
+            .stop_price(29000.)
+            .stop_limit_price(28900.)
+            .list_client_order_id("list")
+            .build(&btcusdt())
+            .unwrap();
+        let params = oco.to_params();
+        assert_eq!(params.get("quantity").map(String::as_str), Some("0.12345"));
+        assert_eq!(params.get("price").map(String::as_str), Some("32000.00"));
+        assert_eq!(params.get("stopLimitPrice").map(String::as_str), Some("28900.00"));
+        assert_eq!(params.get("stopLimitTimeInForce").map(String::as_str), Some("GTC"));
+        assert_eq!(params.get("listClientOrderId").map(String::as_str), Some("list"));
+
+        let err = OcoRequest::builder("BTCUSDT", Side::Buy)
+            .quantity(0.1)
+            .price(32000.)
+            .stop_price(29000.)
+            .build(&btcusdt())
+            .unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("opposite sides")));
+        let err = OcoRequest::builder("BTCUSDT", Side::Buy).quantity(0.1).price(1.).build(&btcusdt()).unwrap_err();
+        assert!(matches!(err, EdpError::InvalidOrder(ref m) if m.contains("stop price")));
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::prelude::*;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// 本地时钟与交易所服务器时间的偏差
+// offset = serverTime - (发送时间 + rtt / 2)
+#[derive(Debug)]
+pub struct ServerClock {
+    end_point: String,
+    offset_ms: AtomicI64,
+    rtt_ms: AtomicI64,
+    last_sync: Mutex<Option<Instant>>,
+    pub refresh_interval: Duration,
+    // 每次同步的采样次数, 取rtt最小的一次
+    pub samples: usize,
+}
+
+impl ServerClock {
+    // end_point: 现货 /api/v3/time, U本位 /fapi/v1/time
+    pub fn new(end_point: &str) -> Self {
+        Self {
+            end_point: end_point.to_string(),
+            offset_ms: AtomicI64::new(0),
+            rtt_ms: AtomicI64::new(0),
+            last_sync: Mutex::new(None),
+            refresh_interval: Duration::from_secs(300),
+            samples: 3,
+        }
+    }
+
+    pub fn end_point(&self) -> &str {
+        &self.end_point
+    }
+
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn rtt_ms(&self) -> i64 {
+        self.rtt_ms.load(Ordering::Relaxed)
+    }
+
+    // 校正后的服务器时间
+    pub fn now_ms(&self) -> i64 {
+        Utc::now().timestamp_millis() + self.offset_ms()
+    }
+
+    pub fn needs_sync(&self) -> bool {
+        match *self.last_sync.lock().unwrap() {
+            Some(at) => at.elapsed() >= self.refresh_interval,
+            None => true,
+        }
+    }
+
+    // samples: (发送时间, 收到时间, serverTime)
+    pub fn update(&self, samples: &[(i64, i64, i64)]) {
+        let best = samples.iter().min_by_key(|(sent, recv, _)| recv - sent);
+        if let Some(&(sent, recv, server)) = best {
+            let rtt = recv - sent;
+            let offset = server - (sent + rtt / 2);
+            self.offset_ms.store(offset, Ordering::Relaxed);
+            self.rtt_ms.store(rtt, Ordering::Relaxed);
+            *self.last_sync.lock().unwrap() = Some(Instant::now());
+            log::debug!("server clock offset {}ms, rtt {}ms", offset, rtt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_uses_fastest_sample() {
+        let clock = ServerClock::new("/api/v3/time");
+        assert!(clock.needs_sync());
+        clock.update(&[(1000, 1400, 3000), (2000, 2020, 3510), (5000, 5100, 6000)]);
+        assert_eq!(clock.rtt_ms(), 20);
+        assert_eq!(clock.offset_ms(), 1500);
+        assert!(!clock.needs_sync());
+    }
+}
@@ -0,0 +1,457 @@
+use crate::error::{EdpError, Result};
+use crate::model::RateLimit;
+use chrono::prelude::*;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// 超出额度时的处理方式: 排队等到下一个窗口, 或直接返回RateLimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitPolicy {
+    Wait,
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    RequestWeight,
+    Orders,
+    RawRequests,
+}
+
+impl LimitKind {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "REQUEST_WEIGHT" => Some(LimitKind::RequestWeight),
+            "ORDERS" => Some(LimitKind::Orders),
+            "RAW_REQUESTS" => Some(LimitKind::RawRequests),
+            _ => None,
+        }
+    }
+}
+
+// 单个接口的消耗
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointCost {
+    pub weight: u64,
+    pub orders: u64,
+}
+
+#[derive(Debug)]
+struct Window {
+    kind: LimitKind,
+    interval_ms: i64,
+    limit: u64,
+    used: u64,
+    // 窗口按整点对齐, 与交易所一致
+    window_id: i64,
+    // 对应的响应头, 例如 x-mbx-used-weight-1m
+    header: Option<String>,
+}
+
+impl Window {
+    fn roll(&mut self, now_ms: i64) {
+        let id = now_ms / self.interval_ms;
+        if id != self.window_id {
+            self.window_id = id;
+            self.used = 0;
+        }
+    }
+
+    fn cost(&self, cost: &EndpointCost) -> u64 {
+        match self.kind {
+            LimitKind::RequestWeight => cost.weight,
+            LimitKind::Orders => cost.orders,
+            LimitKind::RawRequests => 1,
+        }
+    }
+
+    fn next_window_ms(&self, now_ms: i64) -> i64 {
+        (now_ms / self.interval_ms + 1) * self.interval_ms - now_ms
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    windows: Vec<Window>,
+    // 429/418 之后交易所要求的等待截止时间
+    banned_until_ms: i64,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    state: Mutex<State>,
+    policy: LimitPolicy,
+}
+
+impl RateLimiter {
+    pub fn new(policy: LimitPolicy) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            policy,
+        }
+    }
+
+    // 用 exchangeInfo 中的 rateLimits 初始化
+    pub fn from_rate_limits(limits: &[RateLimit], policy: LimitPolicy) -> Self {
+        let limiter = Self::new(policy);
+        limiter.set_limits(limits);
+        limiter
+    }
+
+    pub fn set_limits(&self, limits: &[RateLimit]) {
+        let now_ms = Utc::now().timestamp_millis();
+        let windows = limits
+            .iter()
+            .filter_map(|rl| {
+                let kind = LimitKind::from_str(&rl.rate_limit_type)?;
+                let (unit_ms, letter) = match rl.interval.as_str() {
+                    "SECOND" => (1_000, "s"),
+                    "MINUTE" => (60_000, "m"),
+                    "HOUR" => (3_600_000, "h"),
+                    "DAY" => (86_400_000, "d"),
+                    _ => return None,
+                };
+                let interval_ms = unit_ms * rl.interval_num.max(1);
+                let header = match kind {
+                    LimitKind::RequestWeight => {
+                        Some(format!("x-mbx-used-weight-{}{}", rl.interval_num, letter))
+                    }
+                    LimitKind::Orders => Some(format!("x-mbx-order-count-{}{}", rl.interval_num, letter)),
+                    LimitKind::RawRequests => None,
+                };
+                Some(Window {
+                    kind,
+                    interval_ms,
+                    limit: rl.limit.max(0) as u64,
+                    used: 0,
+                    window_id: now_ms / interval_ms,
+                    header,
+                })
+            })
+            .collect();
+        self.state.lock().unwrap().windows = windows;
+    }
+
+    // 未设置 rateLimits 时不做任何限制
+    pub fn has_limits(&self) -> bool {
+        !self.state.lock().unwrap().windows.is_empty()
+    }
+
+    pub fn used(&self, kind: LimitKind) -> Vec<u64> {
+        let now_ms = Utc::now().timestamp_millis();
+        let mut state = self.state.lock().unwrap();
+        state
+            .windows
+            .iter_mut()
+            .filter(|w| w.kind == kind)
+            .map(|w| {
+                w.roll(now_ms);
+                w.used
+            })
+            .collect()
+    }
+
+    // 请求前调用, 额度足够时扣减并返回
+    pub async fn acquire(&self, cost: EndpointCost) -> Result<()> {
+        loop {
+            let wait_ms = self.try_acquire(&cost)?;
+            if wait_ms <= 0 {
+                return Ok(());
+            }
+            let retry_after = Duration::from_millis(wait_ms as u64);
+            match self.policy {
+                LimitPolicy::Reject => return Err(EdpError::RateLimited { retry_after }),
+                LimitPolicy::Wait => {
+                    log::debug!("rate limit reached, waiting {:?}", retry_after);
+                    tokio::time::sleep(retry_after).await;
+                }
+            }
+        }
+    }
+
+    // 返回需要等待的毫秒数, 0表示已扣减成功
+    fn try_acquire(&self, cost: &EndpointCost) -> Result<i64> {
+        let now_ms = Utc::now().timestamp_millis();
+        let mut state = self.state.lock().unwrap();
+        if state.banned_until_ms > now_ms {
+            return Ok(state.banned_until_ms - now_ms);
+        }
+        let mut wait_ms = 0;
+        for w in state.windows.iter_mut() {
+            w.roll(now_ms);
+            let c = w.cost(cost);
+            if c > w.limit {
+                // 单次请求就超过了整个窗口的额度, 等待也无济于事
+                return Err(EdpError::RateLimited {
+                    retry_after: Duration::from_millis(w.interval_ms as u64),
+                });
+            }
+            if w.used + c > w.limit {
+                wait_ms = wait_ms.max(w.next_window_ms(now_ms));
+            }
+        }
+        if wait_ms == 0 {
+            for w in state.windows.iter_mut() {
+                w.used += w.cost(cost);
+            }
+        }
+        Ok(wait_ms)
+    }
+
+    // 响应后调用, 以交易所返回的用量为准
+    pub fn sync(&self, status: StatusCode, headers: &HeaderMap) {
+        let now_ms = Utc::now().timestamp_millis();
+        let mut state = self.state.lock().unwrap();
+        for w in state.windows.iter_mut() {
+            w.roll(now_ms);
+            let used = w
+                .header
+                .as_ref()
+                .and_then(|h| headers.get(h.as_str()))
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok());
+            if let Some(used) = used {
+                w.used = used;
+            }
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS || status.as_u16() == 418 {
+            let retry_secs = headers
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or(60);
+            state.banned_until_ms = state.banned_until_ms.max(now_ms + retry_secs * 1000);
+        }
+    }
+}
+
+// 各接口的权重, 未列出的接口按1计算
+// https://binance-docs.github.io/apidocs/spot/en/#market-data-endpoints
+pub fn endpoint_cost(method: &Method, path: &str, query: &str) -> EndpointCost {
+    let param = |key: &str| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    };
+    let limit = param("limit").and_then(|l| l.parse::<u64>().ok());
+    let has_symbol = param("symbol").is_some();
+    let weight = match (method, path) {
+        (_, "/api/v3/exchangeInfo") => 10,
+        (_, "/api/v3/depth") => match limit.unwrap_or(100) {
+            0..=100 => 5,
+            101..=500 => 25,
+            501..=1000 => 50,
+            _ => 250,
+        },
+        (_, "/api/v3/klines") => 2,
+        (_, "/api/v3/trades") | (_, "/api/v3/historicalTrades") => 25,
+        (_, "/api/v3/ticker/24hr") => {
+            if has_symbol {
+                2
+            } else {
+                80
+            }
+        }
+        (_, "/api/v3/ticker/price") => {
+            if has_symbol {
+                2
+            } else {
+                4
+            }
+        }
+        // 滚动窗口每个交易对4, 最多200
+        (_, "/api/v3/ticker") => {
+            let symbols = param("symbols")
+                .and_then(|s| serde_json::from_str::<Vec<String>>(&s).ok())
+                .map_or(1, |s| s.len() as u64);
+            (symbols * 4).min(200)
+        }
+        (_, "/api/v3/avgPrice") => 2,
+        (_, "/api/v3/aggTrades") => 4,
+        (_, "/api/v3/ticker/bookTicker") => {
+            if has_symbol {
+                2
+            } else {
+                4
+            }
+        }
+        (&Method::GET, "/api/v3/order") | (&Method::GET, "/api/v3/orderList") => 4,
+        (_, "/api/v3/account") => 20,
+        (_, "/api/v3/userDataStream") => 2,
+        (_, "/fapi/v1/depth") | (_, "/dapi/v1/depth") => match limit.unwrap_or(500) {
+            0..=50 => 2,
+            51..=100 => 5,
+            101..=500 => 10,
+            _ => 20,
+        },
+        (_, "/fapi/v1/klines")
+        | (_, "/fapi/v1/markPriceKlines")
+        | (_, "/fapi/v1/indexPriceKlines")
+        | (_, "/fapi/v1/continuousKlines")
+        | (_, "/dapi/v1/klines") => match limit.unwrap_or(500) {
+            0..=99 => 1,
+            100..=499 => 2,
+            500..=1000 => 5,
+            _ => 10,
+        },
+        (_, "/fapi/v1/ticker/bookTicker") | (_, "/dapi/v1/ticker/bookTicker") => {
+            if has_symbol {
+                2
+            } else {
+                5
+            }
+        }
+        (_, "/fapi/v1/trades") | (_, "/dapi/v1/trades") => 5,
+        (_, "/fapi/v1/ticker/24hr") => {
+            if has_symbol {
+                1
+            } else {
+                40
+            }
+        }
+        (_, "/fapi/v1/ticker/price") | (_, "/fapi/v2/ticker/price") => {
+            if has_symbol {
+                1
+            } else {
+                2
+            }
+        }
+        (_, "/fapi/v1/historicalTrades") | (_, "/fapi/v1/aggTrades") => 20,
+        (_, "/fapi/v2/account") | (_, "/fapi/v2/balance") | (_, "/fapi/v2/positionRisk") => 5,
+        (_, "/dapi/v1/account") => 5,
+        (&Method::GET, "/fapi/v1/positionSide/dual") | (&Method::GET, "/fapi/v1/multiAssetsMargin") => 30,
+        (_, "/api/v3/myTrades") => 20,
+        (_, "/fapi/v1/userTrades") => 5,
+        (_, "/fapi/v1/income") => 30,
+        (_, "/fapi/v1/commissionRate") => 20,
+        (&Method::POST, "/fapi/v1/batchOrders") => 5,
+        (_, "/fapi/v1/countdownCancelAll") => 10,
+        (_, "/dapi/v1/premiumIndex") => 10,
+        (_, "/fapi/v1/premiumIndex") => {
+            if has_symbol {
+                1
+            } else {
+                10
+            }
+        }
+        _ => 1,
+    };
+    let orders = match (method, path) {
+        (&Method::POST, "/api/v3/order") | (&Method::POST, "/fapi/v1/order") | (&Method::POST, "/dapi/v1/order") => 1,
+        (&Method::POST, "/api/v3/order/cancelReplace") => 1,
+        (&Method::POST, "/api/v3/order/oco") => 2,
+        // 批量下单按订单数计入下单频率
+        (&Method::POST, "/fapi/v1/batchOrders") => param("batchOrders")
+            .and_then(|b| serde_json::from_str::<Vec<serde_json::Value>>(&b).ok())
+            .map_or(1, |orders| orders.len() as u64),
+        _ => 0,
+    };
+    EndpointCost { weight, orders }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn limits(weight: i64, orders: i64) -> Vec<RateLimit> {
+        vec![
+            RateLimit {
+                rate_limit_type: "REQUEST_WEIGHT".to_string(),
+                interval: "MINUTE".to_string(),
+                interval_num: 1,
+                limit: weight,
+            },
+            RateLimit {
+                rate_limit_type: "ORDERS".to_string(),
+                interval: "SECOND".to_string(),
+                interval_num: 10,
+                limit: orders,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_endpoint_cost() {
+        let c = endpoint_cost(&Method::GET, "/api/v3/depth", "symbol=BTCUSDT&limit=1000");
+        assert_eq!(c, EndpointCost { weight: 50, orders: 0 });
+        let c = endpoint_cost(&Method::POST, "/fapi/v1/order", "symbol=BTCUSDT");
+        assert_eq!(c, EndpointCost { weight: 1, orders: 1 });
+        let c = endpoint_cost(&Method::GET, "/api/v3/ticker/bookTicker", "");
+        assert_eq!(c.weight, 4);
+        let c = endpoint_cost(&Method::POST, "/fapi/v1/batchOrders", "batchOrders=%5B%7B%7D%2C%7B%7D%2C%7B%7D%5D");
+        assert_eq!(c, EndpointCost { weight: 5, orders: 3 });
+        let c = endpoint_cost(&Method::GET, "/fapi/v1/historicalTrades", "symbol=BTCUSDT&fromId=1");
+        assert_eq!(c.weight, 20);
+        let c = endpoint_cost(&Method::GET, "/api/v3/ticker", "symbols=%5B%22BTCUSDT%22%2C%22BNBUSDT%22%5D&windowSize=1d");
+        assert_eq!(c.weight, 8);
+        let c = endpoint_cost(&Method::GET, "/api/v3/ticker/24hr", "");
+        assert_eq!(c.weight, 80);
+    }
+
+    #[tokio::test]
+    async fn test_reject_over_budget() {
+        let limiter = RateLimiter::from_rate_limits(&limits(10, 100), LimitPolicy::Reject);
+        let cost = EndpointCost { weight: 4, orders: 0 };
+        limiter.acquire(cost).await.unwrap();
+        limiter.acquire(cost).await.unwrap();
+        let err = limiter.acquire(cost).await.unwrap_err();
+        assert!(matches!(err, EdpError::RateLimited { .. }));
+        assert_eq!(limiter.used(LimitKind::RequestWeight), vec![8]);
+    }
+
+    #[tokio::test]
+    async fn test_order_count() {
+        let limiter = RateLimiter::from_rate_limits(&limits(1000, 1), LimitPolicy::Reject);
+        let order = EndpointCost { weight: 1, orders: 1 };
+        limiter.acquire(order).await.unwrap();
+        assert!(limiter.acquire(order).await.is_err());
+        // 非下单接口不受订单数限制
+        limiter.acquire(EndpointCost { weight: 1, orders: 0 }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_next_window() {
+        let limits = vec![RateLimit {
+            rate_limit_type: "REQUEST_WEIGHT".to_string(),
+            interval: "SECOND".to_string(),
+            interval_num: 1,
+            limit: 1,
+        }];
+        let limiter = RateLimiter::from_rate_limits(&limits, LimitPolicy::Wait);
+        let cost = EndpointCost { weight: 1, orders: 0 };
+        limiter.acquire(cost).await.unwrap();
+        let start = std::time::Instant::now();
+        tokio::time::timeout(Duration::from_secs(3), limiter.acquire(cost))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(start.elapsed() <= Duration::from_millis(1100));
+    }
+
+    #[test]
+    fn test_sync_from_headers() {
+        let limiter = RateLimiter::from_rate_limits(&limits(1200, 100), LimitPolicy::Reject);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-mbx-used-weight-1m", HeaderValue::from_static("1150"));
+        headers.insert("x-mbx-order-count-10s", HeaderValue::from_static("7"));
+        limiter.sync(StatusCode::OK, &headers);
+        assert_eq!(limiter.used(LimitKind::RequestWeight), vec![1150]);
+        assert_eq!(limiter.used(LimitKind::Orders), vec![7]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_blocks() {
+        let limiter = RateLimiter::new(LimitPolicy::Reject);
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+        limiter.sync(StatusCode::TOO_MANY_REQUESTS, &headers);
+        let err = limiter.acquire(EndpointCost { weight: 1, orders: 0 }).await.unwrap_err();
+        match err {
+            EdpError::RateLimited { retry_after } => assert!(retry_after > Duration::from_secs(28)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
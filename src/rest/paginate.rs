@@ -0,0 +1,308 @@
+use crate::error::{EdpError, Result};
+use crate::model::KData;
+use futures::stream::{self, Stream, TryStreamExt};
+use std::collections::BTreeMap;
+use std::future::Future;
+
+// 把 [start, end) 拆成多次请求, 每次最多 page_limit 根K线
+// fetch(startTime, endTime, limit), 交易所的 endTime 包含在内, 这里传 end - 1
+// 下一页从上一页最后一根的开盘时间 + 1 开始, 不依赖周期长度, 1M 这类不定长周期也适用
+pub async fn klines_range<F, Fut>(start: u64, end: u64, page_limit: u64, fetch: F) -> Result<Vec<KData>>
+where
+    F: FnMut(u64, u64, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<KData>>>,
+{
+    time_range(start, end, page_limit, |k: &KData| k.ts, fetch).await
+}
+
+// 按时间升序返回的任意记录, ts 取记录的时间, 例如资金费率的 fundingTime
+// 同一毫秒有多条记录且正好跨页时, 后一页的同毫秒记录会被当作重复丢弃
+pub async fn time_range<T, K, F, Fut>(start: u64, end: u64, page_limit: u64, ts: K, mut fetch: F) -> Result<Vec<T>>
+where
+    K: Fn(&T) -> u64,
+    F: FnMut(u64, u64, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let mut items: Vec<T> = Vec::new();
+    let mut cursor = start;
+    while cursor < end {
+        let page = fetch(cursor, end - 1, page_limit).await?;
+        let page_len = page.len() as u64;
+        for item in page {
+            let t = ts(&item);
+            let is_new = items.last().is_none_or(|last| t > ts(last));
+            if is_new && t >= start && t < end {
+                items.push(item);
+            }
+        }
+        match items.last().map(&ts) {
+            Some(last) if page_len >= page_limit && last >= cursor => cursor = last + 1,
+            _ => break,
+        }
+    }
+    Ok(items)
+}
+
+// 分页请求的条件, 交易所的 endTime 包含在内
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageQuery {
+    Time { start: u64, end: u64 },
+    // 从该id开始, 不能与时间参数同时使用
+    FromId(u64),
+}
+
+impl PageQuery {
+    pub fn apply(&self, params: &mut BTreeMap<String, String>) {
+        match *self {
+            PageQuery::Time { start, end } => {
+                params.insert("startTime".to_string(), start.to_string());
+                params.insert("endTime".to_string(), end.to_string());
+            }
+            PageQuery::FromId(id) => {
+                params.insert("fromId".to_string(), id.to_string());
+            }
+        }
+    }
+}
+
+enum Cursor {
+    // seen 为上一页最后一毫秒已经返回的记录id
+    Time { start: u64, seen: Vec<u64> },
+    FromId(u64),
+    Done,
+}
+
+// 成交/资金流水这类带 id 的记录, key 返回 (时间, id), 按时间升序
+// [start, end) 按 window 拆分, 例如现货 myTrades 每次最多查询24小时
+// 某个窗口一页装不下时, from_id 为 true 的接口改用 fromId 继续翻页直到 end,
+// 否则从该页最后一条的时间继续, 并按 id 去掉同一毫秒已经返回的记录
+pub fn id_range<T, K, F, Fut>(
+    start: u64,
+    end: u64,
+    window: u64,
+    page_limit: u64,
+    from_id: bool,
+    key: K,
+    fetch: F,
+) -> impl Stream<Item = Result<T>>
+where
+    K: Fn(&T) -> (u64, u64),
+    F: FnMut(PageQuery, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let init = (Cursor::Time { start, seen: Vec::new() }, key, fetch);
+    stream::try_unfold(init, move |(mut cursor, key, mut fetch)| async move {
+        loop {
+            match cursor {
+                Cursor::Done => return Ok::<_, EdpError>(None),
+                Cursor::Time { start: from, ref seen } => {
+                    if from >= end {
+                        return Ok(None);
+                    }
+                    let window_end = end.min(from.saturating_add(window));
+                    let page = fetch(PageQuery::Time { start: from, end: window_end - 1 }, page_limit).await?;
+                    let full = page.len() as u64 >= page_limit;
+                    let last = page.last().map(&key);
+                    // 改用 fromId 翻页时从该页最后一条继续, 该页中 window_end 之后的记录也要保留,
+                    // historicalTrades 这类按 fromId 查询的接口返回的页可能越过 window_end
+                    let next_from_id = full && from_id && last.is_some();
+                    let upper = if next_from_id { end } else { window_end };
+                    let past_end = page.iter().any(|t| key(t).0 >= end);
+                    let items: Vec<T> = page
+                        .into_iter()
+                        .filter(|t| {
+                            let (ts, id) = key(t);
+                            ts >= from && ts < upper && !(ts == from && seen.contains(&id))
+                        })
+                        .collect();
+                    cursor = match last {
+                        Some(_) if next_from_id && past_end => Cursor::Done,
+                        Some((_, id)) if next_from_id => Cursor::FromId(id + 1),
+                        Some((ts, _)) if full && ts > from => Cursor::Time {
+                            start: ts,
+                            seen: items.iter().map(&key).filter(|k| k.0 == ts).map(|k| k.1).collect(),
+                        },
+                        // 同一毫秒的记录超过一页, 只能跳过
+                        Some(_) if full => Cursor::Time { start: from + 1, seen: Vec::new() },
+                        _ => Cursor::Time { start: window_end, seen: Vec::new() },
+                    };
+                    if !items.is_empty() {
+                        return Ok(Some((items, (cursor, key, fetch))));
+                    }
+                }
+                Cursor::FromId(id) => {
+                    let page = fetch(PageQuery::FromId(id), page_limit).await?;
+                    let full = page.len() as u64 >= page_limit;
+                    let past_end = page.iter().any(|t| key(t).0 >= end);
+                    cursor = match page.last().map(&key) {
+                        Some((_, last)) if full && !past_end => Cursor::FromId(last + 1),
+                        _ => Cursor::Done,
+                    };
+                    let items: Vec<T> = page
+                        .into_iter()
+                        .filter(|t| (start..end).contains(&key(t).0))
+                        .collect();
+                    if !items.is_empty() {
+                        return Ok(Some((items, (cursor, key, fetch))));
+                    }
+                }
+            }
+        }
+    })
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn bar(ts: u64) -> KData {
+        KData {
+            ts,
+            open: 1.,
+            high: 1.,
+            low: 1.,
+            close: 1.,
+            vol: 1.,
+            turnover: 1.,
+        }
+    }
+
+    // 每分钟一根, 模拟交易所按 startTime/endTime/limit 返回
+    fn serve(start: u64, end: u64, limit: u64) -> Vec<KData> {
+        let first = start.div_ceil(60_000) * 60_000;
+        (0..limit)
+            .map(|i| first + i * 60_000)
+            .take_while(|ts| *ts <= end)
+            .map(bar)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_are_contiguous() {
+        let calls = Mutex::new(Vec::new());
+        let end = 3500 * 60_000;
+        let klines = klines_range(0, end, 1500, |s, e, l| {
+            calls.lock().unwrap().push(s);
+            async move { Ok(serve(s, e, l)) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(klines.len(), 3500);
+        assert!(klines.windows(2).all(|w| w[1].ts - w[0].ts == 60_000));
+        assert_eq!(klines.last().unwrap().ts, end - 60_000);
+        assert_eq!(*calls.lock().unwrap(), vec![0, 1499 * 60_000 + 1, 2999 * 60_000 + 1]);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_pages_are_deduplicated() {
+        // 交易所返回的页包含上一页的最后一根
+        let klines = klines_range(0, 10 * 60_000, 4, |s, e, l| async move {
+            let s = s.saturating_sub(60_000);
+            Ok(serve(s, e, l))
+        })
+        .await
+        .unwrap();
+        let ts: Vec<u64> = klines.iter().map(|k| k.ts / 60_000).collect();
+        assert_eq!(ts, (0..10).collect::<Vec<u64>>());
+    }
+
+    #[tokio::test]
+    async fn test_empty_range() {
+        let klines = klines_range(100, 100, 1500, |_, _, _| async { Ok(vec![bar(0)]) })
+            .await
+            .unwrap();
+        assert!(klines.is_empty());
+    }
+
+    // (时间, id) 记录, 模拟交易所按 startTime/endTime 或 fromId 返回
+    fn serve_records(records: &[(u64, u64)], query: PageQuery, limit: u64) -> Vec<(u64, u64)> {
+        records
+            .iter()
+            .filter(|(ts, id)| match query {
+                PageQuery::Time { start, end } => *ts >= start && *ts <= end,
+                PageQuery::FromId(from) => *id >= from,
+            })
+            .take(limit as usize)
+            .copied()
+            .collect()
+    }
+
+    async fn collect_records(
+        records: &[(u64, u64)],
+        start: u64,
+        end: u64,
+        window: u64,
+        limit: u64,
+        from_id: bool,
+    ) -> (Vec<(u64, u64)>, Vec<PageQuery>) {
+        let queries = Mutex::new(Vec::new());
+        let items: Vec<(u64, u64)> = id_range(start, end, window, limit, from_id, |r: &(u64, u64)| *r, |q, l| {
+            queries.lock().unwrap().push(q);
+            let page = serve_records(records, q, l);
+            async move { Ok(page) }
+        })
+        .try_collect()
+        .await
+        .unwrap();
+        (items, queries.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_id_range_switches_to_from_id() {
+        // 每分钟一笔, 共3天, 每个窗口1天
+        let day = 86_400_000;
+        let records: Vec<(u64, u64)> = (0..3 * 1440).map(|i| (i * 60_000, i)).collect();
+        let (items, queries) = collect_records(&records, 0, 3 * day, day, 1000, true).await;
+        assert_eq!(items, records);
+        assert_eq!(queries[0], PageQuery::Time { start: 0, end: day - 1 });
+        assert_eq!(queries[1], PageQuery::FromId(1000));
+        assert!(queries[1..].iter().all(|q| matches!(q, PageQuery::FromId(_))));
+
+        // 只取中间一段, fromId 翻页越过 end 后停止
+        let (items, queries) = collect_records(&records, day, day + 1500 * 60_000, day, 1000, true).await;
+        assert_eq!(items, records[1440..1440 + 1500].to_vec());
+        assert_eq!(queries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_id_range_by_time() {
+        // 每毫秒3条记录, 页大小4, 同一毫秒的记录跨页
+        let records: Vec<(u64, u64)> = (0..30).map(|i| (100 + i / 3, i)).collect();
+        let (items, _) = collect_records(&records, 0, 200, 1000, 4, false).await;
+        assert_eq!(items, records);
+
+        // 空窗口被跳过
+        let records = vec![(2500, 1), (2600, 2)];
+        let (items, queries) = collect_records(&records, 0, 3000, 1000, 10, false).await;
+        assert_eq!(items, records);
+        assert_eq!(queries.len(), 3);
+        assert_eq!(queries[2], PageQuery::Time { start: 2000, end: 2999 });
+    }
+
+    #[tokio::test]
+    async fn test_id_range_from_id_page_past_window_end() {
+        // 窗口内第一笔在窗口结束前1秒, 之后每毫秒一笔, 按时间查询时返回从第一笔开始的一整页, 不受窗口结束时间限制
+        let hour = 3_600_000;
+        let mut records = vec![(hour - 1000, 0)];
+        records.extend((1..2500).map(|i| (hour + i, i)));
+        let queries = Mutex::new(Vec::new());
+        let items: Vec<(u64, u64)> = id_range(0, 2 * hour, hour, 1000, true, |r: &(u64, u64)| *r, |q, l| {
+            queries.lock().unwrap().push(q);
+            let page: Vec<(u64, u64)> = match q {
+                PageQuery::Time { start, .. } => records.iter().filter(|r| r.0 >= start).take(l as usize).copied().collect(),
+                PageQuery::FromId(_) => serve_records(&records, q, l),
+            };
+            async move { Ok(page) }
+        })
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(items, records);
+        let queries = queries.into_inner().unwrap();
+        assert_eq!(queries[1], PageQuery::FromId(1000));
+        assert_eq!(queries.len(), 3);
+    }
+}
@@ -1,28 +1,230 @@
-use anyhow::{anyhow, format_err};
+use crate::error::{ApiErrorCode, EdpError, Result};
 use hex::encode as hex_encode;
-use reqwest::{Client, StatusCode};
+use crate::model::RateLimit;
+use crate::rest::clock::ServerClock;
+use crate::rest::limiter::{endpoint_cost, EndpointCost, RateLimiter};
+use crate::rest::retry::{classify_error, classify_status, is_idempotent, RespClass, RetryPolicy};
+use reqwest::{Client, Method, Proxy, StatusCode};
 use ring::hmac;
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::prelude::*;
+use serde::Deserialize;
+use url::Url;
+
+// 连接池配置, 一个RestClient只构建一次reqwest::Client
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub connect_timeout: Duration,
+    // 整个请求(含读取响应)的超时
+    pub timeout: Duration,
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+    pub tcp_keepalive: Option<Duration>,
+    pub tcp_nodelay: bool,
+    // 例如 http://127.0.0.1:7890 或 socks5://...
+    pub proxy: Option<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 32,
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            tcp_nodelay: true,
+            proxy: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    // TLS下通过ALPN协商HTTP/2, 服务端不支持时回落到HTTP/1.1 keep-alive
+    pub fn build(&self) -> Result<Client> {
+        let mut builder = Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive)
+            .tcp_nodelay(self.tcp_nodelay)
+            .http2_adaptive_window(true);
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        Ok(builder.build()?)
+    }
+}
 
 #[derive(Clone)]
 pub struct RestClient {
     base_url: String,
     keys: Option<(String, String)>,
+    // 内部是Arc, clone后共享同一个连接池
+    client: Client,
+    // 多个客户端可共享同一个限频器
+    limiter: Option<Arc<RateLimiter>>,
+    retry: RetryPolicy,
+    // 配置后签名请求的timestamp按服务器时间校正
+    clock: Option<Arc<ServerClock>>,
+    recv_window: Option<u64>,
+    // 限频器没有额度时, 第一次请求前从该 exchangeInfo 地址读取 rateLimits
+    rate_limits_end_point: Option<String>,
 }
 
 impl RestClient {
     pub fn new(base_url: String) -> Self {
-        Self {
-            base_url: base_url,
-            keys: None,
-        }
+        Self::with_config(base_url, None, &ClientConfig::default())
+            .expect("default http client config is valid")
     }
 
     pub fn with_key(base_url: String, keys: (String, String)) -> Self {
-        Self {
-            base_url: base_url,
-            keys: Some(keys),
+        Self::with_config(base_url, Some(keys), &ClientConfig::default())
+            .expect("default http client config is valid")
+    }
+
+    pub fn with_config(
+        base_url: String,
+        keys: Option<(String, String)>,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        Ok(Self {
+            base_url,
+            keys,
+            client: config.build()?,
+            limiter: None,
+            retry: RetryPolicy::default(),
+            clock: None,
+            recv_window: None,
+            rate_limits_end_point: None,
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    // end_point: 现货 /api/v3/exchangeInfo, U本位 /fapi/v1/exchangeInfo
+    pub fn with_rate_limits(mut self, end_point: &str) -> Self {
+        self.rate_limits_end_point = Some(end_point.to_string());
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // end_point: 现货 /api/v3/time, U本位 /fapi/v1/time
+    pub fn with_time_sync(self, end_point: &str) -> Self {
+        self.with_clock(Arc::new(ServerClock::new(end_point)))
+    }
+
+    pub fn with_clock(mut self, clock: Arc<ServerClock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn clock(&self) -> Option<&Arc<ServerClock>> {
+        self.clock.as_ref()
+    }
+
+    // 签名请求未指定recvWindow时使用
+    pub fn with_recv_window(mut self, recv_window: u64) -> Self {
+        self.recv_window = Some(recv_window);
+        self
+    }
+
+    pub fn timestamp_ms(&self) -> i64 {
+        match self.clock {
+            Some(ref clock) => clock.now_ms(),
+            None => Utc::now().timestamp_millis(),
+        }
+    }
+
+    // 采样服务器时间并更新时钟偏差
+    pub async fn sync_time(&self) -> Result<()> {
+        let clock = match self.clock {
+            Some(ref clock) => clock,
+            None => return Ok(()),
+        };
+        let url = format!("{}{}", self.base_url, clock.end_point());
+        let mut samples = Vec::with_capacity(clock.samples);
+        for _ in 0..clock.samples.max(1) {
+            let sent = Utc::now().timestamp_millis();
+            let resp = self.client.get(&url).send().await?;
+            let resp = self.resp2string(resp).await?;
+            let recv = Utc::now().timestamp_millis();
+            let server_time: ServerTime = serde_json::from_str(&resp)?;
+            samples.push((sent, recv, server_time.server_time));
         }
+        clock.update(&samples);
+        Ok(())
+    }
+
+    // 用 exchangeInfo 的 rateLimits 设置限频器额度, 不经过限频器
+    pub async fn init_rate_limits(&self) -> Result<()> {
+        let (limiter, end_point) = match (&self.limiter, &self.rate_limits_end_point) {
+            (Some(limiter), Some(end_point)) => (limiter, end_point),
+            _ => return Ok(()),
+        };
+        let resp = self.client.get(format!("{}{}", self.base_url, end_point)).send().await?;
+        let resp = self.resp2string(resp).await?;
+        let info: ExchangeRateLimits = serde_json::from_str(&resp)?;
+        limiter.set_limits(&info.rate_limits);
+        Ok(())
+    }
+
+    // 后台定期同步, 返回的JoinHandle可用于abort
+    pub fn spawn_time_sync(&self) -> tokio::task::JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let interval = match client.clock {
+                Some(ref clock) => clock.refresh_interval,
+                None => return,
+            };
+            loop {
+                if let Err(err) = client.sync_time().await {
+                    log::warn!("sync server time failed: {}", err);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    fn sign(&self, payload: &str) -> String {
+        let signed_key = hmac::Key::new(hmac::HMAC_SHA256, self.keys.as_ref().unwrap().1.as_bytes());
+        hex_encode(hmac::sign(&signed_key, payload.as_bytes()))
+    }
+
+    // 用当前校正后的时间替换timestamp并重新签名
+    fn restamp(&self, url: &str) -> String {
+        let (base, query) = match url.split_once('?') {
+            Some(parts) => parts,
+            None => return url.to_string(),
+        };
+        let ts = self.timestamp_ms();
+        let payload: Vec<String> = query
+            .split('&')
+            .filter(|kv| !kv.starts_with("signature="))
+            .map(|kv| {
+                if kv.starts_with("timestamp=") {
+                    format!("timestamp={}", ts)
+                } else {
+                    kv.to_string()
+                }
+            })
+            .collect();
+        let payload = payload.join("&");
+        format!("{}?{}&signature={}", base, payload, self.sign(&payload))
     }
 
     pub fn build_request_string(
@@ -30,78 +232,463 @@ impl RestClient {
         end_point: &str,
         params: BTreeMap<String, String>,
         need_sign: bool,
-    ) -> anyhow::Result<String> {
-        if let None = self.keys {
-            return Err(format_err!("{}", "KEYS not set"));
+    ) -> Result<String> {
+        if need_sign && self.keys.is_none() {
+            return Err(EdpError::MissingCredentials);
         }
+        let mut params = params;
+        if need_sign {
+            if !params.contains_key("timestamp") {
+                params.insert("timestamp".to_string(), self.timestamp_ms().to_string());
+            }
+            if let Some(recv_window) = self.recv_window {
+                params.entry("recvWindow".to_string()).or_insert_with(|| recv_window.to_string());
+            }
+        }
+        // 签名按编码后的字符串计算, batchOrders 这类 JSON 参数必须先编码
         let mut params_string = String::new();
         for (k, v) in &params {
+            let v: String = url::form_urlencoded::byte_serialize(v.as_bytes()).collect();
             params_string.push_str(&format!("&{}={}", k, v));
         }
-        params_string.remove(0);
+        if !params_string.is_empty() {
+            params_string.remove(0);
+        }
         if need_sign {
-            let signed_key =
-                hmac::Key::new(hmac::HMAC_SHA256, self.keys.as_ref().unwrap().1.as_bytes());
-            let signature = hex_encode(hmac::sign(&signed_key, params_string.as_bytes().as_ref()));
-            params_string.push_str(&format!("&signature={}", signature));
+            let signature = self.sign(&params_string);
+            if params_string.is_empty() {
+                params_string = format!("signature={}", signature);
+            } else {
+                params_string.push_str(&format!("&signature={}", signature));
+            }
+        }
+        if params_string.is_empty() {
+            Ok(format!("{}{}", self.base_url, end_point))
         } else {
+            Ok(format!("{}{}?{}", self.base_url, end_point, params_string))
         }
-        Ok(format!("{}{}?{}", self.base_url, end_point, params_string))
     }
 
-    pub async fn resp2string(&self, resp: reqwest::Response) -> anyhow::Result<String> {
-        match resp.status() {
-            StatusCode::OK => {
-                let resp_string = resp.text().await?;
-                Ok(resp_string)
+    pub async fn resp2string(&self, resp: reqwest::Response) -> Result<String> {
+        let status = resp.status();
+        let resp_string = resp.text().await?;
+        if status == StatusCode::OK {
+            Ok(resp_string)
+        } else {
+            Err(EdpError::from_response(status, &resp_string))
+        }
+    }
+
+    pub async fn post_sign(&self, url: String) -> Result<String> {
+        self.send(Method::POST, url, true).await
+    }
+
+    pub async fn delete_sign(&self, url: String) -> Result<String> {
+        self.send(Method::DELETE, url, true).await
+    }
+
+    pub async fn get_sign(&self, url: String) -> Result<String> {
+        self.send(Method::GET, url, true).await
+    }
+
+    pub async fn get(&self, url: String) -> Result<String> {
+        self.send(Method::GET, url, false).await
+    }
+
+    // 只需要 X-MBX-APIKEY 不需要签名的接口, 如 listenKey, historicalTrades
+    pub async fn get_key(&self, url: String) -> Result<String> {
+        self.send(Method::GET, url, true).await
+    }
+
+    pub async fn post_key(&self, url: String) -> Result<String> {
+        self.send(Method::POST, url, true).await
+    }
+
+    pub async fn put_key(&self, url: String) -> Result<String> {
+        self.send(Method::PUT, url, true).await
+    }
+
+    pub async fn delete_key(&self, url: String) -> Result<String> {
+        self.send(Method::DELETE, url, true).await
+    }
+
+    async fn send(&self, method: Method, url: String, with_key: bool) -> Result<String> {
+        let signed = with_key && url.contains("signature=");
+        let clock = match self.clock {
+            Some(ref clock) if signed => clock,
+            _ => return self.send_with_retry(&method, &url, with_key).await,
+        };
+        let mut url = url;
+        if clock.needs_sync() {
+            match self.sync_time().await {
+                Ok(()) => url = self.restamp(&url),
+                Err(err) => log::warn!("sync server time failed: {}", err),
+            }
+        }
+        match self.send_with_retry(&method, &url, with_key).await {
+            // 时间戳超出recvWindow时, 请求未被执行, 重新同步后重试一次
+            Err(err) if err.api_code() == Some(ApiErrorCode::InvalidTimestamp) => {
+                log::warn!("{}, resync server time and retry", err);
+                self.sync_time().await?;
+                self.send_with_retry(&method, &self.restamp(&url), with_key).await
             }
-            StatusCode::BAD_REQUEST => Err(anyhow!("{}\n{:#?}", "BAD REQUEST", resp.text().await)),
-            StatusCode::INTERNAL_SERVER_ERROR => Err(anyhow!("{}", "INTERNAL SERVER ERROR")),
-            _ => Err(format_err!("{}", "Unknown Error")),
+            other => other,
         }
     }
 
-    pub async fn post_sign(&self, url: String) -> anyhow::Result<String> {
-        if let Some((ref ak, _)) = self.keys {
-            let client = Client::new();
-            let resp = client.post(&url).header("X-MBX-APIKEY", ak).send().await?;
-            let re = self.resp2string(resp).await?;
-            Ok(re)
-        } else {
-            Err(format_err!("{}", "KEYS not config"))
+    async fn send_with_retry(&self, method: &Method, url: &str, with_key: bool) -> Result<String> {
+        let api_key = match (with_key, &self.keys) {
+            (false, _) => None,
+            (true, Some((ak, _))) => Some(ak.clone()),
+            (true, None) => return Err(EdpError::MissingCredentials),
+        };
+        if self.limiter.as_ref().is_some_and(|l| !l.has_limits()) && self.rate_limits_end_point.is_some() {
+            if let Err(err) = self.init_rate_limits().await {
+                log::warn!("load rate limits failed: {}", err);
+            }
+        }
+        let cost = match Url::parse(url) {
+            Ok(u) => endpoint_cost(method, u.path(), u.query().unwrap_or_default()),
+            Err(_) => EndpointCost { weight: 1, orders: 0 },
+        };
+        let can_retry = is_idempotent(method, url);
+        let mut attempt = 0;
+        loop {
+            if let Some(ref limiter) = self.limiter {
+                limiter.acquire(cost).await?;
+            }
+            let mut req = self.client.request(method.clone(), url);
+            if let Some(ref ak) = api_key {
+                req = req.header("X-MBX-APIKEY", ak);
+            }
+            let result = req.send().await;
+            let class = match &result {
+                Ok(resp) => {
+                    if let Some(ref limiter) = self.limiter {
+                        limiter.sync(resp.status(), resp.headers());
+                    }
+                    classify_status(resp.status(), resp.headers())
+                }
+                Err(err) => classify_error(err),
+            };
+            if let RespClass::Retryable(after) = class {
+                let delay = after.unwrap_or_else(|| self.retry.backoff(attempt));
+                if can_retry && attempt < self.retry.max_retries && delay <= self.retry.max_delay {
+                    attempt += 1;
+                    log::warn!(
+                        "{} {} failed, retry {}/{} in {:?}",
+                        method,
+                        url.split('?').next().unwrap_or_default(),
+                        attempt,
+                        self.retry.max_retries,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            }
+            return self.resp2string(result?).await;
         }
     }
+}
 
-    pub async fn delete_sign(&self, url: String) -> anyhow::Result<String> {
-        if let Some((ref ak, _)) = self.keys {
-            let client = Client::new();
-            let resp = client
-                .delete(&url)
-                .header("X-MBX-APIKEY", ak)
-                .send()
-                .await?;
-            let re = self.resp2string(resp).await?;
-            Ok(re)
-        } else {
-            Err(format_err!("{}", "KEYS not config"))
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerTime {
+    server_time: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExchangeRateLimits {
+    rate_limits: Vec<RateLimit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::limiter::{LimitKind, LimitPolicy};
+    use crate::rest::stub::{StubResponse, StubServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_api_error_is_typed() {
+        let server = StubServer::start(|_| {
+            StubResponse::with_status(429, r#"{"code":-1003,"msg":"Too many requests."}"#)
+        })
+        .await;
+        let client = RestClient::new(server.url()).with_retry(RetryPolicy::none());
+        let err = client.get(format!("{}/api/v3/ping", server.url())).await.unwrap_err();
+        assert_eq!(err.api_code(), Some(ApiErrorCode::TooManyRequests));
+        assert_eq!(err.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    #[tokio::test]
+    async fn test_sign_without_keys() {
+        let client = RestClient::new("http://127.0.0.1:1".to_string());
+        let err = client.get_sign("http://127.0.0.1:1/api/v3/account".to_string()).await.unwrap_err();
+        assert!(matches!(err, EdpError::MissingCredentials));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_resyncs_from_headers() {
+        let server = StubServer::start(|_| {
+            StubResponse::ok("{}")
+                .header("X-MBX-USED-WEIGHT-1M", "1195")
+                .header("X-MBX-ORDER-COUNT-10S", "3")
+        })
+        .await;
+        let limits = vec![
+            RateLimit {
+                rate_limit_type: "REQUEST_WEIGHT".to_string(),
+                interval: "MINUTE".to_string(),
+                interval_num: 1,
+                limit: 1200,
+            },
+            RateLimit {
+                rate_limit_type: "ORDERS".to_string(),
+                interval: "SECOND".to_string(),
+                interval_num: 10,
+                limit: 50,
+            },
+        ];
+        let limiter = Arc::new(RateLimiter::from_rate_limits(&limits, LimitPolicy::Reject));
+        let client = RestClient::new(server.url()).with_limiter(limiter.clone());
+        // 共享同一个限频器的另一个客户端
+        let other = RestClient::new(server.url()).with_limiter(limiter.clone());
+
+        client.get(format!("{}/api/v3/ping", server.url())).await.unwrap();
+        assert_eq!(limiter.used(LimitKind::RequestWeight), vec![1195]);
+        assert_eq!(limiter.used(LimitKind::Orders), vec![3]);
+
+        // exchangeInfo 权重为10, 超出剩余额度, 请求不会发出
+        let err = other
+            .get(format!("{}/api/v3/exchangeInfo", server.url()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EdpError::RateLimited { .. }));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_loaded_lazily() {
+        let server = StubServer::start(|req| match req.path.as_str() {
+            "/api/v3/exchangeInfo" => StubResponse::ok(
+                r#"{"timezone":"UTC","rateLimits":[{"rateLimitType":"REQUEST_WEIGHT","interval":"MINUTE","intervalNum":1,"limit":6000},{"rateLimitType":"ORDERS","interval":"SECOND","intervalNum":10,"limit":100}],"symbols":[]}"#,
+            ),
+            _ => StubResponse::ok("{}"),
+        })
+        .await;
+        let limiter = Arc::new(RateLimiter::new(LimitPolicy::Reject));
+        let client = RestClient::new(server.url())
+            .with_limiter(limiter.clone())
+            .with_rate_limits("/api/v3/exchangeInfo");
+        assert!(!limiter.has_limits());
+        client.get(format!("{}/api/v3/ping", server.url())).await.unwrap();
+        client.get(format!("{}/api/v3/ping", server.url())).await.unwrap();
+        assert!(limiter.has_limits());
+        assert_eq!(limiter.used(LimitKind::RequestWeight), vec![2]);
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, vec!["/api/v3/exchangeInfo", "/api/v3/ping", "/api/v3/ping"]);
+    }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_secs(2),
         }
     }
 
-    pub async fn get_sign(&self, url: String) -> anyhow::Result<String> {
-        if let Some((ref ak, _)) = self.keys {
-            let client = Client::new();
-            let resp = client.get(&url).header("X-MBX-APIKEY", ak).send().await?;
-            let re = self.resp2string(resp).await?;
-            Ok(re)
-        } else {
-            Err(format_err!("{}", "KEYS not config"))
+    #[tokio::test]
+    async fn test_retry_on_server_error() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let server = StubServer::start(move |_| match c.fetch_add(1, Ordering::SeqCst) {
+            0 => StubResponse::with_status(502, "bad gateway"),
+            1 => StubResponse::reset(),
+            _ => StubResponse::ok("{}"),
+        })
+        .await;
+        let client = RestClient::new(server.url()).with_retry(fast_retry());
+        let resp = client.get(format!("{}/api/v3/ping", server.url())).await.unwrap();
+        assert_eq!(resp, "{}");
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_client_error() {
+        let server = StubServer::start(|_| {
+            StubResponse::with_status(400, r#"{"code":-1102,"msg":"Mandatory parameter missing"}"#)
+        })
+        .await;
+        let client = RestClient::new(server.url()).with_retry(fast_retry());
+        assert!(client.get(format!("{}/api/v3/depth", server.url())).await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_order_retry_needs_client_order_id() {
+        let server = StubServer::start(|_| StubResponse::with_status(503, "unavailable")).await;
+        let client = RestClient::with_key(server.url(), ("ak".to_string(), "sk".to_string()))
+            .with_retry(fast_retry());
+
+        let url = format!("{}/api/v3/order?symbol=BTCUSDT&side=BUY", server.url());
+        assert!(client.post_sign(url).await.is_err());
+        assert_eq!(server.requests().len(), 1);
+
+        let url = format!("{}/api/v3/order?symbol=BTCUSDT&side=BUY&newClientOrderId=abc", server.url());
+        assert!(client.post_sign(url).await.is_err());
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_is_honoured() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let server = StubServer::start(move |_| match c.fetch_add(1, Ordering::SeqCst) {
+            0 => StubResponse::with_status(429, r#"{"code":-1003,"msg":"Too many requests."}"#)
+                .header("Retry-After", "1"),
+            _ => StubResponse::ok("{}"),
+        })
+        .await;
+        let client = RestClient::new(server.url()).with_retry(fast_retry());
+        let start = Instant::now();
+        client.get(format!("{}/api/v3/ping", server.url())).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        // Retry-After 超过 max_delay 时直接返回错误
+        let server = StubServer::start(|_| {
+            StubResponse::with_status(418, r#"{"code":-1003,"msg":"banned"}"#).header("Retry-After", "600")
+        })
+        .await;
+        let client = RestClient::new(server.url()).with_retry(fast_retry());
+        let err = client.get(format!("{}/api/v3/ping", server.url())).await.unwrap_err();
+        assert_eq!(err.api_code(), Some(ApiErrorCode::TooManyRequests));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_params_are_url_encoded() {
+        let client = RestClient::with_key("http://127.0.0.1:1".to_string(), ("ak".to_string(), "sk".to_string()));
+        let mut params = BTreeMap::new();
+        params.insert("batchOrders".to_string(), r#"[{"symbol":"BTCUSDT","price":"1.5"}]"#.to_string());
+        params.insert("timestamp".to_string(), "1".to_string());
+        let url = client.build_request_string("/fapi/v1/batchOrders", params, true).unwrap();
+        let query = url.split_once('?').unwrap().1;
+        let (payload, signature) = query.rsplit_once("&signature=").unwrap();
+        assert_eq!(
+            payload,
+            "batchOrders=%5B%7B%22symbol%22%3A%22BTCUSDT%22%2C%22price%22%3A%221.5%22%7D%5D&timestamp=1"
+        );
+        assert_eq!(signature, client.sign(payload));
+        let decoded = url::form_urlencoded::parse(query.as_bytes()).next().unwrap().1;
+        assert_eq!(decoded, r#"[{"symbol":"BTCUSDT","price":"1.5"}]"#);
+    }
+
+    #[tokio::test]
+    async fn test_connection_is_reused() {
+        let server = StubServer::start(|_| StubResponse::ok("{}")).await;
+        let client = RestClient::new(server.url());
+        let cloned = client.clone();
+        for _ in 0..5 {
+            client.get(format!("{}/api/v3/ping", server.url())).await.unwrap();
         }
+        cloned.get(format!("{}/api/v3/exchangeInfo", server.url())).await.unwrap();
+        assert_eq!(server.requests().len(), 6);
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[test]
+    fn test_build_request_string_without_params() {
+        let client = RestClient::new("https://api.binance.com".to_string());
+        let url = client.build_request_string("/api/v3/ping", BTreeMap::new(), false).unwrap();
+        assert_eq!(url, "https://api.binance.com/api/v3/ping");
+        assert!(client.build_request_string("/api/v3/account", BTreeMap::new(), true).is_err());
+    }
+
+    #[test]
+    fn test_invalid_proxy() {
+        let config = ClientConfig {
+            proxy: Some("not a proxy url".to_string()),
+            ..ClientConfig::default()
+        };
+        let result = RestClient::with_config("https://api.binance.com".to_string(), None, &config);
+        assert!(matches!(result, Err(EdpError::Transport(_))));
+    }
+
+    fn param(url: &str, key: &str) -> Option<String> {
+        Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_sync_time_offset() {
+        let server = StubServer::start(|_| StubResponse::ok("{}")).await;
+        server.set_time_offset(5_000);
+        let client = RestClient::with_key(server.url(), ("ak".to_string(), "sk".to_string()))
+            .with_time_sync("/api/v3/time")
+            .with_recv_window(3000);
+        client.sync_time().await.unwrap();
+        let clock = client.clock().unwrap();
+        assert!((clock.offset_ms() - 5_000).abs() < 200, "offset {}", clock.offset_ms());
+        assert_eq!(server.time_requests(), 3);
+
+        let url = client.build_request_string("/api/v3/account", BTreeMap::new(), true).unwrap();
+        let ts: i64 = param(&url, "timestamp").unwrap().parse().unwrap();
+        assert!((ts - Utc::now().timestamp_millis() - 5_000).abs() < 200);
+        assert_eq!(param(&url, "recvWindow").as_deref(), Some("3000"));
+    }
+
+    #[tokio::test]
+    async fn test_signed_request_syncs_lazily() {
+        let server = StubServer::start(|_| StubResponse::ok("{}")).await;
+        server.set_time_offset(-60_000);
+        let client = RestClient::with_key(server.url(), ("ak".to_string(), "sk".to_string()))
+            .with_time_sync("/api/v3/time");
+        // 构建url时尚未同步, 发送前同步并重新签名
+        let url = client.build_request_string("/api/v3/account", BTreeMap::new(), true).unwrap();
+        client.get_sign(url).await.unwrap();
+        let req = &server.requests()[0];
+        let ts: i64 = req.param("timestamp").unwrap().parse().unwrap();
+        assert!((ts - Utc::now().timestamp_millis() + 60_000).abs() < 200);
+        let (payload, signature) = req.query.split_at(req.query.find("&signature=").unwrap());
+        assert_eq!(&signature["&signature=".len()..], client.sign(payload));
+    }
+
+    #[tokio::test]
+    async fn test_resync_on_invalid_timestamp() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let server = StubServer::start(move |_| match c.fetch_add(1, Ordering::SeqCst) {
+            0 => StubResponse::with_status(400, r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#),
+            _ => StubResponse::ok("{}"),
+        })
+        .await;
+        let client = RestClient::with_key(server.url(), ("ak".to_string(), "sk".to_string()))
+            .with_time_sync("/api/v3/time");
+        client.sync_time().await.unwrap();
+        let url = client.build_request_string("/api/v3/account", BTreeMap::new(), true).unwrap();
+        client.get_sign(url).await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(server.time_requests(), 6);
     }
 
-    pub async fn get(&self, url: String) -> anyhow::Result<String> {
-        let client = Client::new();
-        let resp = client.get(&url).send().await?;
-        let re = self.resp2string(resp).await?;
-        Ok(re)
+    #[tokio::test]
+    async fn test_invalid_timestamp_retried_once() {
+        let server = StubServer::start(|_| {
+            StubResponse::with_status(400, r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#)
+        })
+        .await;
+        let client = RestClient::with_key(server.url(), ("ak".to_string(), "sk".to_string()))
+            .with_time_sync("/fapi/v1/time");
+        let url = client.build_request_string("/fapi/v1/order", BTreeMap::new(), true).unwrap();
+        let err = client.post_sign(url).await.unwrap_err();
+        assert_eq!(err.api_code(), Some(ApiErrorCode::InvalidTimestamp));
+        assert_eq!(server.requests().len(), 2);
     }
 }
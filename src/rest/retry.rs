@@ -0,0 +1,139 @@
+use crate::rest::limiter::endpoint_cost;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // 不含第一次请求
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    // 超过该等待时间的Retry-After不再重试, 直接返回错误
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    // 指数退避 + full jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .checked_mul(1u32 << attempt.min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let millis = exp.as_millis() as u64;
+        if millis == 0 {
+            return exp;
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(millis / 2..=millis))
+    }
+}
+
+// 对一次请求结果的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RespClass {
+    Success,
+    // 可以重试, 交易所给出Retry-After时带上等待时间
+    Retryable(Option<Duration>),
+    Fatal,
+}
+
+pub fn classify_status(status: StatusCode, headers: &HeaderMap) -> RespClass {
+    if status.is_success() {
+        return RespClass::Success;
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS || status.as_u16() == 418 {
+        let after = headers
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return RespClass::Retryable(after);
+    }
+    if status.is_server_error() {
+        return RespClass::Retryable(None);
+    }
+    RespClass::Fatal
+}
+
+pub fn classify_error(err: &reqwest::Error) -> RespClass {
+    // 连接失败, 超时, 连接被重置
+    if err.is_connect() || err.is_timeout() || err.is_request() {
+        RespClass::Retryable(None)
+    } else {
+        RespClass::Fatal
+    }
+}
+
+// 下单请求只有带了 newClientOrderId 才能安全重试, 否则可能重复下单
+pub fn is_idempotent(method: &Method, url: &str) -> bool {
+    let url = match Url::parse(url) {
+        Ok(u) => u,
+        Err(_) => return false,
+    };
+    let query = url.query().unwrap_or_default();
+    if endpoint_cost(method, url.path(), query).orders == 0 {
+        return true;
+    }
+    url.query_pairs().any(|(k, v)| k == "newClientOrderId" && !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_classify_status() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(classify_status(StatusCode::OK, &headers), RespClass::Success);
+        assert_eq!(classify_status(StatusCode::BAD_REQUEST, &headers), RespClass::Fatal);
+        assert_eq!(classify_status(StatusCode::BAD_GATEWAY, &headers), RespClass::Retryable(None));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(
+            classify_status(StatusCode::from_u16(418).unwrap(), &headers),
+            RespClass::Retryable(Some(Duration::from_secs(7)))
+        );
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        let base = "https://api.binance.com/api/v3/order?symbol=BTCUSDT&side=BUY";
+        assert!(!is_idempotent(&Method::POST, base));
+        assert!(is_idempotent(&Method::POST, &format!("{}&newClientOrderId=abc", base)));
+        assert!(is_idempotent(&Method::DELETE, base));
+        assert!(is_idempotent(&Method::GET, "https://api.binance.com/api/v3/depth?symbol=BTCUSDT"));
+    }
+
+    #[test]
+    fn test_backoff_bounds() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        for attempt in 0..8 {
+            let d = policy.backoff(attempt);
+            let cap = (100u64 << attempt).min(1000);
+            assert!(d >= Duration::from_millis(cap / 2) && d <= Duration::from_millis(cap));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// 测试用的本地HTTP桩服务, 支持keep-alive
+// */time 接口由桩服务自己应答(可设置时钟偏差), 不经过handler也不计入requests
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StubRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl StubRequest {
+    pub fn param(&self, key: &str) -> Option<String> {
+        url::form_urlencoded::parse(self.query.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    // 不返回任何内容直接断开连接
+    pub reset: bool,
+}
+
+impl StubResponse {
+    pub fn ok(body: &str) -> Self {
+        Self::with_status(200, body)
+    }
+
+    pub fn with_status(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+            reset: false,
+        }
+    }
+
+    pub fn reset() -> Self {
+        Self {
+            reset: true,
+            ..Self::with_status(0, "")
+        }
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+type Handler = dyn Fn(&StubRequest) -> StubResponse + Send + Sync;
+
+pub(crate) struct StubServer {
+    pub addr: SocketAddr,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+    connections: Arc<AtomicUsize>,
+    time: Arc<StubTime>,
+}
+
+#[derive(Default)]
+struct StubTime {
+    offset_ms: AtomicI64,
+    requests: AtomicUsize,
+}
+
+impl StubServer {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&StubRequest) -> StubResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        let connections = Arc::new(AtomicUsize::new(0));
+        let time = Arc::new(StubTime::default());
+        let reqs = requests.clone();
+        let conns = connections.clone();
+        let t = time.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                conns.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(serve(socket, handler.clone(), reqs.clone(), t.clone()));
+            }
+        });
+        Self {
+            addr,
+            requests,
+            connections,
+            time,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    // 服务器时间 = 本地时间 + offset
+    pub fn set_time_offset(&self, offset_ms: i64) {
+        self.time.offset_ms.store(offset_ms, Ordering::SeqCst);
+    }
+
+    pub fn time_requests(&self) -> usize {
+        self.time.requests.load(Ordering::SeqCst)
+    }
+}
+
+async fn serve(
+    mut socket: TcpStream,
+    handler: Arc<Handler>,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+    time: Arc<StubTime>,
+) {
+    let mut buf: Vec<u8> = Vec::new();
+    loop {
+        let header_end = loop {
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            let mut chunk = [0u8; 4096];
+            match socket.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let method = request_line.next().unwrap_or_default().to_string();
+        let target = request_line.next().unwrap_or_default().to_string();
+        let (path, query) = match target.find('?') {
+            Some(i) => (target[..i].to_string(), target[i + 1..].to_string()),
+            None => (target, String::new()),
+        };
+        let headers: HashMap<String, String> = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+            .collect();
+        let content_length: usize = headers
+            .get("content-length")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        while buf.len() < header_end + content_length {
+            let mut chunk = [0u8; 4096];
+            match socket.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+        buf.drain(..header_end + content_length);
+
+        let req = StubRequest {
+            method,
+            path,
+            query,
+            headers,
+        };
+        let resp = if req.path.ends_with("/time") {
+            time.requests.fetch_add(1, Ordering::SeqCst);
+            let now = Utc::now().timestamp_millis() + time.offset_ms.load(Ordering::SeqCst);
+            StubResponse::ok(&format!(r#"{{"serverTime":{}}}"#, now))
+        } else {
+            requests.lock().unwrap().push(req.clone());
+            handler(&req)
+        };
+        if resp.reset {
+            return;
+        }
+        let mut out = format!(
+            "HTTP/1.1 {} STUB\r\ncontent-type: application/json\r\ncontent-length: {}\r\n",
+            resp.status,
+            resp.body.len()
+        );
+        for (k, v) in &resp.headers {
+            out.push_str(&format!("{}: {}\r\n", k, v));
+        }
+        out.push_str("\r\n");
+        out.push_str(&resp.body);
+        if socket.write_all(out.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
@@ -1,41 +1,120 @@
-use crate::model::{OrderResp, Ticker, OrderBook};
-use anyhow::Result;
+use crate::model::{Balance, KData, OpenInterest, Order, OrderBook, OrderResp, RateLimit, SymbolInfo, Ticker, Trade};
+use crate::error::Result;
+use crate::order::OrderRequest;
+use crate::rest::paginate::klines_range;
 use async_trait::async_trait;
 
-
+// 行情接口, 不需要签名
 #[async_trait]
-pub trait ExchangeAPI {
-    async fn order(
+pub trait MarketDataAPI: Send + Sync {
+    async fn ping(&self) -> Result<()>;
+
+    async fn get_symbols(&self) -> Result<Vec<SymbolInfo>>;
+
+    async fn get_ticker(&self, symbol: &str) -> Result<Ticker>;
+
+    async fn get_order_book(&self, symbol: &str, limit: Option<u64>) -> Result<OrderBook>;
+
+    // 最近的逐笔成交, 按时间升序
+    async fn get_trades(&self, symbol: &str, limit: Option<u64>) -> Result<Vec<Trade>>;
+
+    async fn get_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<KData>>;
+
+    // 单次请求K线的最大数量
+    fn kline_page_limit(&self) -> u64 {
+        1000
+    }
+
+    // [start_time, end_time) 内的全部K线, 超过单次上限时自动分页
+    async fn get_klines_range(
         &self,
         symbol: &str,
-        side: &str,
-        type_: &str,
-        quantity: f64,
-        price: Option<f64>,
-        time_in_force: &str,
-        recv_window: u64,
-        new_client_order_id: Option<&str>,
-        timestamp: Option<u64>,
-    ) -> Result<OrderResp>;
+        interval: &str,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<KData>> {
+        klines_range(start_time, end_time, self.kline_page_limit(), |start, end, limit| {
+            self.get_klines(symbol, interval, Some(start), Some(end), Some(limit))
+        })
+        .await
+    }
+}
+
+// 交易接口, order_id 与 client_order_id 至少指定一个
+#[async_trait]
+pub trait TradingAPI: Send + Sync {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderResp>;
 
     async fn cancel_order(
         &self,
         symbol: &str,
         order_id: Option<u64>,
         client_order_id: Option<&str>,
-    ) -> anyhow::Result<OrderResp>;
+    ) -> Result<Order>;
+
+    async fn query_order(
+        &self,
+        symbol: &str,
+        order_id: Option<u64>,
+        client_order_id: Option<&str>,
+    ) -> Result<Order>;
+}
 
-    async fn query_order(&self, symbol: &str, order_id: Option<u64>, client_order_id: Option<&str>) -> anyhow::Result<OrderResp>;
-    async fn get_ticker(&self, symbol: &str) -> anyhow::Result<Ticker>;
-    async fn get_order_book(&self, symbol: &str, limit: Option<u64>) -> anyhow::Result<OrderBook>;
-    async fn get_klines();
+// 账户接口
+#[async_trait]
+pub trait AccountAPI: Send + Sync {
+    // 只返回非零资产
+    async fn get_balances(&self) -> Result<Vec<Balance>>;
 }
 
+// 所有市场都实现的接口, 可以用 Box<dyn ExchangeAPI> 统一管理
+pub trait ExchangeAPI: MarketDataAPI + TradingAPI + AccountAPI {}
+
+impl<T: MarketDataAPI + TradingAPI + AccountAPI> ExchangeAPI for T {}
+
 #[async_trait]
-pub trait SpotAPI: ExchangeAPI {}
+pub trait SpotAPI: ExchangeAPI {
+    // exchangeInfo 中的限频规则, 用于初始化 RateLimiter
+    async fn get_rate_limits(&self) -> Result<Vec<RateLimit>>;
+}
 
 #[async_trait]
-pub trait PerpetualAPI: ExchangeAPI {}
+pub trait PerpetualAPI: ExchangeAPI {
+    async fn get_open_interest(&self, symbol: &str) -> Result<OpenInterest>;
+}
 
 #[async_trait]
-pub trait DeliveryAPI {}
+pub trait DeliveryAPI: ExchangeAPI {
+    // 单位为张
+    async fn get_open_interest(&self, symbol: &str) -> Result<OpenInterest>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance::perpetual::BinancePerpetual;
+    use crate::binance::spot::BinanceSpot;
+    use crate::rest::stub::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn test_exchanges_as_trait_objects() {
+        let server = StubServer::start(|_| StubResponse::ok("{}")).await;
+        let keys = ("key".to_string(), "secret".to_string());
+        let exchanges: Vec<Box<dyn ExchangeAPI>> = vec![
+            Box::new(BinanceSpot::with_key(server.url(), keys.clone())),
+            Box::new(BinancePerpetual::with_key(server.url(), "wss://127.0.0.1:1".to_string(), keys)),
+        ];
+        for exchange in &exchanges {
+            exchange.ping().await.unwrap();
+        }
+        let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, vec!["/api/v3/ping", "/fapi/v1/ping"]);
+    }
+}
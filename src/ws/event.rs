@@ -0,0 +1,342 @@
+use crate::model::{opt_string_or_float, Asks, Bids, KData, OrderStatus, OrderType, Side, TimeInForce};
+use crate::utils::de2float;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// 行情推送的种类, 与 symbol 组合成 stream 名称, 例如 btcusdt@kline_1m
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StreamKind {
+    AggTrade,
+    Trade,
+    // K线周期, 例如 1m, 1h
+    Kline(String),
+    // 100ms 增量深度
+    Depth100ms,
+    BookTicker,
+    // 合约标记价格, 每3秒推送
+    MarkPrice,
+    // 合约强平订单
+    ForceOrder,
+    MiniTicker,
+}
+
+impl StreamKind {
+    pub fn stream_name(&self, symbol: &str) -> String {
+        format!("{}@{}", symbol.to_lowercase(), self)
+    }
+}
+
+impl fmt::Display for StreamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamKind::AggTrade => f.write_str("aggTrade"),
+            StreamKind::Trade => f.write_str("trade"),
+            StreamKind::Kline(interval) => write!(f, "kline_{}", interval),
+            StreamKind::Depth100ms => f.write_str("depth@100ms"),
+            StreamKind::BookTicker => f.write_str("bookTicker"),
+            StreamKind::MarkPrice => f.write_str("markPrice"),
+            StreamKind::ForceOrder => f.write_str("forceOrder"),
+            StreamKind::MiniTicker => f.write_str("miniTicker"),
+        }
+    }
+}
+
+// 按事件类型字段 e 区分, 现货的 bookTicker 没有 e 字段, 由 stream 名称判断
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "e")]
+pub enum MarketEvent {
+    #[serde(rename = "aggTrade")]
+    AggTrade(AggTradeEvent),
+    #[serde(rename = "trade")]
+    Trade(TradeEvent),
+    #[serde(rename = "kline")]
+    Kline(KlineEvent),
+    #[serde(rename = "depthUpdate")]
+    Depth(DepthEvent),
+    #[serde(rename = "bookTicker")]
+    BookTicker(BookTickerEvent),
+    #[serde(rename = "markPriceUpdate")]
+    MarkPrice(MarkPriceEvent),
+    #[serde(rename = "forceOrder")]
+    ForceOrder(ForceOrderEvent),
+    #[serde(rename = "24hrMiniTicker")]
+    MiniTicker(MiniTickerEvent),
+}
+
+impl MarketEvent {
+    // 组合stream推送的 data 部分
+    pub fn from_stream(stream: &str, data: serde_json::Value) -> serde_json::Result<Self> {
+        if stream.ends_with("@bookTicker") && data.get("e").is_none() {
+            return serde_json::from_value(data).map(MarketEvent::BookTicker);
+        }
+        serde_json::from_value(data)
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            MarketEvent::AggTrade(e) => &e.symbol,
+            MarketEvent::Trade(e) => &e.symbol,
+            MarketEvent::Kline(e) => &e.symbol,
+            MarketEvent::Depth(e) => &e.symbol,
+            MarketEvent::BookTicker(e) => &e.symbol,
+            MarketEvent::MarkPrice(e) => &e.symbol,
+            MarketEvent::ForceOrder(e) => &e.order.symbol,
+            MarketEvent::MiniTicker(e) => &e.symbol,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggTradeEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "a")]
+    pub agg_trade_id: u64,
+    #[serde(rename = "p", with = "de2float")]
+    pub price: f64,
+    #[serde(rename = "q", with = "de2float")]
+    pub qty: f64,
+    #[serde(rename = "f")]
+    pub first_trade_id: u64,
+    #[serde(rename = "l")]
+    pub last_trade_id: u64,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+    // 买方是maker, 即主动卖出
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "t")]
+    pub trade_id: u64,
+    #[serde(rename = "p", with = "de2float")]
+    pub price: f64,
+    #[serde(rename = "q", with = "de2float")]
+    pub qty: f64,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KlineEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "k")]
+    pub kline: WsKline,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsKline {
+    #[serde(rename = "t")]
+    pub start_time: u64,
+    #[serde(rename = "T")]
+    pub close_time: u64,
+    #[serde(rename = "i")]
+    pub interval: String,
+    #[serde(rename = "o", with = "de2float")]
+    pub open: f64,
+    #[serde(rename = "c", with = "de2float")]
+    pub close: f64,
+    #[serde(rename = "h", with = "de2float")]
+    pub high: f64,
+    #[serde(rename = "l", with = "de2float")]
+    pub low: f64,
+    #[serde(rename = "v", with = "de2float")]
+    pub volume: f64,
+    #[serde(rename = "q", with = "de2float")]
+    pub quote_volume: f64,
+    #[serde(rename = "n")]
+    pub trades: u64,
+    // 这根K线是否已收盘
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+}
+
+impl From<&WsKline> for KData {
+    fn from(k: &WsKline) -> Self {
+        KData {
+            ts: k.start_time,
+            open: k.open,
+            high: k.high,
+            low: k.low,
+            close: k.close,
+            vol: k.volume,
+            turnover: k.quote_volume,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "U")]
+    pub first_update_id: i64,
+    #[serde(rename = "u")]
+    pub final_update_id: i64,
+    // 合约才有, 上一条推送的 u
+    #[serde(rename = "pu", default)]
+    pub prev_final_update_id: Option<i64>,
+    #[serde(rename = "b")]
+    pub bids: Vec<Bids>,
+    #[serde(rename = "a")]
+    pub asks: Vec<Asks>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookTickerEvent {
+    #[serde(rename = "u")]
+    pub update_id: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "b", with = "de2float")]
+    pub bid_price: f64,
+    #[serde(rename = "B", with = "de2float")]
+    pub bid_qty: f64,
+    #[serde(rename = "a", with = "de2float")]
+    pub ask_price: f64,
+    #[serde(rename = "A", with = "de2float")]
+    pub ask_qty: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkPriceEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "p", with = "de2float")]
+    pub mark_price: f64,
+    #[serde(rename = "i", with = "de2float")]
+    pub index_price: f64,
+    #[serde(rename = "P", with = "de2float")]
+    pub estimated_settle_price: f64,
+    // 交割合约为空字符串
+    #[serde(rename = "r", with = "opt_string_or_float")]
+    pub funding_rate: Option<f64>,
+    #[serde(rename = "T")]
+    pub next_funding_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForceOrderEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "o")]
+    pub order: LiquidationOrder,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiquidationOrder {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "S")]
+    pub side: Side,
+    #[serde(rename = "o")]
+    pub order_type: OrderType,
+    #[serde(rename = "f")]
+    pub time_in_force: TimeInForce,
+    #[serde(rename = "q", with = "de2float")]
+    pub orig_qty: f64,
+    #[serde(rename = "p", with = "de2float")]
+    pub price: f64,
+    #[serde(rename = "ap", with = "de2float")]
+    pub avg_price: f64,
+    #[serde(rename = "X")]
+    pub status: OrderStatus,
+    #[serde(rename = "l", with = "de2float")]
+    pub last_filled_qty: f64,
+    #[serde(rename = "z", with = "de2float")]
+    pub filled_qty: f64,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MiniTickerEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c", with = "de2float")]
+    pub close: f64,
+    #[serde(rename = "o", with = "de2float")]
+    pub open: f64,
+    #[serde(rename = "h", with = "de2float")]
+    pub high: f64,
+    #[serde(rename = "l", with = "de2float")]
+    pub low: f64,
+    #[serde(rename = "v", with = "de2float")]
+    pub volume: f64,
+    #[serde(rename = "q", with = "de2float")]
+    pub quote_volume: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stream: &str, data: &str) -> MarketEvent {
+        MarketEvent::from_stream(stream, serde_json::from_str(data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_stream_names() {
+        assert_eq!(StreamKind::Kline("1m".to_string()).stream_name("BTCUSDT"), "btcusdt@kline_1m");
+        assert_eq!(StreamKind::Depth100ms.stream_name("ETHUSDT"), "ethusdt@depth@100ms");
+        assert_eq!(StreamKind::AggTrade.stream_name("BNBUSDT"), "bnbusdt@aggTrade");
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let ev = parse("btcusdt@kline_1m", r#"{"e":"kline","E":123456789,"s":"BTCUSDT","k":{"t":123400000,"T":123460000,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"0.0010","c":"0.0020","h":"0.0025","l":"0.0015","v":"1000","n":100,"x":false,"q":"1.0000","V":"500","Q":"0.500","B":"123456"}}"#);
+        match ev {
+            MarketEvent::Kline(ref k) => {
+                assert_eq!(k.kline.interval, "1m");
+                assert_eq!(KData::from(&k.kline).close, 0.002);
+            }
+            _ => panic!("unexpected {:?}", ev),
+        }
+
+        let ev = parse("btcusdt@depth@100ms", r#"{"e":"depthUpdate","E":123456789,"T":123456788,"s":"BTCUSDT","U":157,"u":160,"pu":149,"b":[["0.0024","10"]],"a":[["0.0026","100"]]}"#);
+        match ev {
+            MarketEvent::Depth(ref d) => {
+                assert_eq!(d.prev_final_update_id, Some(149));
+                assert_eq!(d.asks[0].qty, 100.);
+            }
+            _ => panic!("unexpected {:?}", ev),
+        }
+
+        let ev = parse("btcusdt@markPrice", r#"{"e":"markPriceUpdate","E":1562305380000,"s":"BTCUSDT","p":"11794.15000000","i":"11784.62659091","P":"11784.25641265","r":"0.00038167","T":1562306400000}"#);
+        assert!(matches!(ev, MarketEvent::MarkPrice(ref m) if m.funding_rate == Some(0.00038167)));
+
+        let ev = parse("btcusdt@forceOrder", r#"{"e":"forceOrder","E":1568014460893,"o":{"s":"BTCUSDT","S":"SELL","o":"LIMIT","f":"IOC","q":"0.014","p":"9910","ap":"9910","X":"FILLED","l":"0.014","z":"0.014","T":1568014460893}}"#);
+        assert_eq!(ev.symbol(), "BTCUSDT");
+        assert!(matches!(ev, MarketEvent::ForceOrder(ref f) if f.order.side == Side::Sell));
+
+        let ev = parse("bnbusdt@miniTicker", r#"{"e":"24hrMiniTicker","E":123456789,"s":"BNBUSDT","c":"0.0025","o":"0.0010","h":"0.0025","l":"0.0010","v":"10000","q":"18"}"#);
+        assert!(matches!(ev, MarketEvent::MiniTicker(ref t) if t.quote_volume == 18.));
+    }
+
+    #[test]
+    fn test_spot_book_ticker_without_event_type() {
+        let ev = parse("bnbusdt@bookTicker", r#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#);
+        assert!(matches!(ev, MarketEvent::BookTicker(ref b) if b.ask_qty == 40.66));
+        let ev = parse("btcusdt@bookTicker", r#"{"e":"bookTicker","u":400900217,"E":1568014460893,"T":1568014460891,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#);
+        assert!(matches!(ev, MarketEvent::BookTicker(ref b) if b.update_id == 400900217));
+    }
+}
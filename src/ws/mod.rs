@@ -1 +1,3 @@
-pub(crate) mod wclient;
\ No newline at end of file
+pub mod event;
+pub mod user;
+pub mod wclient;
@@ -0,0 +1,652 @@
+use crate::error::Result;
+use crate::model::{opt_string_or_float, ExecutionType, OrderStatus, OrderType, PositionSide, Side, TimeInForce};
+use crate::rest::rclient::RestClient;
+use crate::utils::de2float;
+use crate::ws::wclient::{StreamEvent, WsStream};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// 创建 listenKey 的接口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenKeyEndpoint {
+    // /api/v3/userDataStream, 延期和关闭需要带 listenKey 参数
+    Spot,
+    // /fapi/v1/listenKey, 每个 API key 只有一个 listenKey
+    UsdFutures,
+}
+
+impl ListenKeyEndpoint {
+    pub fn path(&self) -> &'static str {
+        match self {
+            ListenKeyEndpoint::Spot => "/api/v3/userDataStream",
+            ListenKeyEndpoint::UsdFutures => "/fapi/v1/listenKey",
+        }
+    }
+}
+
+// 按事件类型字段 e 区分, 未识别的事件(如 balanceUpdate)忽略
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "e")]
+pub enum UserEvent {
+    #[serde(rename = "executionReport")]
+    ExecutionReport(ExecutionReport),
+    #[serde(rename = "ORDER_TRADE_UPDATE")]
+    OrderTradeUpdate(OrderTradeUpdateEvent),
+    #[serde(rename = "outboundAccountPosition")]
+    AccountPosition(AccountPositionEvent),
+    #[serde(rename = "ACCOUNT_UPDATE")]
+    AccountUpdate(AccountUpdateEvent),
+    #[serde(rename = "MARGIN_CALL")]
+    MarginCall(MarginCallEvent),
+    #[serde(rename = "ACCOUNT_CONFIG_UPDATE")]
+    AccountConfigUpdate(AccountConfigUpdateEvent),
+    // 收到后 UserStream 会重新创建 listenKey, 之间的推送可能丢失
+    #[serde(rename = "listenKeyExpired")]
+    ListenKeyExpired(ListenKeyExpiredEvent),
+    #[serde(other)]
+    Other,
+}
+
+impl UserEvent {
+    // 组合stream推送的 data 部分, stream 为 listenKey
+    pub fn from_stream(_stream: &str, data: serde_json::Value) -> serde_json::Result<Self> {
+        serde_json::from_value(data)
+    }
+}
+
+// 现货订单更新
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "S")]
+    pub side: Side,
+    #[serde(rename = "o")]
+    pub order_type: OrderType,
+    #[serde(rename = "f")]
+    pub time_in_force: TimeInForce,
+    #[serde(rename = "q", with = "de2float")]
+    pub qty: f64,
+    #[serde(rename = "p", with = "de2float")]
+    pub price: f64,
+    #[serde(rename = "P", with = "de2float")]
+    pub stop_price: f64,
+    // 撤单时为被撤订单的 clientOrderId, 否则为空
+    #[serde(rename = "C")]
+    pub orig_client_order_id: String,
+    #[serde(rename = "x")]
+    pub execution_type: ExecutionType,
+    #[serde(rename = "X")]
+    pub status: OrderStatus,
+    #[serde(rename = "r")]
+    pub reject_reason: String,
+    #[serde(rename = "i")]
+    pub order_id: u64,
+    #[serde(rename = "l", with = "de2float")]
+    pub last_filled_qty: f64,
+    #[serde(rename = "z", with = "de2float")]
+    pub cum_filled_qty: f64,
+    #[serde(rename = "L", with = "de2float")]
+    pub last_filled_price: f64,
+    #[serde(rename = "n", with = "de2float")]
+    pub commission: f64,
+    #[serde(rename = "N")]
+    pub commission_asset: Option<String>,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    // 没有成交时为 -1
+    #[serde(rename = "t")]
+    pub trade_id: i64,
+    #[serde(rename = "m")]
+    pub is_maker: bool,
+    #[serde(rename = "Z", with = "de2float")]
+    pub cum_quote_qty: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderTradeUpdateEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "o")]
+    pub order: OrderUpdate,
+}
+
+// 合约订单更新
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "S")]
+    pub side: Side,
+    #[serde(rename = "o")]
+    pub order_type: OrderType,
+    #[serde(rename = "f")]
+    pub time_in_force: TimeInForce,
+    #[serde(rename = "q", with = "de2float")]
+    pub qty: f64,
+    #[serde(rename = "p", with = "de2float")]
+    pub price: f64,
+    #[serde(rename = "ap", with = "de2float")]
+    pub avg_price: f64,
+    #[serde(rename = "sp", with = "de2float")]
+    pub stop_price: f64,
+    #[serde(rename = "x")]
+    pub execution_type: ExecutionType,
+    #[serde(rename = "X")]
+    pub status: OrderStatus,
+    #[serde(rename = "i")]
+    pub order_id: u64,
+    #[serde(rename = "l", with = "de2float")]
+    pub last_filled_qty: f64,
+    #[serde(rename = "z", with = "de2float")]
+    pub cum_filled_qty: f64,
+    #[serde(rename = "L", with = "de2float")]
+    pub last_filled_price: f64,
+    // 没有成交时不推送手续费
+    #[serde(rename = "n", with = "opt_string_or_float", default)]
+    pub commission: Option<f64>,
+    #[serde(rename = "N", default)]
+    pub commission_asset: Option<String>,
+    #[serde(rename = "T")]
+    pub trade_time: i64,
+    #[serde(rename = "t")]
+    pub trade_id: i64,
+    #[serde(rename = "m")]
+    pub is_maker: bool,
+    #[serde(rename = "R")]
+    pub reduce_only: bool,
+    #[serde(rename = "ps")]
+    pub position_side: PositionSide,
+    #[serde(rename = "rp", with = "de2float")]
+    pub realized_profit: f64,
+}
+
+// 现货账户余额变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountPositionEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "u")]
+    pub last_update_time: i64,
+    #[serde(rename = "B")]
+    pub balances: Vec<WsBalance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsBalance {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "f", with = "de2float")]
+    pub free: f64,
+    #[serde(rename = "l", with = "de2float")]
+    pub locked: f64,
+}
+
+// 合约余额和持仓变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountUpdateEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "a")]
+    pub update: AccountUpdate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountUpdate {
+    // 变化原因, 如 ORDER, FUNDING_FEE, DEPOSIT
+    #[serde(rename = "m")]
+    pub reason: String,
+    #[serde(rename = "B")]
+    pub balances: Vec<BalanceUpdate>,
+    #[serde(rename = "P")]
+    pub positions: Vec<PositionUpdate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceUpdate {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "wb", with = "de2float")]
+    pub wallet_balance: f64,
+    #[serde(rename = "cw", with = "de2float")]
+    pub cross_wallet_balance: f64,
+    // 除盈亏和手续费以外的变化量
+    #[serde(rename = "bc", with = "de2float")]
+    pub balance_change: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "pa", with = "de2float")]
+    pub position_amt: f64,
+    #[serde(rename = "ep", with = "de2float")]
+    pub entry_price: f64,
+    // 累计已实现盈亏
+    #[serde(rename = "cr", with = "de2float")]
+    pub accumulated_realized: f64,
+    #[serde(rename = "up", with = "de2float")]
+    pub unrealized_pnl: f64,
+    // isolated 或 cross
+    #[serde(rename = "mt")]
+    pub margin_type: String,
+    #[serde(rename = "iw", with = "de2float")]
+    pub isolated_wallet: f64,
+    #[serde(rename = "ps")]
+    pub position_side: PositionSide,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarginCallEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    // 仅全仓推送
+    #[serde(rename = "cw", with = "opt_string_or_float", default)]
+    pub cross_wallet_balance: Option<f64>,
+    #[serde(rename = "p")]
+    pub positions: Vec<MarginCallPosition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarginCallPosition {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "ps")]
+    pub position_side: PositionSide,
+    #[serde(rename = "pa", with = "de2float")]
+    pub position_amt: f64,
+    #[serde(rename = "mt")]
+    pub margin_type: String,
+    #[serde(rename = "iw", with = "de2float")]
+    pub isolated_wallet: f64,
+    #[serde(rename = "mp", with = "de2float")]
+    pub mark_price: f64,
+    #[serde(rename = "up", with = "de2float")]
+    pub unrealized_pnl: f64,
+    // 维持保证金
+    #[serde(rename = "mm", with = "de2float")]
+    pub maint_margin: f64,
+}
+
+// 杠杆倍数或联合保证金模式变化, 两者只会出现一个
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountConfigUpdateEvent {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "ac", default)]
+    pub leverage: Option<LeverageUpdate>,
+    #[serde(rename = "ai", default)]
+    pub asset_mode: Option<AssetModeUpdate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeverageUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "l")]
+    pub leverage: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetModeUpdate {
+    #[serde(rename = "j")]
+    pub multi_assets: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListenKeyExpiredEvent {
+    #[serde(rename = "listenKey", default)]
+    pub listen_key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenKeyResp {
+    listen_key: String,
+}
+
+// 用户数据流, 断线重连沿用 WsStream, listenKey 由后台任务维护:
+// 每 WsConfig::listen_key_keepalive 延期一次, 过期或延期失败时重新创建并切换订阅
+// drop 后关闭 listenKey
+pub struct UserStream {
+    event_rx: mpsc::UnboundedReceiver<Result<StreamEvent<UserEvent>>>,
+}
+
+impl UserStream {
+    pub(crate) async fn start(
+        ws: WsStream<UserEvent>,
+        rest_client: RestClient,
+        endpoint: ListenKeyEndpoint,
+        keepalive: Duration,
+    ) -> Result<Self> {
+        let keeper = ListenKeyKeeper {
+            rest_client,
+            endpoint,
+            listen_key: String::new(),
+        };
+        let listen_key = keeper.create().await?;
+        ws.subscribe_streams(vec![listen_key.clone()]).await?;
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(ListenKeyKeeper { listen_key, ..keeper }.run(ws, event_tx, keepalive));
+        Ok(Self { event_rx })
+    }
+}
+
+impl Stream for UserStream {
+    type Item = Result<StreamEvent<UserEvent>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.event_rx.poll_recv(cx)
+    }
+}
+
+struct ListenKeyKeeper {
+    rest_client: RestClient,
+    endpoint: ListenKeyEndpoint,
+    listen_key: String,
+}
+
+impl ListenKeyKeeper {
+    fn url(&self) -> Result<String> {
+        let mut params: BTreeMap<String, String> = BTreeMap::new();
+        if self.endpoint == ListenKeyEndpoint::Spot && !self.listen_key.is_empty() {
+            params.insert("listenKey".into(), self.listen_key.clone());
+        }
+        self.rest_client.build_request_string(self.endpoint.path(), params, false)
+    }
+
+    async fn create(&self) -> Result<String> {
+        let url = self.rest_client.build_request_string(self.endpoint.path(), BTreeMap::new(), false)?;
+        let resp = self.rest_client.post_key(url).await?;
+        let resp: ListenKeyResp = serde_json::from_str(&resp)?;
+        Ok(resp.listen_key)
+    }
+
+    async fn keepalive(&self) -> Result<()> {
+        self.rest_client.put_key(self.url()?).await.map(|_| ())
+    }
+
+    async fn close(&self) {
+        let result = match self.url() {
+            Ok(url) => self.rest_client.delete_key(url).await.map(|_| ()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            log::warn!("close listenKey failed: {}", err);
+        }
+    }
+
+    // 创建新的 listenKey 并把订阅切换过去, 合约在旧 key 未过期时会返回同一个 key
+    async fn renew(&mut self, ws: &WsStream<UserEvent>) -> Result<()> {
+        let listen_key = self.create().await?;
+        if listen_key == self.listen_key {
+            return Ok(());
+        }
+        ws.subscribe_streams(vec![listen_key.clone()]).await?;
+        let expired = std::mem::replace(&mut self.listen_key, listen_key);
+        ws.unsubscribe_streams(vec![expired]).await
+    }
+
+    async fn run(
+        mut self,
+        mut ws: WsStream<UserEvent>,
+        event_tx: mpsc::UnboundedSender<Result<StreamEvent<UserEvent>>>,
+        keepalive: Duration,
+    ) {
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + keepalive, keepalive);
+        loop {
+            let renew = tokio::select! {
+                _ = event_tx.closed() => break,
+                _ = ticker.tick() => match self.keepalive().await {
+                    Ok(()) => false,
+                    Err(err) => {
+                        log::warn!("keepalive listenKey failed: {}", err);
+                        true
+                    }
+                },
+                event = ws.next() => {
+                    let event = match event {
+                        Some(event) => event,
+                        None => break,
+                    };
+                    let renew = matches!(event, Ok(StreamEvent::Data(UserEvent::ListenKeyExpired(_))));
+                    // 断线期间 listenKey 可能已过期, 重连后立即延期确认
+                    let reconnected = matches!(event, Ok(StreamEvent::Resubscribed(_)));
+                    if event_tx.send(event).is_err() {
+                        break;
+                    }
+                    renew || (reconnected && self.keepalive().await.is_err())
+                }
+            };
+            if renew {
+                if let Err(err) = self.renew(&ws).await {
+                    log::warn!("renew listenKey failed: {}", err);
+                    let _ = event_tx.send(Err(err));
+                }
+            }
+        }
+        self.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::stub::{StubResponse, StubServer};
+    use crate::ws::wclient::{WsConfig, WssClient};
+    use async_tungstenite::tokio::accept_async;
+    use async_tungstenite::tungstenite::Message;
+    use futures::SinkExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    const EXECUTION_REPORT: &str = r#"{"e":"executionReport","E":1499405658658,"s":"ETHBTC","c":"mUvoqJxFIILMdfAW5iGSOW","S":"BUY","o":"LIMIT","f":"GTC","q":"1.00000000","p":"0.10264410","P":"0.00000000","F":"0.00000000","g":-1,"C":"","x":"TRADE","X":"PARTIALLY_FILLED","r":"NONE","i":4293153,"l":"0.40000000","z":"0.40000000","L":"0.10264410","n":"0.00040000","N":"ETH","T":1499405658657,"t":1234,"I":8641984,"w":false,"m":true,"M":false,"O":1499405658657,"Z":"0.04105764","Y":"0.04105764","Q":"0.00000000"}"#;
+    const ORDER_TRADE_UPDATE: &str = r#"{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{"s":"BTCUSDT","c":"TEST","S":"SELL","o":"TRAILING_STOP_MARKET","f":"GTC","q":"0.001","p":"0","ap":"0","sp":"7103.04","x":"NEW","X":"NEW","i":8886774,"l":"0","z":"0","L":"0","T":1568879465650,"t":0,"b":"0","a":"9.91","m":false,"R":false,"wt":"CONTRACT_PRICE","ot":"TRAILING_STOP_MARKET","ps":"LONG","cp":false,"AP":"7476.89","cr":"5.0","rp":"0"}}"#;
+    const ACCOUNT_POSITION: &str = r#"{"e":"outboundAccountPosition","E":1564034571105,"u":1564034571073,"B":[{"a":"ETH","f":"10000.000000","l":"0.000000"}]}"#;
+    const ACCOUNT_UPDATE: &str = r#"{"e":"ACCOUNT_UPDATE","E":1564745798939,"T":1564745798938,"a":{"m":"ORDER","B":[{"a":"USDT","wb":"122624.12345678","cw":"100.12345678","bc":"50.12345678"}],"P":[{"s":"BTCUSDT","pa":"-20","ep":"6563.66500","cr":"0","up":"2850.21200","mt":"isolated","iw":"13200.70726908","ps":"SHORT"}]}}"#;
+    const MARGIN_CALL: &str = r#"{"e":"MARGIN_CALL","E":1587727187525,"cw":"3.16812045","p":[{"s":"ETHUSDT","ps":"LONG","pa":"1.327","mt":"CROSSED","iw":"0","mp":"187.17127","up":"-1.166074","mm":"1.614445"}]}"#;
+    const ACCOUNT_CONFIG_UPDATE: &str = r#"{"e":"ACCOUNT_CONFIG_UPDATE","E":1611646737479,"T":1611646737476,"ac":{"s":"BTCUSDT","l":25}}"#;
+    const LISTEN_KEY_EXPIRED: &str = r#"{"e":"listenKeyExpired","E":1576653824250,"listenKey":"key-1"}"#;
+
+    #[test]
+    fn test_parse_user_events() {
+        match serde_json::from_str(EXECUTION_REPORT).unwrap() {
+            UserEvent::ExecutionReport(r) => {
+                assert_eq!(r.execution_type, ExecutionType::Trade);
+                assert_eq!(r.status, OrderStatus::PartiallyFilled);
+                assert_eq!((r.order_id, r.last_filled_qty, r.cum_quote_qty), (4293153, 0.4, 0.04105764));
+                assert_eq!(r.commission_asset.as_deref(), Some("ETH"));
+            }
+            ev => panic!("unexpected {:?}", ev),
+        }
+        match serde_json::from_str(ORDER_TRADE_UPDATE).unwrap() {
+            UserEvent::OrderTradeUpdate(u) => {
+                assert_eq!(u.order.order_type, OrderType::TrailingStopMarket);
+                assert_eq!(u.order.position_side, PositionSide::Long);
+                assert_eq!(u.order.stop_price, 7103.04);
+                assert_eq!(u.order.commission, None);
+            }
+            ev => panic!("unexpected {:?}", ev),
+        }
+        match serde_json::from_str(ACCOUNT_POSITION).unwrap() {
+            UserEvent::AccountPosition(p) => assert_eq!(p.balances[0].free, 10000.),
+            ev => panic!("unexpected {:?}", ev),
+        }
+        match serde_json::from_str(ACCOUNT_UPDATE).unwrap() {
+            UserEvent::AccountUpdate(u) => {
+                assert_eq!(u.update.reason, "ORDER");
+                assert_eq!(u.update.balances[0].balance_change, 50.12345678);
+                assert_eq!(u.update.positions[0].position_amt, -20.);
+                assert_eq!(u.update.positions[0].position_side, PositionSide::Short);
+            }
+            ev => panic!("unexpected {:?}", ev),
+        }
+        match serde_json::from_str(MARGIN_CALL).unwrap() {
+            UserEvent::MarginCall(m) => {
+                assert_eq!(m.cross_wallet_balance, Some(3.16812045));
+                assert_eq!(m.positions[0].maint_margin, 1.614445);
+            }
+            ev => panic!("unexpected {:?}", ev),
+        }
+        match serde_json::from_str(ACCOUNT_CONFIG_UPDATE).unwrap() {
+            UserEvent::AccountConfigUpdate(c) => {
+                assert_eq!(c.leverage.unwrap().leverage, 25);
+                assert!(c.asset_mode.is_none());
+            }
+            ev => panic!("unexpected {:?}", ev),
+        }
+        let ev: UserEvent = serde_json::from_str(r#"{"e":"ACCOUNT_CONFIG_UPDATE","E":1,"T":1,"ai":{"j":true}}"#).unwrap();
+        assert!(matches!(ev, UserEvent::AccountConfigUpdate(c) if c.asset_mode.as_ref().unwrap().multi_assets));
+        let ev: UserEvent = serde_json::from_str(r#"{"e":"balanceUpdate","E":1,"a":"BTC","d":"1"}"#).unwrap();
+        assert_eq!(ev, UserEvent::Other);
+    }
+
+    // 本地ws服务: 订阅 key-1 后推送 executionReport 和 listenKeyExpired, 订阅其它 key 推送 outboundAccountPosition
+    async fn start_ws_server() -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (req_tx, req_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let req_tx = req_tx.clone();
+                tokio::spawn(async move {
+                    let mut ws = accept_async(socket).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let resp = serde_json::json!({"result": null, "id": req["id"]});
+                        ws.send(Message::Text(resp.to_string())).await.unwrap();
+                        let key = req["params"][0].as_str().unwrap().to_string();
+                        let pushes = match (req["method"].as_str().unwrap(), key.as_str()) {
+                            ("SUBSCRIBE", "key-1") => vec![EXECUTION_REPORT, LISTEN_KEY_EXPIRED],
+                            ("SUBSCRIBE", _) => vec![ACCOUNT_POSITION],
+                            _ => vec![],
+                        };
+                        let _ = req_tx.send(req);
+                        for data in pushes {
+                            let frame = format!(r#"{{"stream":"{}","data":{}}}"#, key, data);
+                            ws.send(Message::Text(frame)).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        (format!("ws://{}", addr), req_rx)
+    }
+
+    // 依次返回 key-1, key-2, ...; fail_keepalive 次数内的延期请求返回 -1125
+    async fn start_rest_server(fail_keepalive: usize) -> StubServer {
+        let created = AtomicUsize::new(0);
+        let keepalive = AtomicUsize::new(0);
+        StubServer::start(move |req| match req.method.as_str() {
+            "POST" => {
+                let n = created.fetch_add(1, Ordering::SeqCst) + 1;
+                StubResponse::ok(&format!(r#"{{"listenKey":"key-{}"}}"#, n))
+            }
+            "PUT" if keepalive.fetch_add(1, Ordering::SeqCst) < fail_keepalive => {
+                StubResponse::with_status(400, r#"{"code":-1125,"msg":"This listenKey does not exist."}"#)
+            }
+            _ => StubResponse::ok("{}"),
+        })
+        .await
+    }
+
+    fn rest_client(server: &StubServer) -> RestClient {
+        RestClient::with_key(server.url(), ("key".to_string(), "secret".to_string()))
+    }
+
+    async fn next_event(stream: &mut UserStream) -> StreamEvent<UserEvent> {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("no event within 5s")
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn wait_for<F: Fn() -> bool>(cond: F) {
+        for _ in 0..200 {
+            if cond() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not met within 2s");
+    }
+
+    #[tokio::test]
+    async fn test_renew_listen_key_on_expired() {
+        let server = start_rest_server(0).await;
+        let (ws_url, mut ws_requests) = start_ws_server().await;
+        let mut stream = WssClient::new(ws_url)
+            .user_stream(rest_client(&server), ListenKeyEndpoint::Spot)
+            .await
+            .unwrap();
+
+        assert_eq!(next_event(&mut stream).await, StreamEvent::Connected);
+        assert!(matches!(next_event(&mut stream).await, StreamEvent::Data(UserEvent::ExecutionReport(_))));
+        assert!(matches!(next_event(&mut stream).await, StreamEvent::Data(UserEvent::ListenKeyExpired(_))));
+        // 新 key 订阅后才退订旧 key
+        assert!(matches!(next_event(&mut stream).await, StreamEvent::Data(UserEvent::AccountPosition(_))));
+        let mut requests = Vec::new();
+        for _ in 0..3 {
+            let req = ws_requests.recv().await.unwrap();
+            requests.push(format!("{} {}", req["method"].as_str().unwrap(), req["params"][0].as_str().unwrap()));
+        }
+        assert_eq!(requests, vec!["SUBSCRIBE key-1", "SUBSCRIBE key-2", "UNSUBSCRIBE key-1"]);
+
+        let rest = server.requests();
+        assert_eq!(rest.len(), 2);
+        assert!(rest.iter().all(|r| r.method == "POST" && r.path == "/api/v3/userDataStream"));
+        assert!(rest[0].param("signature").is_none());
+
+        // drop 后关闭当前的 listenKey
+        drop(stream);
+        wait_for(|| server.requests().iter().any(|r| r.method == "DELETE")).await;
+        let delete = server.requests().into_iter().find(|r| r.method == "DELETE").unwrap();
+        assert_eq!(delete.param("listenKey").as_deref(), Some("key-2"));
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_and_recreate_when_missing() {
+        let server = start_rest_server(1).await;
+        let (ws_url, mut ws_requests) = start_ws_server().await;
+        let config = WsConfig {
+            listen_key_keepalive: Duration::from_millis(50),
+            ..WsConfig::default()
+        };
+        let _stream = WssClient::new(ws_url)
+            .with_config(config)
+            .user_stream(rest_client(&server), ListenKeyEndpoint::UsdFutures)
+            .await
+            .unwrap();
+
+        // 第一次延期返回 -1125, 重新创建 key-2 并切换订阅
+        let mut switched = Vec::new();
+        while switched.len() < 3 {
+            let req = tokio::time::timeout(Duration::from_secs(5), ws_requests.recv()).await.unwrap().unwrap();
+            switched.push(req["params"][0].as_str().unwrap().to_string());
+        }
+        assert_eq!(switched, vec!["key-1", "key-2", "key-1"]);
+        wait_for(|| server.requests().iter().filter(|r| r.method == "PUT").count() >= 3).await;
+        let put = server.requests().into_iter().find(|r| r.method == "PUT").unwrap();
+        assert_eq!(put.path, "/fapi/v1/listenKey");
+        assert!(put.param("listenKey").is_none());
+    }
+}